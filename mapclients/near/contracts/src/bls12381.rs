@@ -0,0 +1,371 @@
+//! BLS signatures over BLS12-381 on top of the `bls12381_*` host functions, with signatures
+//! in G1 and public keys in G2 like the bn254 scheme MAP uses today.
+
+use crate::crypto::REGISTER_EXPECTED_ERR;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::env;
+use near_sdk::serde::{Deserialize, Serialize};
+use num::Integer as _;
+use num_bigint::{BigInt as Integer, Sign};
+
+/// BLS12381_G1_LENGTH is the number of bytes of an uncompressed G1 point
+pub const BLS12381_G1_LENGTH: usize = 96;
+
+/// BLS12381_G2_LENGTH is the number of bytes of an uncompressed G2 point
+pub const BLS12381_G2_LENGTH: usize = 192;
+
+/// Domain separation tag of `hash_to_g1`: minimal-signature-size BLS with proof of possession.
+pub const BLS12381_DST: &[u8] = b"BLS_SIG_BLS12381G1_XMD:SHA-256_SSWU_RO_POP_";
+
+/// Domain separation tag of the proof of possession of a public key.
+pub const BLS12381_POP_DST: &[u8] = b"BLS_POP_BLS12381G1_XMD:SHA-256_SSWU_RO_POP_";
+
+const FP_LENGTH: usize = 48;
+const BLS12381_REGISTER: u64 = 3;
+
+const MODULUS: &str = "1a0111ea397fe69a4b1ba7b6434bacd764774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaaab";
+
+/// `x.c1 || x.c0 || y.c1 || y.c0` of the generator of G2
+const G2_GENERATOR: &str = "13e02b6052719f607dacd3a088274f65596bd0d09920b61ab5da61bbdc7f5049334cf11213945d57e5ac7d055d042b7e024aa2b2f08f0a91260805272dc51051c6e47ad4fa403b02b4510b647ae3d1770bac0326a805bbefd48056c8c121bdb80606c4a02ea734cc32acd2b02bc28b99cb3e287e85a763af267492ab572e99ab3f370d275cec1da1aaa9075ff05f79be0ce5d527727d6e118cc9cdc6da2e351aadfd9baa8cbdd3a76d429a695160d12c923ac9cc3baca289e193548608b82801";
+
+/// Uncompressed G1 point in the ZCash serialization used by the host functions.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Bls12381G1(#[serde(with = "crate::serialization::bytes::hexstring")] pub Vec<u8>);
+
+/// Uncompressed G2 point in the ZCash serialization used by the host functions.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Bls12381G2(#[serde(with = "crate::serialization::bytes::hexstring")] pub Vec<u8>);
+
+/// Sums the public keys.
+pub fn aggregate_g2(keys: &[Bls12381G2]) -> Bls12381G2 {
+    let buf: Vec<u8> = keys
+        .iter()
+        .flat_map(|key| [&[0u8][..], key.0.as_slice()].concat())
+        .collect();
+    assert!(!buf.is_empty(), "no g2 point to sum");
+
+    let res = unsafe { near_sys::bls12381_p2_sum(buf.len() as _, buf.as_ptr() as _, BLS12381_REGISTER) };
+    assert_eq!(0, res, "invalid bls12381 g2 point");
+
+    Bls12381G2(env::read_register(BLS12381_REGISTER).expect(REGISTER_EXPECTED_ERR))
+}
+
+/// Hashes the message to G1 as the `BLS12381G1_XMD:SHA-256_SSWU_RO_` suite of RFC 9380.
+pub fn hash_to_g1(msg: &[u8], dst: &[u8]) -> Bls12381G1 {
+    let buf = hash_to_field(msg, dst).concat();
+    let res = unsafe { near_sys::bls12381_map_fp_to_g1(buf.len() as _, buf.as_ptr() as _, BLS12381_REGISTER) };
+    assert_eq!(0, res, "failed to map field elements to g1");
+
+    let points = env::read_register(BLS12381_REGISTER).expect(REGISTER_EXPECTED_ERR);
+    let (p0, p1) = points.split_at(BLS12381_G1_LENGTH);
+    Bls12381G1(sum_g1(&[(false, p0), (false, p1)]))
+}
+
+/// Checks `e(sig, g2) == e(H(msg), pk)`.
+pub fn verify_signature(sig: &Bls12381G1, msg: &[u8], pk: &Bls12381G2) -> bool {
+    verify(sig, msg, BLS12381_DST, pk)
+}
+
+/// Checks the proof of possession, i.e. the signature of the uncompressed public key by itself.
+pub fn verify_possession(pop: &Bls12381G1, pk: &Bls12381G2) -> bool {
+    verify(pop, &pk.0, BLS12381_POP_DST, pk)
+}
+
+fn verify(sig: &Bls12381G1, msg: &[u8], dst: &[u8], pk: &Bls12381G2) -> bool {
+    assert_eq!(BLS12381_G1_LENGTH, sig.0.len(), "invalid bls12381 signature length");
+    assert_eq!(BLS12381_G2_LENGTH, pk.0.len(), "invalid bls12381 public key length");
+
+    let hash = hash_to_g1(msg, dst);
+    let neg_hash = sum_g1(&[(true, hash.0.as_slice())]);
+    let buf = [
+        sig.0.as_slice(),
+        &hex::decode(G2_GENERATOR).unwrap(),
+        neg_hash.as_slice(),
+        pk.0.as_slice(),
+    ]
+    .concat();
+
+    let res = unsafe { near_sys::bls12381_pairing_check(buf.len() as _, buf.as_ptr() as _) };
+    res == 0
+}
+
+/// Sums G1 points, each negated if its flag is set.
+fn sum_g1(points: &[(bool, &[u8])]) -> Vec<u8> {
+    let buf: Vec<u8> = points
+        .iter()
+        .flat_map(|(neg, point)| [&[*neg as u8][..], point].concat())
+        .collect();
+
+    let res = unsafe { near_sys::bls12381_p1_sum(buf.len() as _, buf.as_ptr() as _, BLS12381_REGISTER) };
+    assert_eq!(0, res, "invalid bls12381 g1 point");
+
+    env::read_register(BLS12381_REGISTER).expect(REGISTER_EXPECTED_ERR)
+}
+
+/// Two field elements, 48 bytes big endian, derived from `expand_message_xmd` (RFC 9380 5.2).
+fn hash_to_field(msg: &[u8], dst: &[u8]) -> [[u8; FP_LENGTH]; 2] {
+    let modulus = Integer::parse_bytes(MODULUS.as_bytes(), 16).unwrap();
+    let uniform = expand_message_xmd(msg, dst, 128);
+
+    let mut res = [[0u8; FP_LENGTH]; 2];
+    for (i, chunk) in uniform.chunks(64).enumerate() {
+        let e = Integer::from_bytes_be(Sign::Plus, chunk).mod_floor(&modulus);
+        let bytes = e.to_bytes_be().1;
+        res[i][FP_LENGTH - bytes.len()..].copy_from_slice(&bytes);
+    }
+
+    res
+}
+
+/// `expand_message_xmd` with SHA-256 (RFC 9380 5.3.1).
+fn expand_message_xmd(msg: &[u8], dst: &[u8], len: usize) -> Vec<u8> {
+    assert!(dst.len() <= 255, "dst is too long");
+    let ell = len.div_ceil(32);
+    assert!(ell <= 255, "requested length is too long");
+
+    let dst_prime = [dst, &[dst.len() as u8]].concat();
+    let b0 = env::sha256(&[&[0u8; 64][..], msg, &(len as u16).to_be_bytes(), &[0], &dst_prime].concat());
+
+    let mut bi = env::sha256(&[&b0[..], &[1], &dst_prime].concat());
+    let mut uniform = bi.clone();
+    for i in 2..=ell {
+        let xor: Vec<u8> = b0.iter().zip(bi.iter()).map(|(a, b)| a ^ b).collect();
+        bi = env::sha256(&[&xor[..], &[i as u8], &dst_prime].concat());
+        uniform.extend_from_slice(&bi);
+    }
+    uniform.truncate(len);
+
+    uniform
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::serde_json;
+
+    /// The `bls12381_*` host functions are not mocked by near-sdk, they are implemented on top
+    /// of blst for the tests.
+    mod host {
+        use blst::*;
+        use near_sdk::env;
+
+        /// Writes the value to the register through the storage, as the mocked blockchain
+        /// has no other way to do it.
+        fn write_register(register_id: u64, value: &[u8]) {
+            let key = b"bls12381_register";
+            env::storage_write(key, value);
+            unsafe { near_sys::storage_read(key.len() as _, key.as_ptr() as _, register_id) };
+            env::storage_remove(key);
+        }
+
+        fn input<'a>(value_len: u64, value_ptr: u64) -> &'a [u8] {
+            unsafe { std::slice::from_raw_parts(value_ptr as *const u8, value_len as usize) }
+        }
+
+        #[no_mangle]
+        extern "C" fn bls12381_p1_sum(value_len: u64, value_ptr: u64, register_id: u64) -> u64 {
+            let mut sum = blst_p1::default();
+            for item in input(value_len, value_ptr).chunks(97) {
+                let mut affine = blst_p1_affine::default();
+                let mut point = blst_p1::default();
+                let prev = sum;
+                unsafe {
+                    if blst_p1_deserialize(&mut affine, item[1..].as_ptr()) != BLST_ERROR::BLST_SUCCESS {
+                        return 1;
+                    }
+                    blst_p1_from_affine(&mut point, &affine);
+                    blst_p1_cneg(&mut point, item[0] == 1);
+                    blst_p1_add_or_double(&mut sum, &prev, &point);
+                }
+            }
+
+            let mut out = [0u8; 96];
+            unsafe { blst_p1_serialize(out.as_mut_ptr(), &sum) };
+            write_register(register_id, &out);
+            0
+        }
+
+        #[no_mangle]
+        extern "C" fn bls12381_p2_sum(value_len: u64, value_ptr: u64, register_id: u64) -> u64 {
+            let mut sum = blst_p2::default();
+            for item in input(value_len, value_ptr).chunks(193) {
+                let mut affine = blst_p2_affine::default();
+                let mut point = blst_p2::default();
+                let prev = sum;
+                unsafe {
+                    if blst_p2_deserialize(&mut affine, item[1..].as_ptr()) != BLST_ERROR::BLST_SUCCESS {
+                        return 1;
+                    }
+                    blst_p2_from_affine(&mut point, &affine);
+                    blst_p2_cneg(&mut point, item[0] == 1);
+                    blst_p2_add_or_double(&mut sum, &prev, &point);
+                }
+            }
+
+            let mut out = [0u8; 192];
+            unsafe { blst_p2_serialize(out.as_mut_ptr(), &sum) };
+            write_register(register_id, &out);
+            0
+        }
+
+        #[no_mangle]
+        extern "C" fn bls12381_map_fp_to_g1(value_len: u64, value_ptr: u64, register_id: u64) -> u64 {
+            let mut out = Vec::new();
+            for item in input(value_len, value_ptr).chunks(48) {
+                let mut fp = blst_fp::default();
+                let mut point = blst_p1::default();
+                let mut bytes = [0u8; 96];
+                unsafe {
+                    blst_fp_from_bendian(&mut fp, item.as_ptr());
+                    blst_map_to_g1(&mut point, &fp, std::ptr::null());
+                    blst_p1_serialize(bytes.as_mut_ptr(), &point);
+                }
+                out.extend_from_slice(&bytes);
+            }
+
+            write_register(register_id, &out);
+            0
+        }
+
+        #[no_mangle]
+        extern "C" fn bls12381_pairing_check(value_len: u64, value_ptr: u64) -> u64 {
+            let mut acc = unsafe { *blst_fp12_one() };
+            for item in input(value_len, value_ptr).chunks(288) {
+                let mut p = blst_p1_affine::default();
+                let mut q = blst_p2_affine::default();
+                let mut ml = blst_fp12::default();
+                let prev = acc;
+                unsafe {
+                    if blst_p1_deserialize(&mut p, item.as_ptr()) != BLST_ERROR::BLST_SUCCESS
+                        || blst_p2_deserialize(&mut q, item[96..].as_ptr()) != BLST_ERROR::BLST_SUCCESS
+                    {
+                        return 1;
+                    }
+                    blst_miller_loop(&mut ml, &q, &p);
+                    blst_fp12_mul(&mut acc, &prev, &ml);
+                }
+            }
+
+            let mut res = blst_fp12::default();
+            unsafe {
+                blst_final_exp(&mut res, &acc);
+                if blst_fp12_is_one(&res) {
+                    0
+                } else {
+                    2
+                }
+            }
+        }
+    }
+
+    fn key_pair(seed: u8) -> (blst::min_sig::SecretKey, Bls12381G2) {
+        let sk = blst::min_sig::SecretKey::key_gen(&[seed; 32], &[]).unwrap();
+        let pk = Bls12381G2(sk.sk_to_pk().serialize().to_vec());
+        (sk, pk)
+    }
+
+    fn sign(sk: &blst::min_sig::SecretKey, msg: &[u8], dst: &[u8]) -> Bls12381G1 {
+        Bls12381G1(sk.sign(msg, dst, &[]).serialize().to_vec())
+    }
+
+    #[test]
+    fn expands_message_xmd() {
+        // RFC 9380 K.1
+        let dst = b"QUUX-V01-CS02-with-expander-SHA256-128";
+        assert_eq!(
+            "68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235",
+            hex::encode(expand_message_xmd(b"", dst, 0x20))
+        );
+        assert_eq!(
+            "d8ccab23b5985ccea865c6c97b6e5b8350e794e603b4b97902f53a8a0d605615",
+            hex::encode(expand_message_xmd(b"abc", dst, 0x20))
+        );
+        assert_eq!(
+            "af84c27ccfd45d41914fdff5df25293e221afc53d8ad2ac06d5e3e29485dadbee0d121587713a3e0dd4d5e69e93eb7cd4f5df4cd103e188cf60cb02edc3edf18eda8576c412b18ffb658e3dd6ec849469b979d444cf7b26911a08e63cf31f9dcc541708d3491184472c2c29bb749d4286b004ceb5ee6b9a7fa5b646c993f0ced",
+            hex::encode(expand_message_xmd(b"", dst, 0x80))
+        );
+    }
+
+    #[test]
+    fn hashes_to_field() {
+        // RFC 9380 J.9.1
+        let dst = b"QUUX-V01-CS02-with-BLS12381G1_XMD:SHA-256_SSWU_RO_";
+        let u = hash_to_field(b"", dst);
+        assert_eq!(
+            "0ba14bd907ad64a016293ee7c2d276b8eae71f25a4b941eece7b0d89f17f75cb3ae5438a614fb61d6835ad59f29c564f",
+            hex::encode(u[0])
+        );
+        assert_eq!(
+            "019b9bd7979f12657976de2884c7cce192b82c177c80e0ec604436a7f538d231552f0d96d9f7babe5fa3b19b3ff25ac9",
+            hex::encode(u[1])
+        );
+    }
+
+    #[test]
+    fn hashes_to_g1() {
+        // RFC 9380 J.9.1
+        let dst = b"QUUX-V01-CS02-with-BLS12381G1_XMD:SHA-256_SSWU_RO_";
+        assert_eq!(
+            "052926add2207b76ca4fa57a8734416c8dc95e24501772c814278700eed6d1e4e8cf62d9c09db0fac349612b759e79a1\
+             08ba738453bfed09cb546dbb0783dbb3a5f1f566ed67bb6be0e8c67e2e81a4cc68ee29813bb7994998f3eae0c9c6a265",
+            hex::encode(hash_to_g1(b"", dst).0)
+        );
+        assert_eq!(
+            "03567bc5ef9c690c2ab2ecdf6a96ef1c139cc0b2f284dca0a9a7943388a49a3aee664ba5379a7655d3c68900be2f6903\
+             0b9c15f3fe6e5cf4211f346271d7b01c8f3b28be689c8429c85b67af215533311f0b8dfaaa154fa6b88176c229f2885d",
+            hex::encode(hash_to_g1(b"abc", dst).0)
+        );
+    }
+
+    #[test]
+    fn verifies_signature() {
+        let (sk, pk) = key_pair(1);
+        let (_, other_pk) = key_pair(2);
+        let sig = sign(&sk, b"header", BLS12381_DST);
+
+        assert!(verify_signature(&sig, b"header", &pk));
+        assert!(!verify_signature(&sig, b"another header", &pk));
+        assert!(!verify_signature(&sig, b"header", &other_pk));
+        assert!(!verify_signature(&sign(&sk, b"header", BLS12381_POP_DST), b"header", &pk));
+    }
+
+    #[test]
+    fn verifies_possession() {
+        let (sk, pk) = key_pair(1);
+        let (other_sk, _) = key_pair(2);
+
+        assert!(verify_possession(&sign(&sk, &pk.0, BLS12381_POP_DST), &pk));
+        assert!(!verify_possession(&sign(&sk, &pk.0, BLS12381_DST), &pk));
+        assert!(!verify_possession(&sign(&other_sk, &pk.0, BLS12381_POP_DST), &pk));
+    }
+
+    #[test]
+    fn aggregates_g2() {
+        let pairs: Vec<_> = (1..=3).map(key_pair).collect();
+        let agg_pk = aggregate_g2(&[pairs[0].1.clone(), pairs[2].1.clone()]);
+
+        let pks = [pairs[0].0.sk_to_pk(), pairs[2].0.sk_to_pk()];
+        let expected = blst::min_sig::AggregatePublicKey::aggregate(&[&pks[0], &pks[1]], false).unwrap();
+        assert_eq!(expected.to_public_key().serialize().to_vec(), agg_pk.0);
+
+        let sigs = [
+            pairs[0].0.sign(b"header", BLS12381_DST, &[]),
+            pairs[2].0.sign(b"header", BLS12381_DST, &[]),
+        ];
+        let agg_sig = blst::min_sig::AggregateSignature::aggregate(&[&sigs[0], &sigs[1]], false).unwrap();
+        let agg_sig = Bls12381G1(agg_sig.to_signature().serialize().to_vec());
+        assert!(verify_signature(&agg_sig, b"header", &agg_pk));
+        assert!(!verify_signature(&agg_sig, b"header", &aggregate_g2(&[pairs[0].1.clone(), pairs[1].1.clone()])));
+    }
+
+    #[test]
+    fn serializes_points_to_json() {
+        let key = Bls12381G2(hex::decode(G2_GENERATOR).unwrap());
+        let json = serde_json::to_string(&key).unwrap();
+        assert_eq!(format!("\"0x{}\"", G2_GENERATOR), json);
+        assert_eq!(key, serde_json::from_str(&json).unwrap());
+    }
+}
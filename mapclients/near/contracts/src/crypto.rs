@@ -1,21 +1,39 @@
 use std::convert::TryFrom;
-use std::ops::Sub;
 use near_sdk::env;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::serde::{Serialize, Deserialize};
+use near_sdk::serde::{de, Deserialize, Deserializer, Serialize};
 use near_sdk::serde_json;
-use crate::types::istanbul::{IstanbulAggregatedSeal, IstanbulMsg, G1_PUBLIC_KEY_LENGTH};
+use crate::types::istanbul::{IstanbulAggregatedSeal, IstanbulMsg, SealVersion, G1_PUBLIC_KEY_LENGTH, PUBLIC_KEY_LENGTH};
 use crate::types::header::Hash;
-use num_bigint::{BigInt as Integer, BigInt, Sign};
+use num_bigint::{BigInt as Integer, BigInt};
 use crate::serialization::rlp::big_int_to_rlp_compat_bytes;
-use crate::hash::{hash_to_g1, prime};
+use crate::hash::{hash_to_g1, CURVE_B};
+use crate::fp::{Fp, Fp2};
+use crate::types::errors::Kind;
 
 
-const ALT_BN128_REGISTER: u64 = 1;
+pub(crate) const ALT_BN128_REGISTER: u64 = 1;
 pub const REGISTER_EXPECTED_ERR: &str =
     "Register was expected to have data because we just wrote it into it.";
 
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+/// G1_COMPRESSED_LENGTH is the number of bytes of a compressed G1 point: x with the flags below
+pub const G1_COMPRESSED_LENGTH: usize = 32;
+
+/// G2_COMPRESSED_LENGTH is the number of bytes of a compressed G2 point: `x.i || x.r` with the
+/// flags below in `x.i`
+pub const G2_COMPRESSED_LENGTH: usize = 64;
+
+/// Set in the first byte of compressed points. Coordinates never have it since p < 2^254,
+/// which tells compressed and uncompressed encodings apart.
+const COMPRESSED_FLAG: u8 = 0x80;
+
+/// Set in the first byte of compressed points whose y is the lexicographically largest root.
+const Y_LARGEST_FLAG: u8 = 0x40;
+
+/// G1 is serialized to JSON as its coordinates and accepts the coordinates or a hex string of
+/// the compressed or uncompressed point. Borsh keeps the uncompressed point, so loading it from
+/// state does not recover y.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub struct G1 {
     #[serde(with = "crate::serialization::bytes::hexstring")]
@@ -24,7 +42,8 @@ pub struct G1 {
     pub y: [u8; 32],
 }
 
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug)]
+/// G2 is serialized like G1, its hex string uses the MAP key layout `x.i || x.r || y.i || y.r`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub struct G2 {
     #[serde(with = "crate::serialization::bytes::hexstring")]
@@ -38,7 +57,7 @@ pub struct G2 {
 }
 
 impl G1 {
-    pub fn from_slice(s: &[u8]) -> Result<Self, ()> {
+    pub fn from_slice(s: &[u8]) -> Result<Self, Kind> {
         if let 64 = s.len() {
             let mut x = [0_u8; 32];
             x.copy_from_slice(&s[..32]);
@@ -49,10 +68,10 @@ impl G1 {
             return Ok(G1 { x, y });
         }
 
-        Err(())
+        Err(Kind::InvalidPoint)
     }
 
-    pub fn from_le_slice(s: &[u8]) -> Result<Self, ()> {
+    pub fn from_le_slice(s: &[u8]) -> Result<Self, Kind> {
         if let 64 = s.len() {
             let mut x = [0_u8; 32];
             x.copy_from_slice(&s[..32]);
@@ -65,7 +84,7 @@ impl G1 {
             return Ok(G1 { x, y });
         }
 
-        Err(())
+        Err(Kind::InvalidPoint)
     }
 
     pub fn from(x: &BigInt, y: &BigInt) -> Self {
@@ -79,18 +98,202 @@ impl G1 {
     }
 
     pub fn neg(&self) -> Self {
-        let y = Integer::from_bytes_be(Sign::Plus, self.y.as_slice());
-        let neg_y = prime().sub(&y);
-
-        let neg_y_bytes = integer_to_vec_32(&neg_y, true);
-
         Self {
             x: self.x,
-            y: <[u8; 32]>::try_from(neg_y_bytes).unwrap(),
+            y: Fp::from_be_bytes_reduced(&self.y).neg().to_be_bytes(),
+        }
+    }
+
+    /// Compresses the point to x with the flags in its top bits. Returns `None` for the point at
+    /// infinity and points which are not on the curve.
+    pub fn compress(&self) -> Option<[u8; G1_COMPRESSED_LENGTH]> {
+        let x = Fp::from_be_bytes(&self.x)?;
+        let y = Fp::from_be_bytes(&self.y)?;
+        if y.square() != g1_curve_rhs(&x) || (x.is_zero() && y.is_zero()) {
+            return None;
+        }
+
+        let mut res = self.x;
+        res[0] |= COMPRESSED_FLAG;
+        if y.is_lexicographically_largest() {
+            res[0] |= Y_LARGEST_FLAG;
+        }
+
+        Some(res)
+    }
+
+    /// Parses a compressed point, recovering y from the curve equation.
+    pub fn from_compressed(s: &[u8]) -> Result<Self, Kind> {
+        if s.len() != G1_COMPRESSED_LENGTH || s[0] & COMPRESSED_FLAG == 0 {
+            return Err(Kind::InvalidPoint);
+        }
+
+        let mut x = [0_u8; 32];
+        x.copy_from_slice(s);
+        x[0] &= !(COMPRESSED_FLAG | Y_LARGEST_FLAG);
+
+        let fx = Fp::from_be_bytes(&x).ok_or(Kind::InvalidPoint)?;
+        let mut y = g1_curve_rhs(&fx).checked_sqrt().ok_or(Kind::InvalidPoint)?;
+        if y.is_lexicographically_largest() != (s[0] & Y_LARGEST_FLAG != 0) {
+            y = y.neg();
+        }
+
+        Ok(G1 { x, y: y.to_be_bytes() })
+    }
+
+    /// Parses a compressed or an uncompressed big endian point.
+    fn from_bytes(s: &[u8]) -> Result<Self, Kind> {
+        match s.len() {
+            G1_COMPRESSED_LENGTH => Self::from_compressed(s),
+            _ => Self::from_slice(s),
+        }
+    }
+}
+
+impl G2 {
+    /// Parses a serialized MAP G2 public key: `x.i || x.r || y.i || y.r`, each 32 bytes big endian.
+    pub fn from_slice(s: &[u8]) -> Result<Self, Kind> {
+        if let PUBLIC_KEY_LENGTH = s.len() {
+            let mut xi = [0_u8; 32];
+            xi.copy_from_slice(&s[..32]);
+            let mut xr = [0_u8; 32];
+            xr.copy_from_slice(&s[32..64]);
+            let mut yi = [0_u8; 32];
+            yi.copy_from_slice(&s[64..96]);
+            let mut yr = [0_u8; 32];
+            yr.copy_from_slice(&s[96..]);
+
+            return Ok(G2 { xr, xi, yr, yi });
+        }
+
+        Err(Kind::InvalidPoint)
+    }
+
+    /// Compresses the point to `x.i || x.r` with the flags in the top bits of `x.i`. Returns
+    /// `None` for the point at infinity and points which are not on the curve.
+    pub fn compress(&self) -> Option<[u8; G2_COMPRESSED_LENGTH]> {
+        let x = Fp2::new(Fp::from_be_bytes(&self.xr)?, Fp::from_be_bytes(&self.xi)?);
+        let y = Fp2::new(Fp::from_be_bytes(&self.yr)?, Fp::from_be_bytes(&self.yi)?);
+        if y.square() != g2_curve_rhs(&x) || (x == Fp2::ZERO && y == Fp2::ZERO) {
+            return None;
+        }
+
+        let mut res = [0_u8; G2_COMPRESSED_LENGTH];
+        res[..32].copy_from_slice(&self.xi);
+        res[32..].copy_from_slice(&self.xr);
+        res[0] |= COMPRESSED_FLAG;
+        if y.is_lexicographically_largest() {
+            res[0] |= Y_LARGEST_FLAG;
+        }
+
+        Some(res)
+    }
+
+    /// Parses a compressed point, recovering y from the curve equation.
+    pub fn from_compressed(s: &[u8]) -> Result<Self, Kind> {
+        if s.len() != G2_COMPRESSED_LENGTH || s[0] & COMPRESSED_FLAG == 0 {
+            return Err(Kind::InvalidPoint);
+        }
+
+        let mut xi = [0_u8; 32];
+        xi.copy_from_slice(&s[..32]);
+        xi[0] &= !(COMPRESSED_FLAG | Y_LARGEST_FLAG);
+        let mut xr = [0_u8; 32];
+        xr.copy_from_slice(&s[32..]);
+
+        let x = Fp2::new(Fp::from_be_bytes(&xr).ok_or(Kind::InvalidPoint)?, Fp::from_be_bytes(&xi).ok_or(Kind::InvalidPoint)?);
+        let mut y = g2_curve_rhs(&x).sqrt().ok_or(Kind::InvalidPoint)?;
+        if y.is_lexicographically_largest() != (s[0] & Y_LARGEST_FLAG != 0) {
+            y = y.neg();
+        }
+
+        Ok(G2 {
+            xr,
+            xi,
+            yr: y.c0.to_be_bytes(),
+            yi: y.c1.to_be_bytes(),
+        })
+    }
+
+    /// Parses a compressed point or a serialized MAP G2 public key.
+    fn from_bytes(s: &[u8]) -> Result<Self, Kind> {
+        match s.len() {
+            G2_COMPRESSED_LENGTH => Self::from_compressed(s),
+            _ => Self::from_slice(s),
+        }
+    }
+}
+
+/// x^3 + 3
+fn g1_curve_rhs(x: &Fp) -> Fp {
+    x.cube().add(&CURVE_B)
+}
+
+/// x^3 + 3 / (9 + u)
+fn g2_curve_rhs(x: &Fp2) -> Fp2 {
+    // 3 / (9 + u) in Montgomery form
+    const B: Fp2 = Fp2 {
+        c0: Fp::from_montgomery([0x3bf938e377b802a8, 0x020b1b273633535d, 0x26b7edf049755260, 0x2514c6324384a86d]),
+        c1: Fp::from_montgomery([0x38e7ecccd1dcff67, 0x65f0b37d93ce0d3e, 0xd749d0dd22ac00aa, 0x0141b9ce4a688d4d]),
+    };
+    x.cube().add(&B)
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde", untagged)]
+enum G1Repr {
+    Coordinates { x: String, y: String },
+    Encoded(String),
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde", untagged)]
+enum G2Repr {
+    Coordinates { xr: String, xi: String, yr: String, yi: String },
+    Encoded(String),
+}
+
+impl<'de> Deserialize<'de> for G1 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match G1Repr::deserialize(deserializer)? {
+            G1Repr::Coordinates { x, y } => Ok(G1 {
+                x: decode_coordinate(&x).map_err(de::Error::custom)?,
+                y: decode_coordinate(&y).map_err(de::Error::custom)?,
+            }),
+            G1Repr::Encoded(s) => G1::from_bytes(&decode_hex(&s).map_err(de::Error::custom)?)
+                .map_err(|_| de::Error::custom(format!("invalid g1 point: {}", s))),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for G2 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match G2Repr::deserialize(deserializer)? {
+            G2Repr::Coordinates { xr, xi, yr, yi } => Ok(G2 {
+                xr: decode_coordinate(&xr).map_err(de::Error::custom)?,
+                xi: decode_coordinate(&xi).map_err(de::Error::custom)?,
+                yr: decode_coordinate(&yr).map_err(de::Error::custom)?,
+                yi: decode_coordinate(&yi).map_err(de::Error::custom)?,
+            }),
+            G2Repr::Encoded(s) => G2::from_bytes(&decode_hex(&s).map_err(de::Error::custom)?)
+                .map_err(|_| de::Error::custom(format!("invalid g2 point: {}", s))),
         }
     }
 }
 
+fn decode_hex(s: &str) -> Result<Vec<u8>, String> {
+    if s.len() <= 2 || !s.starts_with("0x") {
+        return Err(format!("hex string should start with '0x', got: {}", s));
+    }
+
+    hex::decode(&s[2..]).map_err(|e| e.to_string())
+}
+
+fn decode_coordinate(s: &str) -> Result<[u8; 32], String> {
+    <[u8; 32]>::try_from(decode_hex(s)?)
+        .map_err(|_| format!("coordinate should be 32 bytes, got: {}", s))
+}
+
 pub(crate) fn integer_to_vec_32(i: &BigInt, be: bool) -> Vec<u8> {
     let mut bytes: Vec<u8> = if be { i.to_signed_bytes_be() } else { i.to_signed_bytes_le() };
     if bytes.len() < 32 {
@@ -150,9 +353,14 @@ pub fn sum_points<'a>(points: &'a Vec<G1>, bitmap: &'a Integer) -> G1 {
 
 pub fn check_aggregated_g2_pub_key(points: &Vec<G1>, bitmap: &Integer, agg_g2_pk: &G2) -> bool {
     let g1_pk_sum = sum_points(points, bitmap);
+    check_g2_pub_key(&g1_pk_sum, agg_g2_pk)
+}
+
+/// Checks that the G1 and G2 public keys share the same private key.
+pub fn check_g2_pub_key(g1_pk: &G1, g2_pk: &G2) -> bool {
     let g2 = get_g2();
     let g1 = get_g1();
-    let buf = pack_points(&g1_pk_sum, &g2, &g1.neg(), agg_g2_pk);
+    let buf = pack_points(g1_pk, &g2, &g1.neg(), g2_pk);
 
     let mut res = 0;
     unsafe {
@@ -162,10 +370,10 @@ pub fn check_aggregated_g2_pub_key(points: &Vec<G1>, bitmap: &Integer, agg_g2_pk
     res == 1
 }
 
-pub fn check_sealed_signature(agg_seal: &IstanbulAggregatedSeal, hash: &Hash, agg_g2_pk: &G2) -> bool {
+pub fn check_sealed_signature(agg_seal: &IstanbulAggregatedSeal, hash: &Hash, agg_g2_pk: &G2, version: SealVersion) -> bool {
     let sig_on_g1 = G1::from_slice(agg_seal.signature.as_slice()).unwrap();
     let g2 = get_g2();
-    let proposal_seal = prepare_commited_seal(*hash, &agg_seal.round);
+    let proposal_seal = prepare_commited_seal(*hash, &agg_seal.round, version);
     let hash_to_g1 = hash_to_g1(&proposal_seal);
 
     let buf = pack_points(&sig_on_g1, &g2, &hash_to_g1.neg(), agg_g2_pk);
@@ -179,23 +387,28 @@ pub fn check_sealed_signature(agg_seal: &IstanbulAggregatedSeal, hash: &Hash, ag
 }
 
 pub(crate) fn pack_points(p0: &G1, p1: &G2, p2: &G1, p3: &G2) -> Vec<u8> {
-    [
-        to_le_bytes(&p0.x),
-        to_le_bytes(&p0.y),
-        to_le_bytes(&p1.xr),
-        to_le_bytes(&p1.xi),
-        to_le_bytes(&p1.yr),
-        to_le_bytes(&p1.yi),
-        to_le_bytes(&p2.x),
-        to_le_bytes(&p2.y),
-        to_le_bytes(&p3.xr),
-        to_le_bytes(&p3.xi),
-        to_le_bytes(&p3.yr),
-        to_le_bytes(&p3.yi)
-    ].concat()
-}
-
-fn to_le_bytes(bytes: &[u8; 32]) -> [u8; 32] {
+    pack_pairs(&[(p0, p1), (p2, p3)])
+}
+
+/// Packs (G1, G2) pairs into the input of `alt_bn128_pairing_check`.
+pub(crate) fn pack_pairs(pairs: &[(&G1, &G2)]) -> Vec<u8> {
+    pairs
+        .iter()
+        .flat_map(|(g1, g2)| {
+            [
+                to_le_bytes(&g1.x),
+                to_le_bytes(&g1.y),
+                to_le_bytes(&g2.xr),
+                to_le_bytes(&g2.xi),
+                to_le_bytes(&g2.yr),
+                to_le_bytes(&g2.yi),
+            ]
+        })
+        .collect::<Vec<[u8; 32]>>()
+        .concat()
+}
+
+pub(crate) fn to_le_bytes(bytes: &[u8; 32]) -> [u8; 32] {
     let mut buf = [0; 32];
 
     for (k, v) in bytes.iter().enumerate() {
@@ -205,9 +418,178 @@ fn to_le_bytes(bytes: &[u8; 32]) -> [u8; 32] {
     buf
 }
 
-fn prepare_commited_seal(hash: Hash, round: &Integer) -> Vec<u8> {
+pub(crate) fn prepare_commited_seal(hash: Hash, round: &Integer, version: SealVersion) -> Vec<u8> {
     let round_bytes = big_int_to_rlp_compat_bytes(round);
     let commit_bytes = [IstanbulMsg::Commit as u8];
 
-    [&hash[..], &round_bytes[..], &commit_bytes[..]].concat()
-}
\ No newline at end of file
+    match version {
+        SealVersion::V0 => [&hash[..], &round_bytes[..], &commit_bytes[..]].concat(),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::header::Header;
+    use crate::types::istanbul::IstanbulExtra;
+
+    // the keys of the validator added in block 188000 of tests/data/header.json
+    const G2_PUB_KEY: &str = "23378edd46da93002a90a525145d47cb834c53668494d3f8cf36294541baebcb0d1e3312fe248ccd541e561a34b9404ea1d70f2a21831b09f3d0ddf4ed923f5509c90688826a862a0d0394ae9bdd3e904300a0c08b7aea3b49c83e5ee8b3dea409bca6662f6a04bf928cb5fb2a55191e8b7fd9b5db1da3c876480f5427770ee0";
+    const G1_PUB_KEY: &str = "2b8a812d2e9ac7d6799b3ebad52a27402a31e89eb3f383be96314f3f3f0ead3a028250eedb4307d62696f8a1b235dc376682780fb69eb1b7c9403ee6608ad116";
+
+    // block 3000 of tests/data/header.json
+    const HEADER: &str = r#"{
+        "parentHash": "0x6de6bd2e512ef35d2b551399ae76289da63ebde1f27819446f631aa2823eea0a",
+        "coinbase": "0xf655fc7c95c70a118f98b46ca5028746284349a5",
+        "root": "0x446d893713d6d9214b0dbdb792a786c36a3fd67a96df95edda8269ff84af1626",
+        "txHash": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+        "receiptHash": "0x42a7fa9cbc2ee0191a426d2ac2e3b787d522a21b4593d72c469e7f38c6b8e02c",
+        "bloom": "0x10040000000000000000000000000000000000000000000000000000001020010000000080800000008000000000000000000000000008000000000804040000000000000000000000000008000080008000000000040000000000000000002000000000020000000000000000000800000000000a00000000000010000008000000000000000000000000000000000000000000000000000000200000000000000000000800000080002000000080400000000000800000000000000000000000000002000002000000000000000000000080000000000800000000000060000400000000400000000000000000000000400000000000004080000000000000",
+        "number": "0xbb8",
+        "gasLimit": "0x7a1200",
+        "gasUsed": "0x0",
+        "time": "0x6335ca53",
+        "extra": "0xd7820606846765746888676f312e31352e36856c696e75780000000000000000f8d3c0c0c080b8410945855792bb799f379a6ec39caa9a9b30ed8bc5efdf56155f5990f00a36912a22c297ec627e4d8ffadbd5adabd41f47949d22e8b6fc534cddda671e6d905c2300f8440eb84027f49cf61cf3e794c8cb9d3555c7ac0c38ea9cf36b8932fb1ee987031e21119200c7ba5af072e811713880974dc2e99a92de0ae6730cf1742fcdd66b386671ab80f8440fb8401e9900cdfe1ae09a6dcdb4b3f445621a7a94b03dcd3fe86d4917e0c1272e9d7e27c6ab5ac99a1cbb9888e93994c196e2d732b12594d1e56fed7e74ff71dffb9780",
+        "mixDigest": "0x0000000000000000000000000000000000000000000000000000000000000000",
+        "nonce": "0x0000000000000000",
+        "baseFee": "0x174876e800",
+        "hash": "0x8a5350a8115ebba0629f97c05007aae3245c3cbde7da9d72652023ef3e3c9494"
+    }"#;
+    const AGG_PK: &str = r#"{
+        "xr": "0x0bc8dcca7a4802a6e69e89070c4df6a07af1714b393759e28aa1b529127acd55",
+        "xi": "0x2a95f21571f5fb4c7c3b0b4278b7c1dd09fffeca2fd9f26c6ce44dd8ba2b524c",
+        "yr": "0x1f763a5360dbb6538be94e2e2352e068110cb5597994777def42dacf10f5e03d",
+        "yi": "0x1bba02efca561835e6c3b03e9d46574aff1dfe21ce7372ae8f82fac2211e39c8"
+    }"#;
+
+    #[test]
+    fn parses_serialized_g2_pub_key() {
+        let g2 = G2::from_slice(&hex::decode(G2_PUB_KEY).unwrap()).unwrap();
+        assert_eq!(hex::decode(&G2_PUB_KEY[..64]).unwrap(), g2.xi);
+        assert_eq!(hex::decode(&G2_PUB_KEY[64..128]).unwrap(), g2.xr);
+        assert_eq!(hex::decode(&G2_PUB_KEY[128..192]).unwrap(), g2.yi);
+        assert_eq!(hex::decode(&G2_PUB_KEY[192..]).unwrap(), g2.yr);
+
+        assert!(G2::from_slice(&[0; 64]).is_err());
+    }
+
+    #[test]
+    fn checks_g2_pub_key() {
+        let g1 = G1::from_slice(&hex::decode(G1_PUB_KEY).unwrap()).unwrap();
+        let g2 = G2::from_slice(&hex::decode(G2_PUB_KEY).unwrap()).unwrap();
+        assert!(check_g2_pub_key(&g1, &g2));
+        assert!(!check_g2_pub_key(&g1.neg(), &g2));
+        assert!(!check_g2_pub_key(&get_g1(), &g2));
+    }
+
+    #[test]
+    fn prepares_commited_seal() {
+        let hash = [7u8; 32];
+        let round = Integer::from(0x0102);
+
+        let seal = prepare_commited_seal(hash, &round, SealVersion::V0);
+        assert_eq!([&hash[..], &[0x01, 0x02], &[IstanbulMsg::Commit as u8]].concat(), seal);
+
+        // round 0 is encoded as empty bytes
+        let seal = prepare_commited_seal(hash, &Integer::from(0), SealVersion::V0);
+        assert_eq!([&hash[..], &[IstanbulMsg::Commit as u8]].concat(), seal);
+    }
+
+    #[test]
+    fn checks_sealed_signature_with_seal_version() {
+        let header: Header = serde_json::from_str(HEADER).unwrap();
+        let agg_pk: G2 = serde_json::from_str(AGG_PK).unwrap();
+        let extra = IstanbulExtra::from_rlp(&header.extra).unwrap();
+        let hash = header.hash().unwrap();
+
+        assert!(check_sealed_signature(&extra.aggregated_seal, &hash, &agg_pk, SealVersion::V0));
+        assert!(!check_sealed_signature(&extra.aggregated_seal, &[0; 32], &agg_pk, SealVersion::V0));
+    }
+
+    // compressed G1_PUB_KEY and G2_PUB_KEY
+    const G1_COMPRESSED: &str = "ab8a812d2e9ac7d6799b3ebad52a27402a31e89eb3f383be96314f3f3f0ead3a";
+    const G2_COMPRESSED: &str = "a3378edd46da93002a90a525145d47cb834c53668494d3f8cf36294541baebcb0d1e3312fe248ccd541e561a34b9404ea1d70f2a21831b09f3d0ddf4ed923f55";
+
+    #[test]
+    fn compresses_g1_point() {
+        let g1 = G1::from_slice(&hex::decode(G1_PUB_KEY).unwrap()).unwrap();
+        let compressed = g1.compress().unwrap();
+        assert_eq!(G1_COMPRESSED, hex::encode(compressed));
+        assert_eq!(g1, G1::from_compressed(&compressed).unwrap());
+
+        let neg = g1.neg();
+        let compressed = neg.compress().unwrap();
+        assert_eq!(COMPRESSED_FLAG | Y_LARGEST_FLAG, compressed[0] & (COMPRESSED_FLAG | Y_LARGEST_FLAG));
+        assert_eq!(neg, G1::from_compressed(&compressed).unwrap());
+
+        assert_eq!(None, G1 { x: [0; 32], y: [0; 32] }.compress());
+        assert_eq!(None, G1 { x: g1.x, y: g1.x }.compress());
+
+        // x = 0 and x = 4 have no point on the curve
+        let mut x = [0u8; 32];
+        x[0] = COMPRESSED_FLAG;
+        assert!(G1::from_compressed(&x).is_err());
+        x[31] = 4;
+        assert!(G1::from_compressed(&x).is_err());
+        // x >= p
+        assert!(G1::from_compressed(&[0xbf; 32]).is_err());
+        // missing flag
+        assert!(G1::from_compressed(&g1.x).is_err());
+    }
+
+    #[test]
+    fn compresses_g2_point() {
+        let g2 = G2::from_slice(&hex::decode(G2_PUB_KEY).unwrap()).unwrap();
+        let compressed = g2.compress().unwrap();
+        assert_eq!(G2_COMPRESSED, hex::encode(compressed));
+        assert_eq!(g2, G2::from_compressed(&compressed).unwrap());
+
+        let neg = G2 {
+            yr: Fp::from_be_bytes(&g2.yr).unwrap().neg().to_be_bytes(),
+            yi: Fp::from_be_bytes(&g2.yi).unwrap().neg().to_be_bytes(),
+            ..g2
+        };
+        let compressed = neg.compress().unwrap();
+        assert_eq!(COMPRESSED_FLAG | Y_LARGEST_FLAG, compressed[0] & (COMPRESSED_FLAG | Y_LARGEST_FLAG));
+        assert_eq!(neg, G2::from_compressed(&compressed).unwrap());
+
+        assert_eq!(None, G2 { yr: g2.xr, ..g2 }.compress());
+
+        let mut compressed = g2.compress().unwrap();
+        compressed[63] ^= 1;
+        assert_ne!(Ok(g2), G2::from_compressed(&compressed));
+        assert!(G2::from_compressed(&hex::decode(&G2_PUB_KEY[..128]).unwrap()).is_err());
+    }
+
+    #[test]
+    fn serializes_points_with_borsh() {
+        let g1 = G1::from_slice(&hex::decode(G1_PUB_KEY).unwrap()).unwrap();
+        let bytes = g1.try_to_vec().unwrap();
+        assert_eq!([g1.x, g1.y].concat(), bytes);
+        assert_eq!(g1, G1::try_from_slice(&bytes).unwrap());
+
+        let g2 = G2::from_slice(&hex::decode(G2_PUB_KEY).unwrap()).unwrap();
+        let bytes = g2.try_to_vec().unwrap();
+        assert_eq!([g2.xr, g2.xi, g2.yr, g2.yi].concat(), bytes);
+        assert_eq!(g2, G2::try_from_slice(&bytes).unwrap());
+    }
+
+    #[test]
+    fn deserializes_points_from_json() {
+        let g1 = G1::from_slice(&hex::decode(G1_PUB_KEY).unwrap()).unwrap();
+        let coordinates = serde_json::to_string(&g1).unwrap();
+        assert_eq!(g1, serde_json::from_str(&coordinates).unwrap());
+        assert_eq!(g1, serde_json::from_str(&format!("\"0x{}\"", G1_COMPRESSED)).unwrap());
+        assert_eq!(g1, serde_json::from_str(&format!("\"0x{}\"", G1_PUB_KEY)).unwrap());
+        assert_eq!(g1, serde_json::from_value(serde_json::json!(format!("0x{}", G1_COMPRESSED))).unwrap());
+        assert!(serde_json::from_str::<G1>(&format!("\"{}\"", G1_COMPRESSED)).is_err());
+        assert!(serde_json::from_str::<G1>(&format!("\"0x{}\"", &G1_PUB_KEY[..62])).is_err());
+
+        let g2 = G2::from_slice(&hex::decode(G2_PUB_KEY).unwrap()).unwrap();
+        let coordinates = serde_json::to_string(&g2).unwrap();
+        assert_eq!(g2, serde_json::from_str(&coordinates).unwrap());
+        assert_eq!(g2, serde_json::from_str(&format!("\"0x{}\"", G2_COMPRESSED)).unwrap());
+        assert_eq!(g2, serde_json::from_str(&format!("\"0x{}\"", G2_PUB_KEY)).unwrap());
+    }
+}
@@ -0,0 +1,451 @@
+//! Arithmetic in the bn254 base field on fixed 4 x 64-bit limbs (little endian) in Montgomery form,
+//! used instead of arbitrary precision integers on the hot path of seal verification.
+
+use std::cmp::Ordering;
+
+/// p = 21888242871839275222246405745257275088696311157297823662689037894645226208583
+const MODULUS: [u64; 4] = [0x3c208c16d87cfd47, 0x97816a916871ca8d, 0xb85045b68181585d, 0x30644e72e131a029];
+
+/// -p^-1 mod 2^64
+const INV: u64 = 0x87d20782e4866389;
+
+/// R^2 mod p, where R = 2^256
+const R2: [u64; 4] = [0xf32cfc5b538afa89, 0xb5e71911d44501fb, 0x47ab1eff0a417ff6, 0x06d89f71cab8351f];
+
+/// p - 2, exponent of the inversion
+const P_MINUS_2: [u64; 4] = [0x3c208c16d87cfd45, 0x97816a916871ca8d, 0xb85045b68181585d, 0x30644e72e131a029];
+
+/// (p - 1) / 2, exponent of the legendre symbol
+const P_MINUS_1_OVER_2: [u64; 4] = [0x9e10460b6c3e7ea3, 0xcbc0b548b438e546, 0xdc2822db40c0ac2e, 0x183227397098d014];
+
+/// (p + 1) / 4, exponent of the square root since p = 3 mod 4
+const P_PLUS_1_OVER_4: [u64; 4] = [0x4f082305b61f3f52, 0x65e05aa45a1c72a3, 0x6e14116da0605617, 0x0c19139cb84c680a];
+
+/// (p - 3) / 4, exponent of the square root in Fp2
+const P_MINUS_3_OVER_4: [u64; 4] = [0x4f082305b61f3f51, 0x65e05aa45a1c72a3, 0x6e14116da0605617, 0x0c19139cb84c680a];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Fp([u64; 4]);
+
+impl Fp {
+    pub(crate) const ZERO: Fp = Fp([0, 0, 0, 0]);
+
+    /// R mod p
+    pub(crate) const ONE: Fp = Fp([0xd35d438dc58f0d9d, 0x0a78eb28f5c70b3d, 0x666ea36f7879462c, 0x0e0a77c19a07df2f]);
+
+    /// Wraps limbs that are already in Montgomery form, used for precomputed constants.
+    pub(crate) const fn from_montgomery(limbs: [u64; 4]) -> Fp {
+        Fp(limbs)
+    }
+
+    /// Parses a canonical 32 bytes big endian element, `None` if it is not less than p.
+    pub(crate) fn from_be_bytes(bytes: &[u8; 32]) -> Option<Fp> {
+        let limbs = be_bytes_to_limbs(bytes);
+        if cmp(&limbs, &MODULUS) != Ordering::Less {
+            return None;
+        }
+
+        Some(Fp(mont_mul(&limbs, &R2)))
+    }
+
+    /// Parses any 32 bytes big endian integer and reduces it modulo p.
+    pub(crate) fn from_be_bytes_reduced(bytes: &[u8; 32]) -> Fp {
+        Fp(mont_mul(&be_bytes_to_limbs(bytes), &R2))
+    }
+
+    pub(crate) fn to_be_bytes(self) -> [u8; 32] {
+        let limbs = self.to_canonical();
+        let mut bytes = [0u8; 32];
+        for (i, limb) in limbs.iter().rev().enumerate() {
+            bytes[i * 8..(i + 1) * 8].copy_from_slice(&limb.to_be_bytes());
+        }
+
+        bytes
+    }
+
+    /// Returns the limbs of the element out of Montgomery form.
+    pub(crate) fn to_canonical(self) -> [u64; 4] {
+        mont_mul(&self.0, &[1, 0, 0, 0])
+    }
+
+    pub(crate) fn is_zero(&self) -> bool {
+        *self == Fp::ZERO
+    }
+
+    pub(crate) fn add(&self, other: &Fp) -> Fp {
+        // both operands are below p < 2^254, so the sum never overflows 256 bits
+        let (sum, _) = add_limbs(&self.0, &other.0);
+        Fp(reduce_once(sum))
+    }
+
+    pub(crate) fn sub(&self, other: &Fp) -> Fp {
+        let (diff, borrow) = sub_limbs(&self.0, &other.0);
+        if borrow {
+            Fp(add_limbs(&diff, &MODULUS).0)
+        } else {
+            Fp(diff)
+        }
+    }
+
+    pub(crate) fn neg(&self) -> Fp {
+        Fp::ZERO.sub(self)
+    }
+
+    pub(crate) fn mul(&self, other: &Fp) -> Fp {
+        Fp(mont_mul(&self.0, &other.0))
+    }
+
+    pub(crate) fn square(&self) -> Fp {
+        self.mul(self)
+    }
+
+    pub(crate) fn cube(&self) -> Fp {
+        self.square().mul(self)
+    }
+
+    /// Raises the element to a fixed exponent given as little endian limbs.
+    pub(crate) fn pow(&self, exp: &[u64; 4]) -> Fp {
+        let mut res = Fp::ONE;
+        for limb in exp.iter().rev() {
+            for i in (0..64).rev() {
+                res = res.square();
+                if (limb >> i) & 1 == 1 {
+                    res = res.mul(self);
+                }
+            }
+        }
+
+        res
+    }
+
+    pub(crate) fn invert(&self) -> Fp {
+        self.pow(&P_MINUS_2)
+    }
+
+    /// Returns 1 for a non-zero quadratic residue, -1 for a non-residue and 0 for zero.
+    pub(crate) fn legendre(&self) -> i32 {
+        let s = self.pow(&P_MINUS_1_OVER_2);
+        if s.is_zero() {
+            0
+        } else if s == Fp::ONE {
+            1
+        } else {
+            -1
+        }
+    }
+
+    pub(crate) fn sqrt(&self) -> Fp {
+        self.pow(&P_PLUS_1_OVER_4)
+    }
+
+    /// Returns a square root of the element, `None` if it is not a quadratic residue.
+    pub(crate) fn checked_sqrt(&self) -> Option<Fp> {
+        let root = self.sqrt();
+        if root.square() == *self {
+            Some(root)
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if the canonical value of the element is greater than (p - 1) / 2.
+    pub(crate) fn is_lexicographically_largest(&self) -> bool {
+        cmp(&self.to_canonical(), &P_MINUS_1_OVER_2) == Ordering::Greater
+    }
+}
+
+/// Element `c0 + c1 * u` of the quadratic extension Fp[u] / (u^2 + 1), over which the
+/// coordinates of G2 points are defined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Fp2 {
+    pub(crate) c0: Fp,
+    pub(crate) c1: Fp,
+}
+
+impl Fp2 {
+    pub(crate) const ZERO: Fp2 = Fp2 { c0: Fp::ZERO, c1: Fp::ZERO };
+    pub(crate) const ONE: Fp2 = Fp2 { c0: Fp::ONE, c1: Fp::ZERO };
+
+    pub(crate) fn new(c0: Fp, c1: Fp) -> Fp2 {
+        Fp2 { c0, c1 }
+    }
+
+    pub(crate) fn add(&self, other: &Fp2) -> Fp2 {
+        Fp2::new(self.c0.add(&other.c0), self.c1.add(&other.c1))
+    }
+
+    pub(crate) fn neg(&self) -> Fp2 {
+        Fp2::new(self.c0.neg(), self.c1.neg())
+    }
+
+    pub(crate) fn conjugate(&self) -> Fp2 {
+        Fp2::new(self.c0, self.c1.neg())
+    }
+
+    pub(crate) fn mul(&self, other: &Fp2) -> Fp2 {
+        let c0 = self.c0.mul(&other.c0).sub(&self.c1.mul(&other.c1));
+        let c1 = self.c0.mul(&other.c1).add(&self.c1.mul(&other.c0));
+        Fp2::new(c0, c1)
+    }
+
+    pub(crate) fn square(&self) -> Fp2 {
+        self.mul(self)
+    }
+
+    pub(crate) fn cube(&self) -> Fp2 {
+        self.square().mul(self)
+    }
+
+    pub(crate) fn pow(&self, exp: &[u64; 4]) -> Fp2 {
+        let mut res = Fp2::ONE;
+        for limb in exp.iter().rev() {
+            for i in (0..64).rev() {
+                res = res.square();
+                if (limb >> i) & 1 == 1 {
+                    res = res.mul(self);
+                }
+            }
+        }
+
+        res
+    }
+
+    /// Returns a square root of the element, `None` if it is not a quadratic residue.
+    /// This is algorithm 9 of "Square root computation over even extension fields"
+    /// (Adj, Rodríguez-Henríquez) for p = 3 mod 4.
+    pub(crate) fn sqrt(&self) -> Option<Fp2> {
+        let minus_one = Fp2::ONE.neg();
+
+        let a1 = self.pow(&P_MINUS_3_OVER_4);
+        let alpha = a1.square().mul(self);
+        let a0 = alpha.conjugate().mul(&alpha);
+        if a0 == minus_one {
+            return None;
+        }
+
+        let x0 = a1.mul(self);
+        let root = if alpha == minus_one {
+            // multiply by u
+            Fp2::new(x0.c1.neg(), x0.c0)
+        } else {
+            alpha.add(&Fp2::ONE).pow(&P_MINUS_1_OVER_2).mul(&x0)
+        };
+
+        if root.square() == *self {
+            Some(root)
+        } else {
+            None
+        }
+    }
+
+    /// Orders elements by `c1` first and by `c0` if `c1` is zero.
+    pub(crate) fn is_lexicographically_largest(&self) -> bool {
+        if self.c1.is_zero() {
+            self.c0.is_lexicographically_largest()
+        } else {
+            self.c1.is_lexicographically_largest()
+        }
+    }
+}
+
+fn be_bytes_to_limbs(bytes: &[u8; 32]) -> [u64; 4] {
+    let mut limbs = [0u64; 4];
+    for (i, limb) in limbs.iter_mut().enumerate() {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(&bytes[(3 - i) * 8..(4 - i) * 8]);
+        *limb = u64::from_be_bytes(buf);
+    }
+
+    limbs
+}
+
+fn cmp(a: &[u64; 4], b: &[u64; 4]) -> Ordering {
+    a.iter().rev().cmp(b.iter().rev())
+}
+
+/// a + b * c + carry, returned as (low, high) words.
+#[inline(always)]
+fn mac(a: u64, b: u64, c: u64, carry: u64) -> (u64, u64) {
+    let t = (a as u128) + (b as u128) * (c as u128) + (carry as u128);
+    (t as u64, (t >> 64) as u64)
+}
+
+fn add_limbs(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], bool) {
+    let mut res = [0u64; 4];
+    let mut carry = false;
+    for i in 0..4 {
+        let (s, c1) = a[i].overflowing_add(b[i]);
+        let (s, c2) = s.overflowing_add(carry as u64);
+        res[i] = s;
+        carry = c1 || c2;
+    }
+
+    (res, carry)
+}
+
+fn sub_limbs(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], bool) {
+    let mut res = [0u64; 4];
+    let mut borrow = false;
+    for i in 0..4 {
+        let (d, b1) = a[i].overflowing_sub(b[i]);
+        let (d, b2) = d.overflowing_sub(borrow as u64);
+        res[i] = d;
+        borrow = b1 || b2;
+    }
+
+    (res, borrow)
+}
+
+/// Subtracts p once if the value is not less than p.
+fn reduce_once(a: [u64; 4]) -> [u64; 4] {
+    if cmp(&a, &MODULUS) == Ordering::Less {
+        a
+    } else {
+        sub_limbs(&a, &MODULUS).0
+    }
+}
+
+/// Montgomery multiplication a * b * R^-1 mod p (CIOS). `b` must be less than p, `a` may be
+/// any 256-bit value, which is how raw integers are reduced when converted into Montgomery form.
+fn mont_mul(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+    let mut t = [0u64; 6];
+    for &bi in b.iter() {
+        let mut carry = 0;
+        for j in 0..4 {
+            let (lo, hi) = mac(t[j], a[j], bi, carry);
+            t[j] = lo;
+            carry = hi;
+        }
+        let (lo, hi) = mac(t[4], 1, carry, 0);
+        t[4] = lo;
+        t[5] = hi;
+
+        let m = t[0].wrapping_mul(INV);
+        let (_, mut carry) = mac(t[0], m, MODULUS[0], 0);
+        for j in 1..4 {
+            let (lo, hi) = mac(t[j], m, MODULUS[j], carry);
+            t[j - 1] = lo;
+            carry = hi;
+        }
+        let (lo, hi) = mac(t[4], 1, carry, 0);
+        t[3] = lo;
+        t[4] = t[5] + hi;
+    }
+
+    // the result is below 2p, so a single subtraction brings it into range
+    let res = [t[0], t[1], t[2], t[3]];
+    if t[4] != 0 {
+        sub_limbs(&res, &MODULUS).0
+    } else {
+        reduce_once(res)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::{BigInt, Sign};
+    use num::Integer;
+    use std::str::FromStr;
+
+    fn prime() -> BigInt {
+        BigInt::from_str("21888242871839275222246405745257275088696311157297823662689037894645226208583").unwrap()
+    }
+
+    fn to_fp(v: &BigInt) -> Fp {
+        let bytes = v.to_bytes_be().1;
+        let mut buf = [0u8; 32];
+        buf[32 - bytes.len()..].copy_from_slice(&bytes);
+        Fp::from_be_bytes(&buf).unwrap()
+    }
+
+    fn to_int(v: &Fp) -> BigInt {
+        BigInt::from_bytes_be(Sign::Plus, &v.to_be_bytes())
+    }
+
+    fn samples() -> Vec<BigInt> {
+        let p = prime();
+        vec![
+            BigInt::from(0),
+            BigInt::from(1),
+            BigInt::from(2),
+            BigInt::from(3),
+            BigInt::from(u64::MAX),
+            &p - 1,
+            &p - 2,
+            (&p - 1) / 2,
+            BigInt::from_str("5910564439876404922092592771818717274376490012008896503918548302548484008557").unwrap(),
+            BigInt::from_str("7054493174779650031422375588220690385584759055599915892200652159233194526041").unwrap(),
+            BigInt::from_str("14592161914559516814830937163504850059130874104865215775126025263096817472389").unwrap(),
+        ]
+    }
+
+    #[test]
+    fn converts_to_and_from_bytes() {
+        for v in samples() {
+            assert_eq!(v, to_int(&to_fp(&v)));
+        }
+
+        assert_eq!(Fp::ONE, to_fp(&BigInt::from(1)));
+        assert_eq!(None, Fp::from_be_bytes(&[0xff; 32]));
+
+        let mut p = [0u8; 32];
+        p.copy_from_slice(&prime().to_bytes_be().1);
+        assert_eq!(None, Fp::from_be_bytes(&p));
+        assert_eq!(Fp::ZERO, Fp::from_be_bytes_reduced(&p));
+
+        let max = [0xff; 32];
+        let expected = BigInt::from_bytes_be(Sign::Plus, &max).mod_floor(&prime());
+        assert_eq!(expected, to_int(&Fp::from_be_bytes_reduced(&max)));
+    }
+
+    #[test]
+    fn matches_bigint_arithmetic() {
+        let p = prime();
+        for a in samples() {
+            for b in samples() {
+                let (fa, fb) = (to_fp(&a), to_fp(&b));
+                assert_eq!((&a + &b).mod_floor(&p), to_int(&fa.add(&fb)));
+                assert_eq!((&a - &b).mod_floor(&p), to_int(&fa.sub(&fb)));
+                assert_eq!((&a * &b).mod_floor(&p), to_int(&fa.mul(&fb)));
+            }
+            let fa = to_fp(&a);
+            assert_eq!((-&a).mod_floor(&p), to_int(&fa.neg()));
+            assert_eq!(a.modpow(&BigInt::from(3), &p), to_int(&fa.cube()));
+            assert_eq!(a.modpow(&(&p - 2), &p), to_int(&fa.invert()));
+            assert_eq!(a.modpow(&((&p + 1) / 4), &p), to_int(&fa.sqrt()));
+            assert_eq!(a > (&p - 1) / 2, fa.is_lexicographically_largest());
+        }
+    }
+
+    #[test]
+    fn computes_square_roots() {
+        for a in samples() {
+            let fa = to_fp(&a);
+            match fa.checked_sqrt() {
+                Some(root) => assert_eq!(fa, root.square()),
+                None => assert_eq!(-1, fa.legendre()),
+            }
+
+            for b in samples() {
+                let x = Fp2::new(fa, to_fp(&b));
+                let square = x.square();
+                let root = square.sqrt().unwrap();
+                assert!(root == x || root == x.neg());
+            }
+        }
+
+        // the norm 3^2 + 1^2 = 10 is a non-residue in Fp, so 3 + u is a non-residue in Fp2
+        assert_eq!(None, Fp2::new(to_fp(&BigInt::from(3)), Fp::ONE).sqrt());
+    }
+
+    #[test]
+    fn computes_legendre_symbol() {
+        assert_eq!(0, Fp::ZERO.legendre());
+        assert_eq!(1, Fp::ONE.legendre());
+        assert_eq!(1, to_fp(&BigInt::from(4)).legendre());
+        // -1 is a non-residue since p = 3 mod 4
+        assert_eq!(-1, Fp::ONE.neg().legendre());
+    }
+}
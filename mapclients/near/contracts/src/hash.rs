@@ -1,11 +1,28 @@
-use std::ops::{Add, Mul, Sub};
-use std::str::FromStr;
 use near_sdk::env;
-use num::Integer;
-use num_bigint::{BigInt, Sign};
-use num_traits::{Zero, One};
+use num_bigint::BigInt;
 use crate::G1;
 use crate::crypto::sum_points;
+use crate::fp::Fp;
+
+// field constants below are precomputed in Montgomery form
+
+/// 3
+pub(crate) const CURVE_B: Fp = Fp::from_montgomery([0x7a17caa950ad28d7, 0x1f6ac17ae15521b9, 0x334bea4e696bd284, 0x2a1f6744ce179d8e]);
+
+/// 2203960485148121921418603742825762020974279258880205651966
+const HASH_CONST_1: Fp = Fp::from_montgomery([0x71930c11d782e155, 0xa6bb947cffbe3323, 0xaa303344d4741444, 0x2c3b3f0d26594943]);
+
+/// 4407920970296243842837207485651524041948558517760411303933
+const HASH_CONST_2: Fp = Fp::from_montgomery([0x3e424383c39ad5b9, 0x28ed3f00245fdc6a, 0x4a2e7e8c1e5ebdfa, 0x05b858f624573163]);
+
+/// 14592161914559516814830937163504850059130874104865215775126025263096817472389
+const HASH_CONST_3: Fp = Fp::from_montgomery([0xafd49a8c34aeae4c, 0xe0a8c73e1f684743, 0xb4ea4db753538a2d, 0x14cf9766d3bdd51d]);
+
+/// 4
+const HASH_CONST_4: Fp = Fp::from_montgomery([0x115482203dbf392d, 0x926242126eaa626a, 0xe16a48076063c052, 0x07c5909386eddc93]);
+
+/// 2^256 mod p
+const TWO_256_MOD_PRIME: Fp = Fp::from_montgomery([0xf32cfc5b538afa89, 0xb5e71911d44501fb, 0x47ab1eff0a417ff6, 0x06d89f71cab8351f]);
 
 pub(crate) fn hash_to_g1(message: &Vec<u8>) -> G1 {
     let t0 = hash_to_base(message, 0x00, 0x01);
@@ -21,58 +38,33 @@ pub(crate) fn hash_to_g1(message: &Vec<u8>) -> G1 {
     h
 }
 
-fn hash_to_base(msg: &Vec<u8>, dsp0: u8, dsp1: u8) -> BigInt {
-    let prime = prime();
-
+fn hash_to_base(msg: &[u8], dsp0: u8, dsp1: u8) -> Fp {
     let data0 = [vec![dsp0], msg.to_vec()].concat();
-    let hash0 = BigInt::from_bytes_be(Sign::Plus, env::keccak256(data0.as_slice()).as_slice());
+    let hash0 = Fp::from_be_bytes_reduced(&keccak256(data0.as_slice()));
 
     let data1 = [vec![dsp1], msg.to_vec()].concat();
-    let hash1 = BigInt::from_bytes_be(Sign::Plus, env::keccak256(data1.as_slice()).as_slice());
+    let hash1 = Fp::from_be_bytes_reduced(&keccak256(data1.as_slice()));
 
-    let hash0 = hash0.mul(two_256_mod_prime());
-    let hash0 = hash0.mod_floor(&prime);
-    let hash1 = hash1.mod_floor(&prime);
-
-    hash0.add(&hash1).mod_floor(&prime)
+    hash0.mul(&TWO_256_MOD_PRIME).add(&hash1)
 }
 
-fn base_to_g1(t: &BigInt) -> G1 {
-    let one = BigInt::one();
-    let two = BigInt::from(2);
-    let three = BigInt::from(3);
-    let prime = prime();
-    let curve_b = curve_b();
-
-    let ap1 = t.modpow(&two, &prime);
-    let ap2 = ap1.clone().add(&hash_const_4()).mod_floor(&prime);
+fn base_to_g1(t: &Fp) -> G1 {
+    let ap1 = t.square();
+    let ap2 = ap1.add(&HASH_CONST_4);
 
-    let alpha = ap1.clone().mul(&ap2).mod_floor(&prime);
-    let alpha = invert(&alpha);
+    let alpha = ap1.mul(&ap2).invert();
 
-    let tmp = ap2.modpow(&three, &prime);
-    let ap1 = ap1.modpow(&two, &prime);
+    let tmp = ap2.cube();
+    let ap1 = ap1.square();
 
-    let x1 = hash_const_2().mul(&ap1).mod_floor(&prime);
-    let x1 = x1.mul(&alpha).mod_floor(&prime);
-    let x1 = neg(&x1);
-    let x1 = x1.add(&hash_const_1()).mod_floor(&prime);
+    let x1 = HASH_CONST_2.mul(&ap1).mul(&alpha).neg().add(&HASH_CONST_1);
 
-    let x2 = x1.clone().add(&one).mod_floor(&prime);
-    let x2 = neg(&x2);
+    let x2 = x1.add(&Fp::ONE).neg();
 
-    let x3 = hash_const_3().mul(&tmp).mod_floor(&prime);
-    let x3 = x3.mul(&alpha).mod_floor(&prime);
-    let x3 = neg(&x3);
-    let x3 = x3.add(&one).mod_floor(&prime);
+    let x3 = HASH_CONST_3.mul(&tmp).mul(&alpha).neg().add(&Fp::ONE);
 
-    let y = x1.modpow(&three, &prime);
-    let y = y.add(&curve_b).mod_floor(&prime);
-    let residue1 = legendre(&y);
-
-    let y = x2.modpow(&three, &prime);
-    let y = y.add(&curve_b).mod_floor(&prime);
-    let residue2 = legendre(&y);
+    let residue1 = x1.cube().add(&CURVE_B).legendre();
+    let residue2 = x2.cube().add(&CURVE_B).legendre();
 
     let i = (residue1 - 1) * (residue2 - 3) / 4 + 1;
     let x = if i == 1 {
@@ -83,130 +75,172 @@ fn base_to_g1(t: &BigInt) -> G1 {
         x3
     };
 
-    let y = x.modpow(&three, &prime);
-    let y = y.add(&curve_b).mod_floor(&prime);
-    let y = sqrt(&y);
-
-    let y_sign = sign0(t);
-    let y = y.mul(&y_sign).mod_floor(&prime);
+    let y = x.cube().add(&CURVE_B).sqrt();
+    let y = if t.is_lexicographically_largest() { y.neg() } else { y };
 
-    let point = G1::from(&x, &y);
+    let point = G1 { x: x.to_be_bytes(), y: y.to_be_bytes() };
     assert!(bn256_g1_is_on_curve(&point), "Invalid point: not on elliptic curve");
 
     point
-
-}
-
-fn curve_b() -> BigInt {
-    BigInt::from(3)
-}
-
-
-fn hash_const_1() -> BigInt {
-    BigInt::from_str("2203960485148121921418603742825762020974279258880205651966").unwrap()
-}
-
-fn hash_const_2() -> BigInt {
-    BigInt::from_str("4407920970296243842837207485651524041948558517760411303933").unwrap()
-}
-
-fn hash_const_3() -> BigInt {
-    BigInt::from_str("14592161914559516814830937163504850059130874104865215775126025263096817472389").unwrap()
-}
-
-fn hash_const_4() -> BigInt {
-    BigInt::from(4)
-}
-
-fn two_256_mod_prime() -> BigInt {
-    BigInt::from_str("6350874878119819312338956282401532409788428879151445726012394534686998597021").unwrap()
-}
-
-pub(crate) fn prime() -> BigInt {
-    BigInt::from_str("21888242871839275222246405745257275088696311157297823662689037894645226208583").unwrap()
-}
-
-fn p_minus_1() -> BigInt {
-    BigInt::from_str("21888242871839275222246405745257275088696311157297823662689037894645226208582").unwrap()
 }
 
-fn p_minus_2() -> BigInt {
-    BigInt::from_str("21888242871839275222246405745257275088696311157297823662689037894645226208581").unwrap()
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(env::keccak256(data).as_slice());
+    hash
 }
 
-fn p_minus_10_over_2() -> BigInt {
-    BigInt::from_str("10944121435919637611123202872628637544348155578648911831344518947322613104291").unwrap()
-}
-
-fn p_plus_10_over_4() -> BigInt {
-    BigInt::from_str("5472060717959818805561601436314318772174077789324455915672259473661306552146").unwrap()
-}
-
-fn invert(t: &BigInt) -> BigInt {
-    t.modpow(&p_minus_2(), &prime())
-}
-
-fn neg(t: &BigInt) -> BigInt {
-    if t.eq(&BigInt::zero()) {
-        BigInt::zero()
-    } else {
-        prime().sub(t)
-    }
-}
-
-fn legendre(t: &BigInt) -> i32 {
-    let s = t.modpow(&p_minus_10_over_2(), &prime());
-    if s.eq(&BigInt::zero()) {
-        0
-    } else if s.bit(0) {
-        1
-    } else {
-        -1
-    }
-}
-
-fn sqrt(t: &BigInt) -> BigInt {
-    t.modpow(&p_plus_10_over_4(), &prime())
-}
-
-fn sign0(t: &BigInt) -> BigInt {
-    if t.gt(&p_minus_10_over_2()) {
-        p_minus_1()
-    } else {
-        BigInt::one()
+pub(crate) fn bn256_g1_is_on_curve(point: &G1) -> bool {
+    match (Fp::from_be_bytes(&point.x), Fp::from_be_bytes(&point.y)) {
+        (Some(x), Some(y)) => y.square() == x.cube().add(&CURVE_B),
+        _ => false,
     }
 }
 
-fn bn256_g1_is_on_curve(point: &G1) -> bool {
-    let prime = prime();
-    let x = BigInt::from_bytes_be(Sign::Plus, point.x.as_slice());
-    let y = BigInt::from_bytes_be(Sign::Plus, point.y.as_slice());
-    let left = y.modpow(&BigInt::from(2), &prime);
-    let right = x.modpow(&BigInt::from(3), &prime);
-    let right = right.add(&curve_b()).mod_floor(&prime);
-    left.eq(&right)
-}
-
 fn safe_signing_point(point: &G1) -> bool {
-    let x = BigInt::from_bytes_be(Sign::Plus, point.x.as_slice());
+    let mut one = [0u8; 32];
+    one[31] = 1;
 
-    !(x.eq(&BigInt::zero()) || x.eq(&BigInt::one()))
+    !(point.x == [0u8; 32] || point.x == one)
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 #[cfg(test)]
 mod tests {
     use super::*;
+    use num_bigint::Sign;
+
+    /// The arbitrary precision implementation this module used before, kept to check
+    /// that the Montgomery one produces the same points.
+    mod reference {
+        use std::ops::{Add, Mul, Sub};
+        use std::str::FromStr;
+        use near_sdk::env;
+        use num::Integer;
+        use num_bigint::{BigInt, Sign};
+        use num_traits::{One, Zero};
+        use crate::G1;
+
+        pub fn hash_to_base(msg: &[u8], dsp0: u8, dsp1: u8) -> BigInt {
+            let prime = prime();
+
+            let data0 = [vec![dsp0], msg.to_vec()].concat();
+            let hash0 = BigInt::from_bytes_be(Sign::Plus, env::keccak256(data0.as_slice()).as_slice());
+
+            let data1 = [vec![dsp1], msg.to_vec()].concat();
+            let hash1 = BigInt::from_bytes_be(Sign::Plus, env::keccak256(data1.as_slice()).as_slice());
+
+            let hash0 = hash0.mul(two_256_mod_prime());
+            let hash0 = hash0.mod_floor(&prime);
+            let hash1 = hash1.mod_floor(&prime);
+
+            hash0.add(&hash1).mod_floor(&prime)
+        }
+
+        pub fn base_to_g1(t: &BigInt) -> G1 {
+            let one = BigInt::one();
+            let two = BigInt::from(2);
+            let three = BigInt::from(3);
+            let prime = prime();
+            let curve_b = BigInt::from(3);
+
+            let ap1 = t.modpow(&two, &prime);
+            let ap2 = ap1.clone().add(&BigInt::from(4)).mod_floor(&prime);
+
+            let alpha = ap1.clone().mul(&ap2).mod_floor(&prime);
+            let alpha = alpha.modpow(&prime.clone().sub(BigInt::from(2)), &prime);
+
+            let tmp = ap2.modpow(&three, &prime);
+            let ap1 = ap1.modpow(&two, &prime);
+
+            let x1 = hash_const(2).mul(&ap1).mod_floor(&prime);
+            let x1 = x1.mul(&alpha).mod_floor(&prime);
+            let x1 = neg(&x1);
+            let x1 = x1.add(&hash_const(1)).mod_floor(&prime);
+
+            let x2 = x1.clone().add(&one).mod_floor(&prime);
+            let x2 = neg(&x2);
+
+            let x3 = hash_const(3).mul(&tmp).mod_floor(&prime);
+            let x3 = x3.mul(&alpha).mod_floor(&prime);
+            let x3 = neg(&x3);
+            let x3 = x3.add(&one).mod_floor(&prime);
+
+            let y = x1.modpow(&three, &prime).add(&curve_b).mod_floor(&prime);
+            let residue1 = legendre(&y);
+
+            let y = x2.modpow(&three, &prime).add(&curve_b).mod_floor(&prime);
+            let residue2 = legendre(&y);
+
+            let i = (residue1 - 1) * (residue2 - 3) / 4 + 1;
+            let x = if i == 1 {
+                x1
+            } else if i == 2 {
+                x2
+            } else {
+                x3
+            };
+
+            let y = x.modpow(&three, &prime).add(&curve_b).mod_floor(&prime);
+            let y = y.modpow(&prime.clone().add(BigInt::one()).div_floor(&BigInt::from(4)), &prime);
+
+            let half = prime.clone().sub(BigInt::one()).div_floor(&two);
+            let y_sign = if t.gt(&half) { prime.clone().sub(BigInt::one()) } else { one };
+            let y = y.mul(&y_sign).mod_floor(&prime);
+
+            G1::from(&x, &y)
+        }
+
+        fn hash_const(i: u8) -> BigInt {
+            let s = match i {
+                1 => "2203960485148121921418603742825762020974279258880205651966",
+                2 => "4407920970296243842837207485651524041948558517760411303933",
+                _ => "14592161914559516814830937163504850059130874104865215775126025263096817472389",
+            };
+            BigInt::from_str(s).unwrap()
+        }
+
+        fn two_256_mod_prime() -> BigInt {
+            BigInt::from_str("6350874878119819312338956282401532409788428879151445726012394534686998597021").unwrap()
+        }
+
+        fn prime() -> BigInt {
+            BigInt::from_str("21888242871839275222246405745257275088696311157297823662689037894645226208583").unwrap()
+        }
+
+        fn neg(t: &BigInt) -> BigInt {
+            if t.eq(&BigInt::zero()) {
+                BigInt::zero()
+            } else {
+                prime().sub(t)
+            }
+        }
+
+        fn legendre(t: &BigInt) -> i32 {
+            let s = t.modpow(&prime().sub(BigInt::one()).div_floor(&BigInt::from(2)), &prime());
+            if s.eq(&BigInt::zero()) {
+                0
+            } else if s.bit(0) {
+                1
+            } else {
+                -1
+            }
+        }
+    }
+
+    fn to_int(t: &Fp) -> BigInt {
+        BigInt::from_bytes_be(Sign::Plus, &t.to_be_bytes())
+    }
 
     #[test]
     fn tets_hash_to_g1() {
         let message = hex::decode("6162636566676869").unwrap();
 
         let t0 = hash_to_base(&message, 0x00, 0x01);
-        assert_eq!("5910564439876404922092592771818717274376490012008896503918548302548484008557", t0.to_str_radix(10));
+        assert_eq!("5910564439876404922092592771818717274376490012008896503918548302548484008557", to_int(&t0).to_str_radix(10));
 
         let t1 = hash_to_base(&message, 0x02, 0x03);
-        assert_eq!("7054493174779650031422375588220690385584759055599915892200652159233194526041", t1.to_str_radix(10));
+        assert_eq!("7054493174779650031422375588220690385584759055599915892200652159233194526041", to_int(&t1).to_str_radix(10));
 
         let h0 = base_to_g1(&t0);
         assert_eq!("121985ee4f2f943a60920c2ff2490bd3b7d4d75ae45ea43748366fdae28b8a9c", hex::encode(h0.x));
@@ -216,4 +250,37 @@ mod tests {
         assert_eq!("07a76e4c4a4f342dcd6913ef3e4869fee67d1059a765e88bec4739f96b7ffa62", hex::encode(h1.x));
         assert_eq!("19bd3f8fc5f0cad616861c37306973bb5ac1eaa9362489ef4ea52e6438792ec6", hex::encode(h1.y));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn matches_reference_implementation() {
+        let mut messages = vec![vec![], hex::decode("6162636566676869").unwrap()];
+        // commited seals are the 32 bytes block hash, the round and the commit message code
+        for i in 0..64u8 {
+            messages.push([env::keccak256(&[i]), vec![i, 2]].concat());
+        }
+
+        for message in messages.iter() {
+            for &(dsp0, dsp1) in [(0x00, 0x01), (0x02, 0x03)].iter() {
+                let t = hash_to_base(message, dsp0, dsp1);
+                let expected = reference::hash_to_base(message, dsp0, dsp1);
+                assert_eq!(expected, to_int(&t));
+
+                let point = base_to_g1(&t);
+                assert_eq!(reference::base_to_g1(&expected), point);
+            }
+        }
+    }
+
+    #[test]
+    fn checks_point_on_curve() {
+        let message = hex::decode("6162636566676869").unwrap();
+        let point = hash_to_g1(&message);
+        assert!(bn256_g1_is_on_curve(&point));
+        assert!(bn256_g1_is_on_curve(&point.neg()));
+
+        let mut y = point.y;
+        y[31] ^= 1;
+        assert!(!bn256_g1_is_on_curve(&G1 { x: point.x, y }));
+        assert!(!bn256_g1_is_on_curve(&G1 { x: [0xff; 32], y: point.y }));
+    }
+}
@@ -5,37 +5,91 @@ use std::collections::HashSet;
 pub use types::*;
 mod serialization;
 pub use serialization::*;
+mod bls12381;
 mod crypto;
+mod fp;
 mod hash;
 mod macros;
 pub mod traits;
+mod zk;
 
-use crate::crypto::{check_aggregated_g2_pub_key, check_sealed_signature};
+use crate::bls12381::{aggregate_g2, verify_possession, verify_signature, BLS12381_G2_LENGTH};
+use crate::crypto::{
+    check_aggregated_g2_pub_key, check_g2_pub_key, check_sealed_signature, prepare_commited_seal,
+};
+use crate::hash::bn256_g1_is_on_curve;
 use crate::types::header::Address;
-use crate::types::proof::{verify_trie_proof, ReceiptProof};
-use crate::types::{header::Header, istanbul::get_epoch_number, istanbul::IstanbulExtra};
+use crate::types::bloom::{contains_bloom, logs_bloom};
+use crate::types::proof::{
+    get_trie_value, verify_trie_proof, ReceiptAbsenceProof, ReceiptProof, ReceiptStatus, RlpBytes,
+    RlpReceiptProof, RpcReceiptProof,
+};
+use crate::traits::FromRlp;
+use crate::types::{
+    header::Header, header::RpcHeader, istanbul::get_epoch_number, istanbul::IstanbulExtra,
+    istanbul::SealVersion, istanbul::SignatureScheme,
+};
+pub use bls12381::{Bls12381G1, Bls12381G2};
 pub use crypto::{G1, G2, REGISTER_EXPECTED_ERR};
+use crate::zk::{header_public_inputs, verify_groth16};
+pub use zk::{Groth16Proof, VerifyingKey};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::UnorderedMap;
+use near_sdk::collections::{LazyOption, UnorderedMap, UnorderedSet};
 use near_sdk::env::keccak256;
-use near_sdk::json_types::{Base64VecU8, U64};
+use near_sdk::json_types::{Base64VecU8, U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::{env, log, near_bindgen, serde_json, AccountId, Gas, PanicOnDefault};
+use near_sdk::{
+    env, is_promise_success, log, near_bindgen, serde_json, AccountId, Balance, Gas,
+    PanicOnDefault, Promise,
+};
 use num::cast::ToPrimitive;
 use num_bigint::BigInt as Integer;
 
 const ECDSA_SIG_LENGTH: usize = 65;
 const ECDSA_REGISTER: u64 = 2;
 const GAS_FOR_UPGRADE_SELF_DEPLOY: Gas = Gas(15_000_000_000_000);
+const GAS_FOR_FINISH_WITHDRAW_REWARD: Gas = Gas(5_000_000_000_000);
+const MAP_MAINNET_CHAIN_ID: u128 = 22776;
+/// Storage in bytes of the reward entry of an account ID of the maximum length.
+const REWARD_STORAGE: u64 = 302;
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct MapLightClient {
+    chains: UnorderedMap<u128, ChainState>,
+    default_chain_id: u128,
+    owner: AccountId,
+    zk_verifying_key: LazyOption<VerifyingKey>,
+    /// Deposit in yoctoNEAR required for each verification, 0 means free.
+    verification_fee: Balance,
+    /// Verification fees earned by relayers and not withdrawn yet.
+    rewards: UnorderedMap<AccountId, Balance>,
+    /// Accounts allowed to verify proofs when the client is restricted.
+    consumers: UnorderedSet<AccountId>,
+    /// Only `consumers` may verify proofs if set, anyone may otherwise.
+    restricted: bool,
+    /// Number of chains added by `add_chain`, used as their storage prefix so that a prefix
+    /// is never reused after a chain id is updated.
+    added_chains: u64,
+}
+
+/// The epoch state of a single MAP network hosted by the light client.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct ChainState {
     epoch_records: UnorderedMap<u64, EpochRecord>,
     epoch_size: u64,
     header_height: u64,
-    owner: AccountId,
     max_records: u64,
+    /// Block timestamp (ns) of the last accepted header.
+    last_update_timestamp: u64,
+    /// Maximum time (ns) since the last accepted header before proofs are refused, 0 means unlimited.
+    max_staleness: u64,
+    /// Consensus format changes sorted by activation height.
+    forks: Vec<Fork>,
+    /// BLS12-381 public keys registered by the validators, by validator address.
+    bls12381_keys: UnorderedMap<Address, Bls12381G2>,
+    /// Accounts which submitted the header that started an epoch, earning its verification fees.
+    relayers: UnorderedMap<u64, AccountId>,
 }
 
 #[derive(BorshDeserialize, BorshSerialize)]
@@ -44,6 +98,7 @@ pub struct MapLightClientV1 {
     epoch_size: u64,
     header_height: u64,
     owner: AccountId,
+    max_records: u64,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
@@ -54,6 +109,41 @@ pub struct EpochRecord {
     pub validators: Vec<Validator>,
 }
 
+/// A change of the consensus format of a MAP network, effective from `height` on.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Fork {
+    pub height: U64,
+    pub seal_version: SealVersion,
+    #[serde(default)]
+    pub signature_scheme: SignatureScheme,
+}
+
+/// Registration of the BLS12-381 public key of a MAP validator.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Bls12381KeyRegistration {
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub validator: Address,
+    pub key: Bls12381G2,
+    /// Proof of possession of the key, the signature of the uncompressed key by itself.
+    pub pop: Bls12381G1,
+    /// ECDSA signature `r || s || v` (v is 0 or 1) of the validator over
+    /// `keccak256(chain id (16 bytes, big endian) || validator || key)`.
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub signature: Vec<u8>,
+}
+
+impl Default for Fork {
+    fn default() -> Self {
+        Self {
+            height: U64(0),
+            seal_version: SealVersion::default(),
+            signature_scheme: SignatureScheme::default(),
+        }
+    }
+}
+
 #[derive(
     BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq,
 )]
@@ -65,17 +155,14 @@ pub struct Validator {
     address: Address,
 }
 
-#[near_bindgen]
-impl MapLightClient {
-    #[init]
-    pub fn new(
+impl ChainState {
+    fn new(
+        prefix: Vec<u8>,
         threshold: U64,
         validators: Vec<Validator>,
         epoch: U64,
         epoch_size: U64,
-        owner: AccountId,
     ) -> Self {
-        assert!(!Self::initialized(), "already initialized");
         assert_ne!(0, validators.len(), "empty validators!");
         assert_ne!(0, threshold.0, "threashold should not be 0");
 
@@ -101,7 +188,7 @@ impl MapLightClient {
             "threashold should not greater than validators' total weight"
         );
 
-        let mut val_records = UnorderedMap::new(b"v".to_vec());
+        let mut val_records = UnorderedMap::new(prefix.clone());
         val_records.insert(
             &epoch.0,
             &EpochRecord {
@@ -111,29 +198,203 @@ impl MapLightClient {
             },
         );
 
-        let max_records = 30 * 3 * 24 * 60 * 60 * 2 / 9 / epoch_size.0; // keep records for 3 months
-
         Self {
             epoch_records: val_records,
             epoch_size: epoch_size.into(),
             header_height: (epoch.0 - 1) * epoch_size.0,
+            max_records: max_records_for(epoch_size.0),
+            last_update_timestamp: env::block_timestamp(),
+            max_staleness: 0,
+            forks: Vec::new(),
+            bls12381_keys: UnorderedMap::new([prefix.clone(), b"b".to_vec()].concat()),
+            relayers: UnorderedMap::new([prefix, b"r".to_vec()].concat()),
+        }
+    }
+
+    /// Returns the latest fork activated at the block, the original format before any fork.
+    fn fork_at(&self, block_num: u64) -> Fork {
+        self.forks
+            .iter()
+            .rev()
+            .find(|fork| fork.height.0 <= block_num)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn is_stale(&self) -> bool {
+        self.max_staleness != 0
+            && env::block_timestamp() > self.last_update_timestamp.saturating_add(self.max_staleness)
+    }
+
+    fn get_verifiable_header_range(&self) -> (U64, U64) {
+        let count = self.epoch_records.len() * self.epoch_size;
+        let begin = self.header_height + self.epoch_size + 1 - count;
+        let end = self.header_height + self.epoch_size;
+        (begin.into(), end.into())
+    }
+}
+
+/// Keep records for 3 months.
+fn max_records_for(epoch_size: u64) -> u64 {
+    30 * 3 * 24 * 60 * 60 * 2 / 9 / epoch_size
+}
+
+/// More than two thirds of the validators' total weight.
+fn epoch_threshold(validators: &[Validator]) -> U64 {
+    let total_weight: u64 = validators.iter().map(|x| x.weight.0).sum();
+    U64(total_weight - total_weight / 3)
+}
+
+/// Returns the validators added by the `IstanbulExtra` with weight 1, after checking that
+/// their G1 and G2 public keys match.
+fn added_validators(extra: &IstanbulExtra) -> Vec<Validator> {
+    assert_eq!(
+        extra.added_validators.len(),
+        extra.added_g1_public_keys.len(),
+        "the number of added validators and g1 public keys are not equal"
+    );
+    assert_eq!(
+        extra.added_validators.len(),
+        extra.added_public_keys.len(),
+        "the number of added validators and g2 public keys are not equal"
+    );
+
+    extra
+        .added_g1_public_keys
+        .iter()
+        .zip(extra.added_public_keys.iter())
+        .zip(extra.added_validators.iter())
+        .map(|((g1_key, g2_key), address)| {
+            let g1_pub_key = G1::from_slice(g1_key).unwrap();
+            let g2_pub_key = G2::from_slice(g2_key).unwrap();
+            assert!(
+                bn256_g1_is_on_curve(&g1_pub_key),
+                "g1 public key of added validator 0x{} is not on curve",
+                hex::encode(address)
+            );
+            assert!(
+                check_g2_pub_key(&g1_pub_key, &g2_pub_key),
+                "g1 and g2 public keys of added validator 0x{} do not match",
+                hex::encode(address)
+            );
+
+            Validator {
+                g1_pub_key,
+                weight: U64(1),
+                address: *address,
+            }
+        })
+        .collect()
+}
+
+#[near_bindgen]
+impl MapLightClient {
+    /// Initializes the light client with its first MAP network. `chain_id` defaults to
+    /// MAP mainnet and is used whenever a method is called without a chain id.
+    #[init]
+    pub fn new(
+        threshold: U64,
+        validators: Vec<Validator>,
+        epoch: U64,
+        epoch_size: U64,
+        owner: AccountId,
+        chain_id: Option<U128>,
+    ) -> Self {
+        assert!(!Self::initialized(), "already initialized");
+
+        let chain_id = chain_id.map_or(MAP_MAINNET_CHAIN_ID, |id| id.0);
+        let mut chains = UnorderedMap::new(b"m".to_vec());
+        chains.insert(
+            &chain_id,
+            &ChainState::new(b"v".to_vec(), threshold, validators, epoch, epoch_size),
+        );
+
+        Self {
+            chains,
+            default_chain_id: chain_id,
             owner,
-            max_records,
+            zk_verifying_key: LazyOption::new(b"z".to_vec(), None),
+            verification_fee: 0,
+            rewards: UnorderedMap::new(b"r".to_vec()),
+            consumers: UnorderedSet::new(b"u".to_vec()),
+            restricted: false,
+            added_chains: 0,
         }
     }
 
+    /// Initializes the light client from the genesis header, the last block of epoch 0. Later
+    /// epoch headers only carry the changes to the validator set, while the `IstanbulExtra` of
+    /// the genesis header adds all validators of epoch 1. They are derived from it with weight 1
+    /// after their G1 and G2 public keys are cross-checked, and the threshold is
+    /// `total - total / 3` of their weights as for every following epoch. Relayers then catch
+    /// up from the last block of epoch 1 with `update_block_header`.
+    #[init]
+    pub fn new_from_epoch_header(
+        header: Header,
+        epoch_size: U64,
+        owner: AccountId,
+        chain_id: Option<U128>,
+    ) -> Self {
+        let block_num = header.number.to_u64().unwrap();
+        assert_eq!(0, block_num, "block {} is not the genesis block", block_num);
+
+        let extra =
+            IstanbulExtra::from_rlp(&header.extra).expect("failed to decode istanbul extra");
+        assert_eq!(
+            Integer::default(),
+            extra.removed_validators,
+            "genesis header should not remove validators"
+        );
+        let validators = added_validators(&extra);
+        log!(
+            "initialize from genesis header 0x{} with {} validators",
+            hex::encode(header.hash().unwrap()),
+            validators.len()
+        );
+
+        Self::new(
+            epoch_threshold(&validators),
+            validators,
+            U64(1),
+            epoch_size,
+            owner,
+            chain_id,
+        )
+    }
+
+    /// Migrates a single-network deployment, whose network is keyed as MAP mainnet. The
+    /// deployed `upgrade_client` calls it without arguments, so the owner of a deployment
+    /// tracking another network must call `update_chain_id` right after the upgrade.
     #[private]
     #[init(ignore_state)]
     pub fn migrate_client() -> Self {
-        let oldClient: MapLightClientV1 =
+        let old_client: MapLightClientV1 =
             env::state_read().expect("ERR_CONTRACT_IS_NOT_INITIALIZED");
-        let max_records = 30 * 3 * 24 * 60 * 60 * 2 / 9 / oldClient.epoch_size;
+        let mut chains = UnorderedMap::new(b"m".to_vec());
+        chains.insert(
+            &MAP_MAINNET_CHAIN_ID,
+            &ChainState {
+                epoch_records: old_client.epoch_records,
+                epoch_size: old_client.epoch_size,
+                header_height: old_client.header_height,
+                max_records: old_client.max_records,
+                last_update_timestamp: env::block_timestamp(),
+                max_staleness: 0,
+                forks: Vec::new(),
+                bls12381_keys: UnorderedMap::new(b"vb".to_vec()),
+                relayers: UnorderedMap::new(b"vr".to_vec()),
+            },
+        );
         Self {
-            epoch_records: oldClient.epoch_records,
-            epoch_size: oldClient.epoch_size,
-            header_height: oldClient.header_height,
-            owner: oldClient.owner,
-            max_records,
+            chains,
+            default_chain_id: MAP_MAINNET_CHAIN_ID,
+            owner: old_client.owner,
+            zk_verifying_key: LazyOption::new(b"z".to_vec(), None),
+            verification_fee: 0,
+            rewards: UnorderedMap::new(b"r".to_vec()),
+            consumers: UnorderedSet::new(b"u".to_vec()),
+            restricted: false,
+            added_chains: 0,
         }
     }
 
@@ -141,46 +402,113 @@ impl MapLightClient {
         env::state_read::<MapLightClient>().is_some()
     }
 
-    pub fn update_block_header(&mut self, header: &Header, agg_pk: G2) {
-        let block_num = header.number.to_u64().unwrap();
-        let block_exp = self.header_height + self.epoch_size;
-        assert_eq!(block_exp, block_num, "block header height is incorrect");
+    /// Starts tracking another MAP network from the given epoch.
+    pub fn add_chain(
+        &mut self,
+        chain_id: U128,
+        threshold: U64,
+        validators: Vec<Validator>,
+        epoch: U64,
+        epoch_size: U64,
+    ) {
+        self.assert_owner();
+        assert!(
+            self.chains.get(&chain_id.0).is_none(),
+            "chain {} already exists",
+            chain_id.0
+        );
+
+        let mut prefix = b"c".to_vec();
+        prefix.extend_from_slice(&self.added_chains.to_le_bytes());
+        self.added_chains += 1;
+        self.chains.insert(
+            &chain_id.0,
+            &ChainState::new(prefix, threshold, validators, epoch, epoch_size),
+        );
+    }
+
+    /// Moves the state of a MAP network to another chain id, e.g. to correct the id
+    /// assigned to the chain migrated from a single-network deployment.
+    pub fn update_chain_id(&mut self, chain_id: U128, new_chain_id: U128) {
+        self.assert_owner();
+        assert!(
+            self.chains.get(&new_chain_id.0).is_none(),
+            "chain {} already exists",
+            new_chain_id.0
+        );
 
+        let chain = self.chain(Some(chain_id));
+        self.chains.remove(&chain_id.0);
+        self.chains.insert(&new_chain_id.0, &chain);
+        if self.default_chain_id == chain_id.0 {
+            self.default_chain_id = new_chain_id.0;
+        }
+    }
+
+    pub fn update_block_header(&mut self, header: &Header, agg_pk: G2, chain_id: Option<U128>) {
         // check ecdsa and bls signature
-        let epoch = get_epoch_number(block_num, self.epoch_size as u64);
-        let mut extra = IstanbulExtra::from_rlp(&header.extra).unwrap();
-        let cur_epoch_record = &self.epoch_records.get(&epoch).unwrap();
-        self.verify_signatures(header, agg_pk, &extra, cur_epoch_record);
+        self.update_epoch(header, chain_id, |client, chain, extra, epoch_record| {
+            client.verify_signatures(chain, header, agg_pk, extra, epoch_record)
+        })
+    }
 
-        // update validators' pair keys
-        self.update_next_validators(cur_epoch_record, &mut extra);
+    /// Same as `update_block_header`, but the quorum signatures of the header are attested by
+    /// a Groth16 proof checked against the verifying key registered by the owner.
+    pub fn update_block_header_zk(
+        &mut self,
+        header: Header,
+        proof: Groth16Proof,
+        chain_id: Option<U128>,
+    ) {
+        let vk = self
+            .zk_verifying_key
+            .get()
+            .expect("zk verifying key is not set");
+        self.update_epoch(&header, chain_id, |_, _, _, epoch_record| {
+            let header_hash = header.hash().unwrap();
+            let inputs = header_public_inputs(&epoch_record.validators, &header_hash);
+            assert!(
+                verify_groth16(&vk, &proof, &inputs),
+                "check zk proof failed"
+            )
+        })
+    }
 
-        self.header_height = block_num;
+    /// Same as `update_block_header`, but takes the header in its RLP encoding.
+    pub fn update_block_header_rlp(
+        &mut self,
+        header: RlpBytes,
+        agg_pk: G2,
+        chain_id: Option<U128>,
+    ) {
+        let header = Header::from_rlp(&header.0).expect("failed to decode header from rlp");
+        self.update_block_header(&header, agg_pk, chain_id)
+    }
 
-        log!(
-            "block header {} is updated for the next epoch {} by {}",
-            block_num,
-            epoch + 1,
-            env::signer_account_id()
-        )
+    /// Same as `update_block_header`, but takes the header as returned by `eth_getBlockByNumber`.
+    pub fn update_block_header_rpc(
+        &mut self,
+        header: RpcHeader,
+        agg_pk: G2,
+        chain_id: Option<U128>,
+    ) {
+        self.update_block_header(&header.into(), agg_pk, chain_id)
     }
 
-    pub fn verify_proof_data(&self, receipt_proof: ReceiptProof) {
+    /// Verifies that the receipt is included in a header signed by a quorum of validators and
+    /// returns the receipt status. In `strict` mode receipts of failed transactions are rejected.
+    /// The verification fee must be attached, it is paid to the relayer of the header's epoch.
+    #[payable]
+    pub fn verify_proof_data(
+        &mut self,
+        receipt_proof: ReceiptProof,
+        chain_id: Option<U128>,
+        strict: Option<bool>,
+    ) -> ReceiptStatus {
+        self.assert_consumer();
         let header = &receipt_proof.header;
-        let extra = IstanbulExtra::from_rlp(&header.extra).unwrap();
-
-        // check ecdsa and bls signature
-        let epoch = get_epoch_number(header.number.to_u64().unwrap(), self.epoch_size as u64);
-        let epoch_record = &self.epoch_records.get(&epoch).unwrap_or_else(|| {
-            let range = self.get_verifiable_header_range();
-            panic!(
-                "cannot get epoch record for block {}, expected range[{}, {}]",
-                header.number.to_string(),
-                range.0 .0,
-                range.1 .0
-            )
-        });
-        self.verify_signatures(header, receipt_proof.agg_pk, &extra, epoch_record);
+        let epoch = self.verify_verifiable_header(header, receipt_proof.agg_pk, chain_id);
+        self.charge_verification_fee(epoch, chain_id);
 
         // Verify receipt included into header
         let data = verify_trie_proof(
@@ -196,29 +524,451 @@ impl MapLightClient {
             hex::encode(data),
             "receipt data is not equal to the value in trie"
         );
+
+        let status = receipt_proof.receipt.status();
+        if strict.unwrap_or(false) {
+            assert_ne!(
+                ReceiptStatus::Failed,
+                status,
+                "receipt of a failed transaction is not accepted"
+            );
+        }
+
+        let receipt = &receipt_proof.receipt;
+        assert_eq!(
+            hex::encode(logs_bloom(&receipt.logs)),
+            hex::encode(receipt.bloom),
+            "receipt bloom does not match its logs"
+        );
+        assert!(
+            contains_bloom(&header.bloom, &receipt.bloom),
+            "header bloom does not contain the receipt bloom"
+        );
+
+        status
     }
 
-    pub fn get_verifiable_header_range(&self) -> (U64, U64) {
-        let count = self.epoch_records.len() * self.epoch_size;
-        let begin = self.header_height + self.epoch_size + 1 - count;
-        let end = self.header_height + self.epoch_size;
-        (begin.into(), end.into())
+    /// Same as `verify_proof_data`, but takes the header and receipt in their RLP encoding.
+    #[payable]
+    pub fn verify_proof_data_rlp(
+        &mut self,
+        receipt_proof: RlpReceiptProof,
+        chain_id: Option<U128>,
+        strict: Option<bool>,
+    ) -> ReceiptStatus {
+        let receipt_proof = receipt_proof
+            .decode()
+            .expect("failed to decode receipt proof from rlp");
+        self.verify_proof_data(receipt_proof, chain_id, strict)
+    }
+
+    /// Same as `verify_proof_data`, but takes the header and receipt as returned by
+    /// `eth_getBlockByNumber` and `eth_getTransactionReceipt`.
+    #[payable]
+    pub fn verify_proof_data_rpc(
+        &mut self,
+        receipt_proof: RpcReceiptProof,
+        chain_id: Option<U128>,
+        strict: Option<bool>,
+    ) -> ReceiptStatus {
+        self.verify_proof_data(receipt_proof.into(), chain_id, strict)
+    }
+
+    /// Verifies that the receipt trie of a header signed by a quorum of validators has no
+    /// receipt at `key_index`, e.g. to bound the number of receipts in the block. The verification
+    /// fee is charged like `verify_proof_data`.
+    #[payable]
+    pub fn verify_receipt_absent(
+        &mut self,
+        absence_proof: ReceiptAbsenceProof,
+        chain_id: Option<U128>,
+    ) {
+        self.assert_consumer();
+        let header = &absence_proof.header;
+        let epoch = self.verify_verifiable_header(header, absence_proof.agg_pk, chain_id);
+        self.charge_verification_fee(epoch, chain_id);
+
+        match get_trie_value(header.receipt_hash, &absence_proof.key_index, &absence_proof.proof) {
+            Ok(None) => (),
+            Ok(Some(_)) => panic!(
+                "receipt {} is included in block {}",
+                hex::encode(&absence_proof.key_index),
+                header.number
+            ),
+            Err(e) => panic!("invalid receipt trie proof: {:?}", e),
+        }
+    }
+
+    pub fn get_verifiable_header_range(&self, chain_id: Option<U128>) -> (U64, U64) {
+        self.chain(chain_id).get_verifiable_header_range()
+    }
+
+    pub fn get_header_height(&self, chain_id: Option<U128>) -> U64 {
+        self.chain(chain_id).header_height.into()
+    }
+
+    pub fn get_epoch_size(&self, chain_id: Option<U128>) -> U64 {
+        self.chain(chain_id).epoch_size.into()
+    }
+
+    pub fn get_record_for_epoch(&self, epoch: U64, chain_id: Option<U128>) -> Option<EpochRecord> {
+        self.chain(chain_id).epoch_records.get(&epoch.0)
+    }
+
+    /// Returns true if no header is accepted within the configured maximum staleness.
+    pub fn is_stale(&self, chain_id: Option<U128>) -> bool {
+        self.chain(chain_id).is_stale()
+    }
+
+    pub fn get_last_update_timestamp(&self, chain_id: Option<U128>) -> U64 {
+        self.chain(chain_id).last_update_timestamp.into()
+    }
+
+    pub fn get_max_staleness(&self, chain_id: Option<U128>) -> U64 {
+        self.chain(chain_id).max_staleness.into()
+    }
+
+    /// Sets the maximum time in nanoseconds since the last accepted header, after which
+    /// `verify_proof_data` refuses proofs. 0 disables the check.
+    pub fn set_max_staleness(&mut self, max_staleness: U64, chain_id: Option<U128>) {
+        self.assert_owner();
+
+        let chain_id = self.chain_id_or_default(chain_id);
+        let mut chain = self.chain(Some(chain_id.into()));
+        chain.max_staleness = max_staleness.0;
+        self.chains.insert(&chain_id, &chain);
+    }
+
+    /// Schedules a change of the consensus format from `fork.height` on. Forks must be added
+    /// in increasing order of height.
+    pub fn add_fork(&mut self, fork: Fork, chain_id: Option<U128>) {
+        self.assert_owner();
+
+        let chain_id = self.chain_id_or_default(chain_id);
+        let mut chain = self.chain(Some(chain_id.into()));
+        if let Some(last) = chain.forks.last() {
+            assert!(
+                fork.height.0 > last.height.0,
+                "fork height should be greater than {}",
+                last.height.0
+            );
+        }
+        chain.forks.push(fork);
+        self.chains.insert(&chain_id, &chain);
+    }
+
+    pub fn get_forks(&self, chain_id: Option<U128>) -> Vec<Fork> {
+        self.chain(chain_id).forks
     }
 
-    pub fn get_header_height(&self) -> U64 {
-        self.header_height.into()
+    /// Registers the BLS12-381 public key of a validator, used to verify seals under a
+    /// `Bls12381` fork. Anyone may submit the registration, which is signed by the validator and
+    /// proves the possession of the key. The key of a validator can not be replaced.
+    pub fn register_bls12381_key(
+        &mut self,
+        registration: Bls12381KeyRegistration,
+        chain_id: Option<U128>,
+    ) {
+        let chain_id = self.chain_id_or_default(chain_id);
+        let mut chain = self.chain(Some(chain_id.into()));
+        let validator = registration.validator;
+        assert!(
+            chain.bls12381_keys.get(&validator).is_none(),
+            "bls12381 key of validator 0x{} is already registered",
+            hex::encode(validator)
+        );
+        assert_eq!(
+            BLS12381_G2_LENGTH,
+            registration.key.0.len(),
+            "invalid bls12381 public key length"
+        );
+
+        let msg = [&chain_id.to_be_bytes()[..], &validator, &registration.key.0].concat();
+        assert_eq!(
+            validator,
+            ecrecover_address(&keccak256(&msg), &registration.signature),
+            "ecdsa signer is not the validator"
+        );
+        assert!(
+            verify_possession(&registration.pop, &registration.key),
+            "invalid proof of possession of the bls12381 key"
+        );
+
+        chain.bls12381_keys.insert(&validator, &registration.key);
+        self.chains.insert(&chain_id, &chain);
+    }
+
+    /// Returns the registered BLS12-381 public keys of the validators of an epoch, in the order
+    /// of its validators.
+    pub fn get_bls12381_keys(
+        &self,
+        epoch: U64,
+        chain_id: Option<U128>,
+    ) -> Option<Vec<Option<Bls12381G2>>> {
+        let chain = self.chain(chain_id);
+        chain.epoch_records.get(&epoch.0).map(|record| {
+            record
+                .validators
+                .iter()
+                .map(|validator| chain.bls12381_keys.get(&validator.address))
+                .collect()
+        })
+    }
+
+    /// Allows the account to verify proofs when the client is restricted.
+    pub fn add_consumer(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        assert!(
+            self.consumers.insert(&account_id),
+            "consumer {} already exists",
+            account_id
+        );
+    }
+
+    pub fn remove_consumer(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        assert!(
+            self.consumers.remove(&account_id),
+            "consumer {} does not exist",
+            account_id
+        );
+    }
+
+    /// Switches between the restricted mode, where only consumers may verify proofs, and the
+    /// open mode, where anyone may.
+    pub fn set_restricted(&mut self, restricted: bool) {
+        self.assert_owner();
+        self.restricted = restricted;
+    }
+
+    pub fn is_restricted(&self) -> bool {
+        self.restricted
+    }
+
+    pub fn get_consumers(&self, from_index: u64, limit: u64) -> Vec<AccountId> {
+        self.consumers.iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    /// Sets the deposit in yoctoNEAR required for each verification, 0 makes verification free.
+    /// A non-zero fee should cover the storage of a reward entry, which the first fee earned by
+    /// a relayer pays for.
+    pub fn set_verification_fee(&mut self, fee: U128) {
+        self.assert_owner();
+        let min_fee = Balance::from(REWARD_STORAGE) * env::storage_byte_cost();
+        assert!(
+            fee.0 == 0 || fee.0 >= min_fee,
+            "verification fee {} is less than the storage cost {} of a reward entry",
+            fee.0,
+            min_fee
+        );
+        self.verification_fee = fee.0;
+    }
+
+    pub fn get_verification_fee(&self) -> U128 {
+        self.verification_fee.into()
+    }
+
+    pub fn get_reward(&self, account_id: AccountId) -> U128 {
+        self.rewards.get(&account_id).unwrap_or(0).into()
+    }
+
+    /// Returns the relayer who submitted the header that started the epoch.
+    pub fn get_epoch_relayer(&self, epoch: U64, chain_id: Option<U128>) -> Option<AccountId> {
+        self.chain(chain_id).relayers.get(&epoch.0)
+    }
+
+    /// Transfers the verification fees earned by the caller, all of them if `amount` is not set.
+    pub fn withdraw_reward(&mut self, amount: Option<U128>) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let reward = self.rewards.get(&account_id).unwrap_or(0);
+        let amount = amount.map_or(reward, |a| a.0);
+        assert!(amount > 0, "nothing to withdraw");
+        assert!(
+            amount <= reward,
+            "reward of {} is {}, less than {}",
+            account_id,
+            reward,
+            amount
+        );
+
+        if amount == reward {
+            self.rewards.remove(&account_id);
+        } else {
+            self.rewards.insert(&account_id, &(reward - amount));
+        }
+        log!("{} withdraws reward {}", account_id, amount);
+
+        Promise::new(account_id.clone()).transfer(amount).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_FINISH_WITHDRAW_REWARD)
+                .finish_withdraw_reward(account_id, amount.into()),
+        )
+    }
+
+    /// Restores the reward withdrawn by `withdraw_reward` if its transfer failed.
+    #[private]
+    pub fn finish_withdraw_reward(&mut self, account_id: AccountId, amount: U128) {
+        if !is_promise_success() {
+            let reward = self.rewards.get(&account_id).unwrap_or(0);
+            self.rewards.insert(&account_id, &(reward + amount.0));
+            log!("failed to transfer reward {} to {}, restored", amount.0, account_id);
+        }
+    }
+
+    /// Registers the Groth16 verifying key used by `update_block_header_zk`, `None` disables it.
+    pub fn set_zk_verifying_key(&mut self, verifying_key: Option<VerifyingKey>) {
+        self.assert_owner();
+
+        match verifying_key {
+            Some(vk) => self.zk_verifying_key.set(&vk),
+            None => self.zk_verifying_key.remove(),
+        };
+    }
+
+    pub fn get_zk_verifying_key(&self) -> Option<VerifyingKey> {
+        self.zk_verifying_key.get()
+    }
+
+    pub fn get_chain_ids(&self) -> Vec<U128> {
+        self.chains.keys().map(|id| id.into()).collect()
+    }
+
+    pub fn get_default_chain_id(&self) -> U128 {
+        self.default_chain_id.into()
+    }
+
+    fn assert_owner(&self) {
+        assert_eq!(
+            self.owner,
+            env::predecessor_account_id(),
+            "unexpected caller {}",
+            env::predecessor_account_id()
+        );
     }
 
-    pub fn get_epoch_size(&self) -> U64 {
-        self.epoch_size.into()
+    fn assert_consumer(&self) {
+        let caller = env::predecessor_account_id();
+        assert!(
+            !self.restricted || self.consumers.contains(&caller),
+            "{} is not an authorized consumer",
+            caller
+        );
     }
 
-    pub fn get_record_for_epoch(&self, epoch: U64) -> Option<EpochRecord> {
-        self.epoch_records.get(&epoch.0)
+    fn chain_id_or_default(&self, chain_id: Option<U128>) -> u128 {
+        chain_id.map_or(self.default_chain_id, |id| id.0)
+    }
+
+    fn update_epoch<F>(&mut self, header: &Header, chain_id: Option<U128>, verify: F)
+    where
+        F: FnOnce(&Self, &ChainState, &IstanbulExtra, &EpochRecord),
+    {
+        let chain_id = self.chain_id_or_default(chain_id);
+        let mut chain = self.chain(Some(chain_id.into()));
+
+        let block_num = header.number.to_u64().unwrap();
+        let block_exp = chain.header_height + chain.epoch_size;
+        assert_eq!(block_exp, block_num, "block header height is incorrect");
+
+        let epoch = get_epoch_number(block_num, chain.epoch_size);
+        let mut extra = IstanbulExtra::from_rlp(&header.extra).unwrap();
+        let cur_epoch_record = &chain.epoch_records.get(&epoch).unwrap();
+        verify(self, &chain, &extra, cur_epoch_record);
+
+        // update validators' pair keys
+        self.update_next_validators(&mut chain, cur_epoch_record, &mut extra);
+
+        chain.header_height = block_num;
+        chain.last_update_timestamp = env::block_timestamp();
+        self.chains.insert(&chain_id, &chain);
+
+        log!(
+            "block header {} of chain {} is updated for the next epoch {} by {}",
+            block_num,
+            chain_id,
+            epoch + 1,
+            env::signer_account_id()
+        )
+    }
+
+    fn chain(&self, chain_id: Option<U128>) -> ChainState {
+        let chain_id = self.chain_id_or_default(chain_id);
+        self.chains
+            .get(&chain_id)
+            .unwrap_or_else(|| panic!("chain {} is not supported", chain_id))
+    }
+
+    /// Checks that the header is signed by a quorum of validators of an epoch the chain keeps,
+    /// and returns the epoch.
+    fn verify_verifiable_header(&self, header: &Header, agg_pk: G2, chain_id: Option<U128>) -> u64 {
+        let chain = self.chain(chain_id);
+        assert!(
+            !chain.is_stale(),
+            "light client is stale, the last header is updated at {}",
+            chain.last_update_timestamp
+        );
+
+        let extra = IstanbulExtra::from_rlp(&header.extra).unwrap();
+
+        // check ecdsa and bls signature
+        let epoch = get_epoch_number(header.number.to_u64().unwrap(), chain.epoch_size);
+        let epoch_record = &chain.epoch_records.get(&epoch).unwrap_or_else(|| {
+            let range = chain.get_verifiable_header_range();
+            panic!(
+                "cannot get epoch record for block {}, expected range[{}, {}]",
+                header.number,
+                range.0 .0,
+                range.1 .0
+            )
+        });
+        self.verify_signatures(&chain, header, agg_pk, &extra, epoch_record);
+
+        epoch
+    }
+
+    /// Credits the verification fee to the relayer of the epoch, or to the owner for epochs
+    /// which were not relayed, e.g. the one the chain is initialized with. Any deposit above
+    /// the fee is refunded.
+    fn charge_verification_fee(&mut self, epoch: u64, chain_id: Option<U128>) {
+        let deposit = env::attached_deposit();
+        assert!(
+            deposit >= self.verification_fee,
+            "attached deposit {} is less than the verification fee {}",
+            deposit,
+            self.verification_fee
+        );
+
+        if self.verification_fee > 0 {
+            let relayer = self
+                .chain(chain_id)
+                .relayers
+                .get(&epoch)
+                .unwrap_or_else(|| self.owner.clone());
+            let reward = match self.rewards.get(&relayer) {
+                Some(reward) => reward + self.verification_fee,
+                None => {
+                    // the first fee of the relayer pays for the storage of its reward entry
+                    let initial_storage = env::storage_usage();
+                    self.rewards.insert(&relayer, &0);
+                    let current_storage = env::storage_usage();
+                    let storage_cost = Balance::from(current_storage - initial_storage) * env::storage_byte_cost();
+                    self.verification_fee.saturating_sub(storage_cost)
+                }
+            };
+            self.rewards.insert(&relayer, &reward);
+        }
+
+        if deposit > self.verification_fee {
+            Promise::new(env::predecessor_account_id()).transfer(deposit - self.verification_fee);
+        }
     }
 
     fn verify_signatures(
         &self,
+        chain: &ChainState,
         header: &Header,
         agg_pk: G2,
         extra: &IstanbulExtra,
@@ -229,7 +979,15 @@ impl MapLightClient {
         self.verify_ecdsa_signature(header, &extra.seal, &addresses);
 
         // check agg seal
-        self.verify_aggregated_seal(header, extra, epoch_record, &agg_pk);
+        let fork = chain.fork_at(header.number.to_u64().unwrap());
+        match fork.signature_scheme {
+            SignatureScheme::Bn254 => {
+                self.verify_aggregated_seal(header, extra, epoch_record, &agg_pk, fork.seal_version)
+            }
+            SignatureScheme::Bls12381 => {
+                self.verify_bls12381_seal(chain, header, extra, epoch_record, fork.seal_version)
+            }
+        }
     }
 
     fn is_quorum(&self, bitmap: &Integer, validators: &Vec<Validator>, threshold: u64) -> bool {
@@ -249,48 +1007,17 @@ impl MapLightClient {
         signature: &Vec<u8>,
         addresses: &Vec<Address>,
     ) {
-        assert_eq!(
-            ECDSA_SIG_LENGTH,
-            signature.len(),
-            "invalid ecdsa signature length"
-        );
-
         let res = addresses
             .iter()
             .filter(|x| x.as_slice() == header.coinbase.as_slice())
             .count();
         assert_eq!(1, res, "the header's coinbase is not in validators");
 
-        let v = signature.last().unwrap();
         let header_hash = header.hash_without_seal().unwrap();
         let hash = keccak256(header_hash.as_slice());
-        let res;
-
-        unsafe {
-            res = near_sys::ecrecover(
-                hash.len() as _,
-                hash.as_ptr() as _,
-                (signature.len() - 1) as _,
-                signature.as_ptr() as _,
-                *v as _,
-                0,
-                ECDSA_REGISTER,
-            );
-        }
-
-        assert_ne!(0, res, "ecrecover returns 0");
-
-        let res = env::read_register(ECDSA_REGISTER).expect(REGISTER_EXPECTED_ERR);
-        assert_eq!(
-            64,
-            res.len(),
-            "the length of the ecrecover result is not expected"
-        );
-
-        let pub_key_hash = env::keccak256(res.as_slice());
         assert_eq!(
-            &header.coinbase,
-            &pub_key_hash[12..],
+            header.coinbase,
+            ecrecover_address(&hash, signature),
             "ecdsa signer is not correct"
         );
     }
@@ -301,6 +1028,7 @@ impl MapLightClient {
         extra: &IstanbulExtra,
         epoch_record: &EpochRecord,
         agg_g2_pk: &G2,
+        seal_version: SealVersion,
     ) {
         let pair_keys = epoch_record
             .validators
@@ -323,13 +1051,61 @@ impl MapLightClient {
 
         let header_hash = header.hash().unwrap();
         assert!(
-            check_sealed_signature(&extra.aggregated_seal, &header_hash, agg_g2_pk),
+            check_sealed_signature(&extra.aggregated_seal, &header_hash, agg_g2_pk, seal_version),
+            "check sealed signature failed"
+        )
+    }
+
+    /// Verifies the aggregated seal over BLS12-381, the aggregated public key is computed from the
+    /// registered keys of the epoch instead of being supplied by the caller.
+    fn verify_bls12381_seal(
+        &self,
+        chain: &ChainState,
+        header: &Header,
+        extra: &IstanbulExtra,
+        epoch_record: &EpochRecord,
+        seal_version: SealVersion,
+    ) {
+        assert!(
+            self.is_quorum(
+                &extra.aggregated_seal.bitmap,
+                &epoch_record.validators,
+                epoch_record.threshold.into()
+            ),
+            "threshold is not satisfied"
+        );
+
+        let keys: Vec<Bls12381G2> = epoch_record
+            .validators
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| extra.aggregated_seal.bitmap.bit(*i as _))
+            .map(|(_, validator)| {
+                chain.bls12381_keys.get(&validator.address).unwrap_or_else(|| {
+                    panic!(
+                        "bls12381 key of validator 0x{} is not registered",
+                        hex::encode(validator.address)
+                    )
+                })
+            })
+            .collect();
+        let agg_pk = aggregate_g2(&keys);
+
+        let header_hash = header.hash().unwrap();
+        let seal = prepare_commited_seal(header_hash, &extra.aggregated_seal.round, seal_version);
+        assert!(
+            verify_signature(
+                &Bls12381G1(extra.aggregated_seal.signature.clone()),
+                &seal,
+                &agg_pk
+            ),
             "check sealed signature failed"
         )
     }
 
     fn update_next_validators(
-        &mut self,
+        &self,
+        chain: &mut ChainState,
         cur_epoch_record: &EpochRecord,
         extra: &mut IstanbulExtra,
     ) {
@@ -340,28 +1116,14 @@ impl MapLightClient {
             .filter(|(i, _)| !extra.removed_validators.bit(*i as _))
             .map(|(_, v)| *v)
             .collect();
-
-        let mut added_validators: Vec<Validator> = extra
-            .added_g1_public_keys
-            .iter()
-            .zip(extra.added_validators.iter())
-            .map(|(g1_key, address)| Validator {
-                g1_pub_key: G1::from_slice(g1_key).unwrap(),
-                weight: U64(1),
-                address: *address,
-            })
-            .collect();
-
-        validator_list.append(&mut added_validators);
-
-        let total_weight: u64 = validator_list.iter().map(|x| x.weight.0).sum();
+        validator_list.append(&mut added_validators(extra));
 
         let next_epoch = cur_epoch_record.epoch.0 + 1;
 
         let next_epoch_record = EpochRecord {
             epoch: U64(next_epoch),
+            threshold: epoch_threshold(&validator_list),
             validators: validator_list,
-            threshold: U64(total_weight - total_weight / 3),
         };
 
         log!(
@@ -371,30 +1133,22 @@ impl MapLightClient {
             serde_json::to_string(&extra.added_validators).unwrap()
         );
 
-        self.epoch_records.insert(&next_epoch, &next_epoch_record);
-        if next_epoch >= self.max_records {
-            let epoch_to_remove = next_epoch - self.max_records;
-            self.epoch_records.remove(&epoch_to_remove);
+        chain.epoch_records.insert(&next_epoch, &next_epoch_record);
+        chain.relayers.insert(&next_epoch, &env::predecessor_account_id());
+        if next_epoch >= chain.max_records {
+            let epoch_to_remove = next_epoch - chain.max_records;
+            chain.epoch_records.remove(&epoch_to_remove);
+            chain.relayers.remove(&epoch_to_remove);
         }
     }
 
     pub fn update_owner(&mut self, new_owner: AccountId) {
-        assert_eq!(
-            self.owner,
-            env::predecessor_account_id(),
-            "unexpected caller {}",
-            env::predecessor_account_id()
-        );
+        self.assert_owner();
         self.owner = new_owner;
     }
 
     pub fn upgrade_client(&mut self, code: Base64VecU8) {
-        assert_eq!(
-            self.owner,
-            env::predecessor_account_id(),
-            "unexpected caller {}",
-            env::predecessor_account_id()
-        );
+        self.assert_owner();
 
         let current_id = env::current_account_id();
         let promise_id = env::promise_batch_create(&current_id);
@@ -408,3 +1162,39 @@ impl MapLightClient {
         );
     }
 }
+
+/// Recovers the address of the signer of the hash from the ECDSA signature `r || s || v`.
+fn ecrecover_address(hash: &[u8], signature: &[u8]) -> Address {
+    assert_eq!(
+        ECDSA_SIG_LENGTH,
+        signature.len(),
+        "invalid ecdsa signature length"
+    );
+
+    let v = signature.last().unwrap();
+    let res;
+    unsafe {
+        res = near_sys::ecrecover(
+            hash.len() as _,
+            hash.as_ptr() as _,
+            (signature.len() - 1) as _,
+            signature.as_ptr() as _,
+            *v as _,
+            0,
+            ECDSA_REGISTER,
+        );
+    }
+
+    assert_ne!(0, res, "ecrecover returns 0");
+
+    let res = env::read_register(ECDSA_REGISTER).expect(REGISTER_EXPECTED_ERR);
+    assert_eq!(
+        64,
+        res.len(),
+        "the length of the ecrecover result is not expected"
+    );
+
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&env::keccak256(res.as_slice())[12..]);
+    address
+}
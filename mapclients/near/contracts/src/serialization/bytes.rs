@@ -117,3 +117,34 @@ pub(crate) mod hexvec {
             .serialize(serializer)
     }
 }
+
+pub(crate) mod hexquantity {
+    use num::Num;
+    use near_sdk::serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Deserialize a JSON-RPC quantity, e.g. "0x1a", into T
+    pub(crate) fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Num,
+        <T as Num>::FromStrRadixErr: std::fmt::Display,
+    {
+        let s: &str = Deserialize::deserialize(deserializer)?;
+        if s.len() <= 2 || !s.starts_with("0x") {
+            return Err(D::Error::custom(format!(
+                "quantity should start with '0x', got: {}",
+                s
+            )));
+        }
+        T::from_str_radix(&s[2..], 16).map_err(D::Error::custom)
+    }
+
+    /// Serialize from T into quantity string
+    pub(crate) fn serialize<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: std::fmt::LowerHex,
+    {
+        format!("0x{:x}", value).serialize(serializer)
+    }
+}
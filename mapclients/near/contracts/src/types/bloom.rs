@@ -0,0 +1,89 @@
+use crate::types::header::{Bloom, BLOOM_BYTE_LENGTH};
+use crate::types::proof::LogEntry;
+use near_sdk::env;
+
+/// Sets the 3 bits selected by keccak256(input) in the bloom, as in go-ethereum's `bloom9`.
+pub fn accrue(bloom: &mut Bloom, input: &[u8]) {
+    for (index, bit) in bloom_bits(input) {
+        bloom[index] |= bit;
+    }
+}
+
+/// Returns false if the input is definitely not in the bloom.
+pub fn contains(bloom: &Bloom, input: &[u8]) -> bool {
+    bloom_bits(input)
+        .iter()
+        .all(|(index, bit)| bloom[*index] & bit == *bit)
+}
+
+/// Returns true if all bits set in `other` are also set in `bloom`.
+pub fn contains_bloom(bloom: &Bloom, other: &Bloom) -> bool {
+    bloom.iter().zip(other.iter()).all(|(a, b)| a & b == *b)
+}
+
+/// Computes the bloom of the logs from their addresses and topics.
+pub fn logs_bloom(logs: &[LogEntry]) -> Bloom {
+    let mut bloom = [0; BLOOM_BYTE_LENGTH];
+    for log in logs {
+        accrue(&mut bloom, &log.address);
+        for topic in log.topics.iter() {
+            accrue(&mut bloom, topic);
+        }
+    }
+
+    bloom
+}
+
+fn bloom_bits(input: &[u8]) -> [(usize, u8); 3] {
+    let hash = env::keccak256(input);
+    let mut bits = [(0, 0); 3];
+    for (i, bit) in bits.iter_mut().enumerate() {
+        let v = (((hash[2 * i] as usize) << 8) | hash[2 * i + 1] as usize) & 2047;
+        *bit = (BLOOM_BYTE_LENGTH - 1 - v / 8, 1 << (v % 8));
+    }
+
+    bits
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::header::Hash;
+
+    #[test]
+    fn accrues_three_bits() {
+        let log = LogEntry {
+            address: [1; 20],
+            topics: vec![[2; 32]],
+            data: vec![],
+        };
+        let mut bloom = [0; BLOOM_BYTE_LENGTH];
+        accrue(&mut bloom, &log.address);
+        assert!((1..=3).contains(&bloom.iter().map(|b| b.count_ones()).sum::<u32>()));
+        assert!(contains(&bloom, &log.address));
+        assert!(!contains(&bloom, &log.topics[0]));
+
+        let bloom = logs_bloom(std::slice::from_ref(&log));
+        assert!(contains(&bloom, &log.address));
+        assert!(contains(&bloom, &log.topics[0]));
+    }
+
+    #[test]
+    fn computes_bloom_of_empty_logs() {
+        assert_eq!([0; BLOOM_BYTE_LENGTH], logs_bloom(&[]));
+    }
+
+    #[test]
+    fn checks_bloom_containment() {
+        let topic: Hash = [1; 32];
+        let mut small = [0; BLOOM_BYTE_LENGTH];
+        accrue(&mut small, &topic);
+        let mut big = small;
+        accrue(&mut big, &[2; 20]);
+
+        assert!(contains_bloom(&big, &small));
+        assert!(!contains_bloom(&small, &big));
+        assert!(contains(&big, &topic));
+    }
+}
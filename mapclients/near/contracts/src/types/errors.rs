@@ -1,5 +1,5 @@
 /// All error kinds related to the light client.
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub enum Kind {
     // #[error("invalid data length while converting slice to fixed-size array type ({current} != {expected}")]
     InvalidDataLength { current: usize, expected: usize },
@@ -19,9 +19,15 @@ pub enum Kind {
     // #[error("BLS invalid public key")]
     BlsInvalidPublicKey,
 
+    // #[error("invalid curve point")]
+    InvalidPoint,
+
     // #[error("header verification failed: {msg}")]
     HeaderVerificationError { msg: &'static str },
 
+    // #[error("invalid trie proof: {msg}")]
+    InvalidTrieProof { msg: &'static str },
+
     // #[error("unknown error occurred")]
     Unknown,
 }
\ No newline at end of file
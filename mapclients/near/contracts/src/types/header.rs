@@ -1,7 +1,7 @@
 use crate::serialization::rlp::{big_int_to_rlp_compat_bytes, rlp_list_field_from_bytes, rlp_to_big_int, };
 use crate::slice_as_array_ref;
 use crate::traits::{DefaultFrom, FromBytes, FromRlp, FromVec, ToRlp};
-use crate::types::{istanbul::ISTANBUL_EXTRA_VANITY_LENGTH, istanbul::istanbul_filtered_header, errors::Kind};
+use crate::types::{istanbul::ISTANBUL_EXTRA_VANITY_LENGTH, istanbul::istanbul_filtered_header, errors::Kind, bloom};
 use num_bigint::BigInt as Integer;
 use rlp::{
     Decodable,
@@ -87,6 +87,75 @@ pub struct Header {
     pub base_fee: Integer,
 }
 
+/// Header in the layout returned by `eth_getBlockByNumber` of MAP nodes. Fields of the RPC
+/// block which are not part of the header, like `hash` or `transactions`, are ignored.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(crate = "near_sdk::serde", rename_all = "camelCase")]
+pub struct RpcHeader {
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub parent_hash: Hash,
+
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub miner: Address,
+
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub state_root: Hash,
+
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub transactions_root: Hash,
+
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub receipts_root: Hash,
+
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub logs_bloom: Bloom,
+
+    #[serde(with = "crate::serialization::bytes::hexbigint")]
+    pub number: Integer,
+
+    #[serde(with = "crate::serialization::bytes::hexbigint")]
+    pub gas_limit: Integer,
+
+    #[serde(with = "crate::serialization::bytes::hexbigint")]
+    pub gas_used: Integer,
+
+    #[serde(with = "crate::serialization::bytes::hexbigint")]
+    pub timestamp: Integer,
+
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub extra_data: Vec<u8>,
+
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub mix_hash: Hash,
+
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub nonce: Nonce,
+
+    #[serde(with = "crate::serialization::bytes::hexbigint")]
+    pub base_fee_per_gas: Integer,
+}
+
+impl From<RpcHeader> for Header {
+    fn from(header: RpcHeader) -> Self {
+        Header {
+            parent_hash: header.parent_hash,
+            coinbase: header.miner,
+            root: header.state_root,
+            tx_hash: header.transactions_root,
+            receipt_hash: header.receipts_root,
+            bloom: header.logs_bloom,
+            number: header.number,
+            gas_limit: header.gas_limit,
+            gas_used: header.gas_used,
+            time: header.timestamp,
+            extra: header.extra_data,
+            mix_digest: header.mix_hash,
+            nonce: header.nonce,
+            base_fee: header.base_fee_per_gas,
+        }
+    }
+}
+
 impl Header {
     pub fn new() -> Self {
         Self {
@@ -111,6 +180,13 @@ impl Header {
         self.internal_hash(true)
     }
 
+    /// Cheap bloom filter check: returns false if the block definitely has no log emitted by
+    /// `address` with all of `topics`. A true result may be a false positive.
+    pub fn may_contain(&self, address: &Address, topics: &[Hash]) -> bool {
+        bloom::contains(&self.bloom, address)
+            && topics.iter().all(|topic| bloom::contains(&self.bloom, topic))
+    }
+
     pub fn hash_without_seal(&self) -> Result<Hash, Kind> {
         self.internal_hash(false)
     }
@@ -311,6 +387,55 @@ mod tests {
         }
     }
 
+    #[test]
+    fn deserializes_from_rpc_json() {
+        let parsed = Header::from_rlp(&hex::decode(HEADER_WITH_EMPTY_EXTRA).unwrap()).unwrap();
+        let json = serde_json::to_value(&parsed).unwrap();
+
+        let rpc = serde_json::json!({
+            "hash": format!("0x{}", hex::encode(parsed.hash().unwrap())),
+            "parentHash": json["parentHash"],
+            "miner": json["coinbase"],
+            "stateRoot": json["root"],
+            "transactionsRoot": json["txHash"],
+            "receiptsRoot": json["receiptHash"],
+            "logsBloom": json["bloom"],
+            "number": json["number"],
+            "gasLimit": json["gasLimit"],
+            "gasUsed": json["gasUsed"],
+            "timestamp": json["time"],
+            "extraData": "0x",
+            "mixHash": json["mixDigest"],
+            "nonce": json["nonce"],
+            "baseFeePerGas": json["baseFee"],
+            "size": "0x1d5",
+            "transactions": [],
+        });
+        assert_eq!("0x1", rpc["number"]);
+
+        let header: RpcHeader = serde_json::from_str(&rpc.to_string()).unwrap();
+        assert_eq!(parsed, Header::from(header));
+
+        let mut rpc = rpc;
+        rpc["number"] = serde_json::json!("1");
+        assert!(serde_json::from_str::<RpcHeader>(&rpc.to_string()).is_err());
+    }
+
+    #[test]
+    fn checks_header_bloom() {
+        let address: Address = [1; ADDRESS_LENGTH];
+        let topic: Hash = [2; HASH_LENGTH];
+        let mut header = Header::new();
+        assert!(!header.may_contain(&address, &[]));
+
+        bloom::accrue(&mut header.bloom, &address);
+        assert!(header.may_contain(&address, &[]));
+        assert!(!header.may_contain(&address, &[topic]));
+
+        bloom::accrue(&mut header.bloom, &topic);
+        assert!(header.may_contain(&address, &[topic]));
+    }
+
     #[test]
     fn generates_valid_header_hash() {
         // testing_env!(get_context(false));
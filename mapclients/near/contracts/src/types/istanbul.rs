@@ -8,6 +8,7 @@ use crate::types::header::Address;
 use num_bigint::BigInt as Integer;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 use near_sdk::serde::{Serialize, Deserialize};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 
 /// PUBLIC_KEY_LENGTH represents the number of bytes used to represent BLS public key
 pub const PUBLIC_KEY_LENGTH: usize = 128;
@@ -37,6 +38,25 @@ pub enum IstanbulMsg {
     RoundChange,
 }
 
+/// SealVersion selects how the message signed by the committed seals is built from the
+/// proposal hash and round, a version is added once a MAP release changes the format
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum SealVersion {
+    /// `hash || round || commit code`, with the round in its minimal big endian encoding
+    #[default]
+    V0,
+}
+
+/// SignatureScheme is the curve of the BLS keys and the aggregated seal
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum SignatureScheme {
+    #[default]
+    Bn254,
+    Bls12381,
+}
+
 /// IstanbulAggregatedSeal contains the aggregated BLS signature created via IBFT consensus
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 #[serde(rename_all = "camelCase",crate = "near_sdk::serde")]
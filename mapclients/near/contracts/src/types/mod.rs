@@ -1,4 +1,5 @@
 pub mod istanbul;
 pub mod header;
 pub mod errors;
-pub mod proof;
\ No newline at end of file
+pub mod proof;
+pub mod bloom;
\ No newline at end of file
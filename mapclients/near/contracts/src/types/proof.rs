@@ -1,8 +1,11 @@
 use hex::FromHex;
 use near_sdk::json_types::{U128, U64};
 use crate::crypto::G2;
-use rlp::{Rlp, Encodable, RlpStream};
-use crate::types::header::{Header, Address, Bloom, Hash};
+use rlp::{Rlp, Decodable, DecoderError, Encodable, RlpStream};
+use crate::serialization::rlp::{rlp_field_from_bytes, rlp_list_field_from_bytes};
+use crate::traits::FromRlp;
+use crate::types::errors::Kind;
+use crate::types::header::{Header, RpcHeader, Address, Bloom, Hash, HASH_LENGTH};
 use near_sdk::serde::{Serialize, ser::Serializer, Deserialize, de::Deserializer};
 use near_sdk::serde::de::Error;
 
@@ -17,6 +20,74 @@ pub struct ReceiptProof {
     pub proof: Vec<ProofEntry>,
 }
 
+/// ReceiptProof with the header and receipt in their raw RLP encoding, as returned by MAP nodes.
+/// The receipt uses the same encoding as the value stored in the receipt trie.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RlpReceiptProof {
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub header: Vec<u8>,
+    pub agg_pk: G2,
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub receipt: Vec<u8>,
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub key_index: Vec<u8>,
+    pub proof: Vec<ProofEntry>,
+}
+
+impl RlpReceiptProof {
+    pub fn decode(self) -> Result<ReceiptProof, Kind> {
+        Ok(ReceiptProof {
+            header: Header::from_rlp(&self.header)?,
+            agg_pk: self.agg_pk,
+            receipt: Receipt::decode_index(&self.receipt)?,
+            key_index: self.key_index,
+            proof: self.proof,
+        })
+    }
+}
+
+/// ReceiptProof with the header and receipt as returned by `eth_getBlockByNumber` and
+/// `eth_getTransactionReceipt`, so relayers can pass the RPC output through unchanged.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RpcReceiptProof {
+    pub header: RpcHeader,
+    pub agg_pk: G2,
+    pub receipt: RpcReceipt,
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub key_index: Vec<u8>,
+    pub proof: Vec<ProofEntry>,
+}
+
+impl From<RpcReceiptProof> for ReceiptProof {
+    fn from(proof: RpcReceiptProof) -> Self {
+        ReceiptProof {
+            header: proof.header.into(),
+            agg_pk: proof.agg_pk,
+            receipt: proof.receipt.into(),
+            key_index: proof.key_index,
+            proof: proof.proof,
+        }
+    }
+}
+
+/// Proof that the receipt trie of the header has no receipt at `key_index`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReceiptAbsenceProof {
+    pub header: Header,
+    pub agg_pk: G2,
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub key_index: Vec<u8>,
+    pub proof: Vec<ProofEntry>,
+}
+
+/// RlpBytes is raw RLP encoded data which is passed as a 0x-prefixed hex string
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RlpBytes(#[serde(with = "crate::serialization::bytes::hexstring")] pub Vec<u8>);
+
 #[derive(Clone, Debug)]
 pub struct ProofEntry (Vec<u8>);
 
@@ -69,7 +140,92 @@ pub struct LogEntry {
     pub data: Vec<u8>,
 }
 
+/// Receipt in the layout returned by `eth_getTransactionReceipt`. Fields which are not part of
+/// the consensus encoding, like `transactionHash` or `gasUsed`, are ignored.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde", rename_all = "camelCase")]
+pub struct RpcReceipt {
+    /// Absent for receipts returned by nodes predating EIP-2718
+    #[serde(rename = "type", default, with = "crate::serialization::bytes::hexquantity")]
+    pub receipt_type: u64,
+    /// Intermediate state root of pre-Byzantium receipts
+    #[serde(default, with = "crate::serialization::bytes::hexstring")]
+    pub root: Vec<u8>,
+    #[serde(default, with = "crate::serialization::bytes::hexquantity")]
+    pub status: u64,
+    #[serde(with = "crate::serialization::bytes::hexquantity")]
+    pub cumulative_gas_used: u64,
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub logs_bloom: Bloom,
+    pub logs: Vec<RpcLogEntry>,
+}
+
+/// Log entry in the layout returned by `eth_getTransactionReceipt`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RpcLogEntry {
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub address: Address,
+    #[serde(with = "crate::serialization::bytes::hexvec")]
+    pub topics: Vec<Hash>,
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub data: Vec<u8>,
+}
+
+impl From<RpcReceipt> for Receipt {
+    fn from(receipt: RpcReceipt) -> Self {
+        // the status is encoded as the minimal big endian bytes, so a failed one is empty
+        let post_state_or_status = if !receipt.root.is_empty() {
+            receipt.root
+        } else if receipt.status == 0 {
+            vec![]
+        } else {
+            vec![receipt.status as u8]
+        };
+
+        Receipt {
+            receipt_type: U128(receipt.receipt_type as _),
+            post_state_or_status,
+            cumulative_gas_used: U64(receipt.cumulative_gas_used),
+            bloom: receipt.logs_bloom,
+            logs: receipt.logs.into_iter().map(LogEntry::from).collect(),
+        }
+    }
+}
+
+impl From<RpcLogEntry> for LogEntry {
+    fn from(log: RpcLogEntry) -> Self {
+        LogEntry {
+            address: log.address,
+            topics: log.topics,
+            data: log.data,
+        }
+    }
+}
+
+/// ReceiptStatus is the execution status carried by `Receipt::post_state_or_status`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ReceiptStatus {
+    /// The transaction is reverted (EIP-658 status 0)
+    Failed,
+    /// The transaction is executed successfully (EIP-658 status 1)
+    Succeeded,
+    /// Pre-Byzantium receipt carrying the intermediate state root instead of a status
+    PostState,
+}
+
 impl Receipt {
+    /// Decodes the execution status, which is encoded the same way for legacy and typed receipts.
+    /// Anything other than a status byte or a state root is treated as failed.
+    pub fn status(&self) -> ReceiptStatus {
+        match self.post_state_or_status.as_slice() {
+            [1] => ReceiptStatus::Succeeded,
+            state if state.len() == HASH_LENGTH => ReceiptStatus::PostState,
+            _ => ReceiptStatus::Failed,
+        }
+    }
+
     pub fn encode_index(&self) -> Vec<u8> {
         if let 0 = self.receipt_type.0 {
             return rlp::encode(self);
@@ -80,6 +236,26 @@ impl Receipt {
         res.append(&mut rlp::encode(self));
         res
     }
+
+    /// Decodes the receipt from the value stored in the receipt trie, which is either a legacy
+    /// RLP list or an EIP-2718 typed receipt prefixed by its type byte.
+    pub fn decode_index(data: &[u8]) -> Result<Self, Kind> {
+        match data.first() {
+            Some(receipt_type) if *receipt_type <= 0x7f => {
+                let mut receipt: Receipt = rlp::decode(&data[1..]).map_err(|_e| Kind::RlpDecodeError)?;
+                receipt.receipt_type = U128(*receipt_type as _);
+                Ok(receipt)
+            }
+            Some(_) => rlp::decode(data).map_err(|_e| Kind::RlpDecodeError),
+            None => Err(Kind::RlpDecodeError),
+        }
+    }
+}
+
+impl FromRlp for Receipt {
+    fn from_rlp(bytes: &[u8]) -> Result<Self, Kind> {
+        Receipt::decode_index(bytes)
+    }
 }
 
 impl Encodable for Receipt {
@@ -121,83 +297,177 @@ impl Encodable for LogEntry {
     }
 }
 
+impl Decodable for Receipt {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let logs: Result<Vec<LogEntry>, DecoderError> = rlp
+            .at(3)?
+            .iter()
+            .map(|r| r.as_val())
+            .collect();
+
+        Ok(Receipt {
+            receipt_type: U128(0),
+            post_state_or_status: rlp.val_at(0)?,
+            cumulative_gas_used: U64(rlp.val_at(1)?),
+            bloom: rlp_list_field_from_bytes(rlp, 2)?,
+            logs: logs?,
+        })
+    }
+}
+
+impl Decodable for LogEntry {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let topics: Result<Vec<Hash>, DecoderError> = rlp
+            .at(1)?
+            .iter()
+            .map(|r| rlp_field_from_bytes(&r))
+            .collect();
+
+        Ok(LogEntry {
+            address: rlp_list_field_from_bytes(rlp, 0)?,
+            topics: topics?,
+            data: rlp.val_at(2)?,
+        })
+    }
+}
+
+/// EMPTY_TRIE_ROOT is the root hash of a trie without any key, i.e. keccak256(rlp(""))
+pub const EMPTY_TRIE_ROOT: Hash = [
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+    0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+];
+
+/// Verifies that `key` is included in the trie and returns its value.
 pub fn verify_trie_proof(expected_root: Hash, key: Vec<u8>, proof: Vec<ProofEntry>) -> Vec<u8> {
+    match get_trie_value(expected_root, &key, &proof) {
+        Ok(Some(value)) => value,
+        Ok(None) => panic!("key {} is not included in trie", hex::encode(&key)),
+        Err(Kind::InvalidTrieProof { msg }) => panic!("{}", msg),
+        Err(e) => panic!("invalid trie proof: {:?}", e),
+    }
+}
+
+/// Looks up `key` in the trie with the proof. Returns the value if the proof shows the key is
+/// included, `None` if the proof shows the key is absent, and an error if the proof is invalid.
+pub fn get_trie_value(
+    expected_root: Hash,
+    key: &[u8],
+    proof: &[ProofEntry],
+) -> Result<Option<Vec<u8>>, Kind> {
     let mut actual_key = vec![];
     for el in key {
         actual_key.push(el / 16);
         actual_key.push(el % 16);
     }
-    _verify_trie_proof(expected_root.to_vec(), &actual_key, &proof, 0, 0)
+
+    if proof.is_empty() {
+        if expected_root == EMPTY_TRIE_ROOT {
+            return Ok(None);
+        }
+        return Err(Kind::InvalidTrieProof { msg: "empty proof for non-empty trie" });
+    }
+
+    _get_trie_value(expected_root.to_vec(), &actual_key, proof, 0, 0)
 }
 
-fn _verify_trie_proof(
+fn _get_trie_value(
     expected_root: Vec<u8>,
-    key: &Vec<u8>,
-    proof: &Vec<ProofEntry>,
+    key: &[u8],
+    proof: &[ProofEntry],
     key_index: usize,
     proof_index: usize,
-) -> Vec<u8> {
-    let node = &proof[proof_index].0;
-
-    if key_index == 0 || node.len() >= 32 {
-        assert_eq!(expected_root.as_slice(), near_keccak256(node), "incorrect root for node {:?}", node);
-    } else {
-        assert_eq!(expected_root, node.as_slice(), "incorrect node root");
+) -> Result<Option<Vec<u8>>, Kind> {
+    let node = match proof.get(proof_index) {
+        Some(entry) => &entry.0,
+        None => return Err(Kind::InvalidTrieProof { msg: "proof is too short" }),
+    };
+    let is_last = proof_index + 1 == proof.len();
+
+    if proof_index == 0 || node.len() >= 32 {
+        if expected_root.as_slice() != near_keccak256(node) {
+            return Err(Kind::InvalidTrieProof { msg: "incorrect root for node" });
+        }
+    } else if expected_root != *node {
+        return Err(Kind::InvalidTrieProof { msg: "incorrect node root" });
     }
 
     let node = Rlp::new(node.as_slice());
 
-    if node.iter().count() == 17 {
-        // Branch node
-        if key_index == key.len() {
-            assert_eq!(proof_index + 1, proof.len(), "incorrect proof length for branch node");
-            get_vec(&node, 16)
-        } else {
-            let new_expected_root = get_vec(&node, key[key_index] as usize);
-            _verify_trie_proof(
-                new_expected_root,
-                key,
-                proof,
-                key_index + 1,
-                proof_index + 1,
-            )
+    // Only the root of an empty trie is an empty node
+    if node.is_empty() {
+        if proof_index != 0 || !is_last {
+            return Err(Kind::InvalidTrieProof { msg: "unexpected empty node" });
         }
-    } else {
-        // Leaf or extension node
-        assert_eq!(node.iter().count(), 2, "incorrect node count");
-        let path_u8 = get_vec(&node, 0);
-        // Extract first nibble
-        let head = path_u8[0] / 16;
-        // assert!(0 <= head); is implicit because of type limits
-        assert!(head <= 3, "incrorrect head {}", head);
-
-        // Extract path
-        let mut path = vec![];
-        if head % 2 == 1 {
-            path.push(path_u8[0] % 16);
-        }
-        for val in path_u8.into_iter().skip(1) {
-            path.push(val / 16);
-            path.push(val % 16);
-        }
-        assert_eq!(path.as_slice(), &key[key_index..key_index + path.len()], "incorrect path");
+        return Ok(None);
+    }
 
-        if head >= 2 {
-            // Leaf node
-            assert_eq!(proof_index + 1, proof.len(), "incorrect proof length for leaf node");
-            assert_eq!(key_index + path.len(), key.len(), "incorrect key length for leaf node");
-            get_vec(&node, 1)
-        } else {
-            // Extension node
-            let new_expected_root = get_vec(&node, 1);
-            _verify_trie_proof(
-                new_expected_root,
-                key,
-                proof,
-                key_index + path.len(),
-                proof_index + 1,
-            )
+    match node.item_count().map_err(|_e| Kind::RlpDecodeError)? {
+        17 => {
+            // Branch node
+            if key_index == key.len() {
+                if !is_last {
+                    return Err(Kind::InvalidTrieProof { msg: "incorrect proof length for branch node" });
+                }
+                let value = get_bytes(&node, 16)?;
+                return Ok(if value.is_empty() { None } else { Some(value) });
+            }
+
+            let new_expected_root = get_bytes(&node, key[key_index] as usize)?;
+            if new_expected_root.is_empty() {
+                // No child on the path of the key
+                if !is_last {
+                    return Err(Kind::InvalidTrieProof { msg: "incorrect proof length for branch node" });
+                }
+                return Ok(None);
+            }
+
+            _get_trie_value(new_expected_root, key, proof, key_index + 1, proof_index + 1)
         }
+        2 => {
+            // Leaf or extension node
+            let path_u8 = get_bytes(&node, 0)?;
+            // Extract first nibble
+            let head = match path_u8.first() {
+                Some(first) => first / 16,
+                None => return Err(Kind::InvalidTrieProof { msg: "empty node path" }),
+            };
+            if head > 3 {
+                return Err(Kind::InvalidTrieProof { msg: "incorrect head" });
+            }
+
+            // Extract path
+            let mut path = vec![];
+            if head % 2 == 1 {
+                path.push(path_u8[0] % 16);
+            }
+            for val in path_u8.into_iter().skip(1) {
+                path.push(val / 16);
+                path.push(val % 16);
+            }
+
+            let rest = &key[key_index..];
+            if head >= 2 {
+                // Leaf node, which proves the absence if it belongs to another key
+                if !is_last {
+                    return Err(Kind::InvalidTrieProof { msg: "incorrect proof length for leaf node" });
+                }
+                if path.as_slice() != rest {
+                    return Ok(None);
+                }
+                Ok(Some(get_bytes(&node, 1)?))
+            } else {
+                // Extension node, which proves the absence if the key diverges from its path
+                if !rest.starts_with(&path) {
+                    if !is_last {
+                        return Err(Kind::InvalidTrieProof { msg: "incorrect proof length for extension node" });
+                    }
+                    return Ok(None);
+                }
+                let new_expected_root = get_bytes(&node, 1)?;
+                _get_trie_value(new_expected_root, key, proof, key_index + path.len(), proof_index + 1)
+            }
+        }
+        _ => Err(Kind::InvalidTrieProof { msg: "incorrect node count" }),
     }
 }
 
@@ -209,8 +479,8 @@ pub fn near_keccak256(data: &[u8]) -> [u8; 32] {
 
 /// Get element at position `pos` from rlp encoded data,
 /// and decode it as vector of bytes
-fn get_vec(data: &Rlp, pos: usize) -> Vec<u8> {
-    data.at(pos).unwrap().as_val::<Vec<u8>>().unwrap()
+fn get_bytes(data: &Rlp, pos: usize) -> Result<Vec<u8>, Kind> {
+    data.val_at::<Vec<u8>>(pos).map_err(|_e| Kind::RlpDecodeError)
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -220,10 +490,23 @@ mod tests {
     use super::*;
     use crate::types::header::{ADDRESS_LENGTH, BLOOM_BYTE_LENGTH, Nonce};
     use crate::traits::FromBytes;
+    use crate::types::bloom::logs_bloom;
 
     const RECEIPT_TINY_LEGACY: &str = "f901068080b9010000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000c0";
     const RECEIPT_SIMPLE_ACCESS_LIST: &str = "f9011e0102b9010000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000d8d7940000000000000000000000000000000000000000c080";
 
+    // proof of the key 0x820137 in a receipt trie: two branch nodes, an extension node with the
+    // path 0x01, two more branch nodes and a leaf node
+    const TRIE_ROOT: &str = "73733f420161b4189ea48140489bfada55d485fd580ab7e6f0f4b6de229f5177";
+    const TRIE_PROOF: [&str; 6] = [
+        "f90131a07dc6deefc13acb3456defc30824e9ba7d344e0fed67d6fe918a845ac6c7ff68ca00743abb9e8a2419e731aabac46e87dd9166ef04f4c0e17b607f699169fd16effa0de6439dd92daf3fe5ae984f9176a1d53f0ab2c03a73ea9ee2c94c79a87b82386a08eccde52f7cdcfa2207e2e8256bbd05a78fbab4f1b564a98a7f5b259b9fcb05da0196a72fd5279acc9146896618d5a134398bfc5d84063bcb2dc4f206bceb1526fa0daa3100c65bc47d986741898d7dfa1cc2f944d9f621b33a53d52047d98ab6e84a0126a9c69a2fb01312dffd739ee2a86c15106497d5e53314875e3a83c915b40c3a0b89b77f6776de33f0d291891d4271546d3b4946325f6fa66d38a1618f699b7b0a06b4f2fac50925da7c11ddac2321257cf157d426bffedcce8c3e999f8dd3902ff8080808080808080",
+        "f871a073170337a44a638efb6d735150b3a06346b54b6176c9424307e6c1f4a4604131a0409f60141274adbaf1fd8808c432c599025a80763a61aca8710ba5416436c885a064d0127fe80ad8301e425eece21dd4811515312fad7e95b9ad4f853d003582a88080808080808080808080808080",
+        "e4820001a022d9ed1b1940164d904d587080c9ca1d5ebb7e711211233bee7ecf6f0fba3d8e",
+        "f8d1a0af41ab83382da16fba21a258c18a231957c14eb91ede9b75b089d37474efe1b8a0102af48a2d48aa200cb90bafdb43c3845ed09e2d34f333944ac7c172f2becec4a0644f776baaf4dd2a45e817c3b70ed881419f31d966debf0e2dac62426b1308eea08498703814dfa09c76b9f8dde1d5e3865b92b805e9ffd77d12cd8221497fe604a02a6a5cc557e67488aef767895f2ad789fa339aab229d7e94b78d6a8187989d3ea002ec005b9dafdfd58601cd6dc96fd958a8681981442c485c87142ae85acc1fd28080808080808080808080",
+        "f90211a02e04ddc4ab28665d70404d04601838d03b219207d68a477e086144d5452b035ea07458b1e7734dcde7a48e763f57b533d39a9893b2fd05ece758eb95c45230b69aa05ce53207da7cb7efdfa60bfc57dc23a5469d2823ac6d94377fda37d7d6e77a23a0ba360fa8bb757bec0086b0d2973bb39de6b874ae3558e0b91eb54579022bc68fa023068af8cff2927c6c437840b4bee730c5ef2d918c0bb086b453da9071f3e3a8a05a6cb3455636113070e724682c1e852b564bc26195690b57adf95b03e453fb56a090e6afaa341c8c8583ba621e0f4369e0a36e488d167061bf0627c8de8e4b1b53a0c974bc6676b17c2e0e0016b86e2261cc69fa10b06ad22938f851674c853face6a0134724224ee173faf5807c3b963e4aff5d8435c2296230ba6fbdf222262ee7a5a0ea6f74b84a4ee7f7d557bb27e61c75ae30ead9092faab3b441c4e5055c8768d7a09e5ab3942cfe8410180611c7eddb2364ba2022e53971b50250636f2576f1528fa0fe27620c114d5ce5b8c96859b754cf0a40a9a0cb7a6c88a492ca906b09db6c5aa023558ac1d7facb4cb81d5ced13c9126b70a898a81b63fe52117792ed5bcadb06a00b52d0a4cd96595521a0783e6f17de8b9bdd15f1e50ab8b51179a98ae6df18e5a0462c433d431953bcead5900c4b372c60b20280d05366fe48de6384152cfc8da9a0a5a9b086a22dc344a40496a9de4ebab616fa87dfc77760eef7939ba51dd193cd80",
+        "f902ad20b902a9f902a60183af4adfb9010000000000000000000000000000000000000000000000000000000000000000000800010000000000000002000100000000000000000000000000000000000000000000000000000008000008000000000000000000000200000000000000000000000000000000000000000000000000000000000000010000000010000000040000000000000000000000000000000200000000010000000000000000000000000000000000200080000000202000000000000000000000000000004000000000000002000000000000000000000000000000000000080000000000000000000000000000000000000000000200000004000000000000000000000000000000f9019bf89b94a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48f863a0ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3efa0000000000000000000000000c22df065a81f6e0107e214991b9d7fb179d401b3a000000000000000000000000023ddd3e3692d1861ed57ede224608875809e127fa00000000000000000000000000000000000000000000000000000000005f5e100f8fc9423ddd3e3692d1861ed57ede224608875809e127ff863a0dd85dc56b5b4da387bf69c28ec19b1d66e793e0d51b567882fa31dc50bbd32c5a0000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48a0000000000000000000000000c22df065a81f6e0107e214991b9d7fb179d401b3b8800000000000000000000000000000000000000000000000000000000005f5e1000000000000000000000000000000000000000000000000000000000000000040000000000000000000000000000000000000000000000000000000000000000a6d616b6b652e6e65617200000000000000000000000000000000000000000000",
+    ];
+
     #[test]
     fn encodes_receipt_to_rlp() {
         let mut receipt = Receipt{
@@ -263,12 +546,116 @@ mod tests {
         assert_eq!( hex::decode(LOG_ENTRY_EMPTY).unwrap(), bytes);
     }
 
+    #[test]
+    fn decodes_receipt_from_rlp() {
+        let receipt = Receipt::decode_index(&hex::decode(RECEIPT_TINY_LEGACY).unwrap()).unwrap();
+        assert_eq!(0, receipt.receipt_type.0);
+        assert!(receipt.post_state_or_status.is_empty());
+        assert_eq!(0, receipt.cumulative_gas_used.0);
+        assert!(receipt.logs.is_empty());
+        assert_eq!(hex::decode(RECEIPT_TINY_LEGACY).unwrap(), receipt.encode_index());
+
+        let typed = [vec![1], hex::decode(RECEIPT_SIMPLE_ACCESS_LIST).unwrap()].concat();
+        let receipt = Receipt::decode_index(&typed).unwrap();
+        assert_eq!(1, receipt.receipt_type.0);
+        assert_eq!(vec![1], receipt.post_state_or_status);
+        assert_eq!(2, receipt.cumulative_gas_used.0);
+        assert_eq!(1, receipt.logs.len());
+        assert_eq!([0; ADDRESS_LENGTH], receipt.logs[0].address);
+        assert_eq!(typed, receipt.encode_index());
+
+        assert!(Receipt::decode_index(&[]).is_err());
+        assert!(Receipt::decode_index(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn decodes_receipt_status() {
+        let mut receipt = Receipt::decode_index(&hex::decode(RECEIPT_TINY_LEGACY).unwrap()).unwrap();
+        assert_eq!(ReceiptStatus::Failed, receipt.status());
+
+        receipt.post_state_or_status = vec![0];
+        assert_eq!(ReceiptStatus::Failed, receipt.status());
+
+        receipt.post_state_or_status = vec![1];
+        assert_eq!(ReceiptStatus::Succeeded, receipt.status());
+
+        receipt.post_state_or_status = vec![2];
+        assert_eq!(ReceiptStatus::Failed, receipt.status());
+
+        receipt.post_state_or_status = vec![1; HASH_LENGTH];
+        assert_eq!(ReceiptStatus::PostState, receipt.status());
+
+        let typed = [vec![1], hex::decode(RECEIPT_SIMPLE_ACCESS_LIST).unwrap()].concat();
+        let receipt = Receipt::decode_index(&typed).unwrap();
+        assert_eq!(ReceiptStatus::Succeeded, receipt.status());
+    }
+
+    #[test]
+    fn decodes_log_entry_from_rlp() {
+        let bytes = hex::decode("f85a940100000000000000000000000000000000000000f842a00000000000000000000000000000000000000000000000000000000000000001a0000000000000000000000000000000000000000000000000000000000000000a01").unwrap();
+        let entry: LogEntry = rlp::decode(&bytes).unwrap();
+
+        assert_eq!(1, entry.address[0]);
+        assert_eq!(2, entry.topics.len());
+        assert_eq!(1, entry.topics[0][31]);
+        assert_eq!(10, entry.topics[1][31]);
+        assert_eq!(vec![1], entry.data);
+        assert_eq!(bytes, rlp::encode(&entry));
+    }
+
+    #[test]
+    fn deserializes_receipt_from_rpc_json() {
+        let bloom = format!("0x{}", hex::encode([0u8; BLOOM_BYTE_LENGTH]));
+        let rpc = serde_json::json!({
+            "type": "0x1",
+            "status": "0x1",
+            "cumulativeGasUsed": "0x2",
+            "gasUsed": "0x2",
+            "logsBloom": bloom,
+            "logs": [{
+                "address": "0x0000000000000000000000000000000000000000",
+                "topics": [],
+                "data": "0x",
+                "blockNumber": "0x2da7d",
+                "logIndex": "0x0",
+                "removed": false
+            }],
+            "transactionHash": "0x7285abd5b24742f184ad676e31f6054663b3529bc35ea2fcad8a3e0f642a46f7",
+            "contractAddress": null
+        });
+        let receipt: Receipt = serde_json::from_str::<RpcReceipt>(&rpc.to_string()).unwrap().into();
+        let typed = [vec![1], hex::decode(RECEIPT_SIMPLE_ACCESS_LIST).unwrap()].concat();
+        assert_eq!(typed, receipt.encode_index());
+
+        // legacy receipt of a failed transaction from a node which does not return the type
+        let rpc = serde_json::json!({
+            "status": "0x0",
+            "cumulativeGasUsed": "0x0",
+            "logsBloom": bloom,
+            "logs": []
+        });
+        let receipt: Receipt = serde_json::from_str::<RpcReceipt>(&rpc.to_string()).unwrap().into();
+        assert_eq!(ReceiptStatus::Failed, receipt.status());
+        assert_eq!(hex::decode(RECEIPT_TINY_LEGACY).unwrap(), receipt.encode_index());
+
+        let rpc = serde_json::json!({
+            "type": "0x0",
+            "root": format!("0x{}", hex::encode([1u8; HASH_LENGTH])),
+            "cumulativeGasUsed": "0x5208",
+            "logsBloom": bloom,
+            "logs": []
+        });
+        let receipt: Receipt = serde_json::from_str::<RpcReceipt>(&rpc.to_string()).unwrap().into();
+        assert_eq!(ReceiptStatus::PostState, receipt.status());
+        assert_eq!(21000, receipt.cumulative_gas_used.0);
+    }
+
     #[test]
     fn test_verify_proof() {
         let expected_value = "f902a60183af4adfb9010000000000000000000000000000000000000000000000000000000000000000000800010000000000000002000100000000000000000000000000000000000000000000000000000008000008000000000000000000000200000000000000000000000000000000000000000000000000000000000000010000000010000000040000000000000000000000000000000200000000010000000000000000000000000000000000200080000000202000000000000000000000000000004000000000000002000000000000000000000000000000000000080000000000000000000000000000000000000000000200000004000000000000000000000000000000f9019bf89b94a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48f863a0ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3efa0000000000000000000000000c22df065a81f6e0107e214991b9d7fb179d401b3a000000000000000000000000023ddd3e3692d1861ed57ede224608875809e127fa00000000000000000000000000000000000000000000000000000000005f5e100f8fc9423ddd3e3692d1861ed57ede224608875809e127ff863a0dd85dc56b5b4da387bf69c28ec19b1d66e793e0d51b567882fa31dc50bbd32c5a0000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48a0000000000000000000000000c22df065a81f6e0107e214991b9d7fb179d401b3b8800000000000000000000000000000000000000000000000000000000005f5e1000000000000000000000000000000000000000000000000000000000000000040000000000000000000000000000000000000000000000000000000000000000a6d616b6b652e6e65617200000000000000000000000000000000000000000000";
-        let expected_root = "73733f420161b4189ea48140489bfada55d485fd580ab7e6f0f4b6de229f5177";
+        let expected_root = TRIE_ROOT;
         let key = "820137";
-        let proof_rlp = vec!["f90131a07dc6deefc13acb3456defc30824e9ba7d344e0fed67d6fe918a845ac6c7ff68ca00743abb9e8a2419e731aabac46e87dd9166ef04f4c0e17b607f699169fd16effa0de6439dd92daf3fe5ae984f9176a1d53f0ab2c03a73ea9ee2c94c79a87b82386a08eccde52f7cdcfa2207e2e8256bbd05a78fbab4f1b564a98a7f5b259b9fcb05da0196a72fd5279acc9146896618d5a134398bfc5d84063bcb2dc4f206bceb1526fa0daa3100c65bc47d986741898d7dfa1cc2f944d9f621b33a53d52047d98ab6e84a0126a9c69a2fb01312dffd739ee2a86c15106497d5e53314875e3a83c915b40c3a0b89b77f6776de33f0d291891d4271546d3b4946325f6fa66d38a1618f699b7b0a06b4f2fac50925da7c11ddac2321257cf157d426bffedcce8c3e999f8dd3902ff8080808080808080", "f871a073170337a44a638efb6d735150b3a06346b54b6176c9424307e6c1f4a4604131a0409f60141274adbaf1fd8808c432c599025a80763a61aca8710ba5416436c885a064d0127fe80ad8301e425eece21dd4811515312fad7e95b9ad4f853d003582a88080808080808080808080808080", "e4820001a022d9ed1b1940164d904d587080c9ca1d5ebb7e711211233bee7ecf6f0fba3d8e", "f8d1a0af41ab83382da16fba21a258c18a231957c14eb91ede9b75b089d37474efe1b8a0102af48a2d48aa200cb90bafdb43c3845ed09e2d34f333944ac7c172f2becec4a0644f776baaf4dd2a45e817c3b70ed881419f31d966debf0e2dac62426b1308eea08498703814dfa09c76b9f8dde1d5e3865b92b805e9ffd77d12cd8221497fe604a02a6a5cc557e67488aef767895f2ad789fa339aab229d7e94b78d6a8187989d3ea002ec005b9dafdfd58601cd6dc96fd958a8681981442c485c87142ae85acc1fd28080808080808080808080", "f90211a02e04ddc4ab28665d70404d04601838d03b219207d68a477e086144d5452b035ea07458b1e7734dcde7a48e763f57b533d39a9893b2fd05ece758eb95c45230b69aa05ce53207da7cb7efdfa60bfc57dc23a5469d2823ac6d94377fda37d7d6e77a23a0ba360fa8bb757bec0086b0d2973bb39de6b874ae3558e0b91eb54579022bc68fa023068af8cff2927c6c437840b4bee730c5ef2d918c0bb086b453da9071f3e3a8a05a6cb3455636113070e724682c1e852b564bc26195690b57adf95b03e453fb56a090e6afaa341c8c8583ba621e0f4369e0a36e488d167061bf0627c8de8e4b1b53a0c974bc6676b17c2e0e0016b86e2261cc69fa10b06ad22938f851674c853face6a0134724224ee173faf5807c3b963e4aff5d8435c2296230ba6fbdf222262ee7a5a0ea6f74b84a4ee7f7d557bb27e61c75ae30ead9092faab3b441c4e5055c8768d7a09e5ab3942cfe8410180611c7eddb2364ba2022e53971b50250636f2576f1528fa0fe27620c114d5ce5b8c96859b754cf0a40a9a0cb7a6c88a492ca906b09db6c5aa023558ac1d7facb4cb81d5ced13c9126b70a898a81b63fe52117792ed5bcadb06a00b52d0a4cd96595521a0783e6f17de8b9bdd15f1e50ab8b51179a98ae6df18e5a0462c433d431953bcead5900c4b372c60b20280d05366fe48de6384152cfc8da9a0a5a9b086a22dc344a40496a9de4ebab616fa87dfc77760eef7939ba51dd193cd80", "f902ad20b902a9f902a60183af4adfb9010000000000000000000000000000000000000000000000000000000000000000000800010000000000000002000100000000000000000000000000000000000000000000000000000008000008000000000000000000000200000000000000000000000000000000000000000000000000000000000000010000000010000000040000000000000000000000000000000200000000010000000000000000000000000000000000200080000000202000000000000000000000000000004000000000000002000000000000000000000000000000000000080000000000000000000000000000000000000000000200000004000000000000000000000000000000f9019bf89b94a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48f863a0ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3efa0000000000000000000000000c22df065a81f6e0107e214991b9d7fb179d401b3a000000000000000000000000023ddd3e3692d1861ed57ede224608875809e127fa00000000000000000000000000000000000000000000000000000000005f5e100f8fc9423ddd3e3692d1861ed57ede224608875809e127ff863a0dd85dc56b5b4da387bf69c28ec19b1d66e793e0d51b567882fa31dc50bbd32c5a0000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48a0000000000000000000000000c22df065a81f6e0107e214991b9d7fb179d401b3b8800000000000000000000000000000000000000000000000000000000005f5e1000000000000000000000000000000000000000000000000000000000000000040000000000000000000000000000000000000000000000000000000000000000a6d616b6b652e6e65617200000000000000000000000000000000000000000000"];
+        let proof_rlp = TRIE_PROOF.to_vec();
 
         let decoded_root = hex::decode(expected_root).unwrap();
         let mut expected_root = Hash::default();
@@ -280,10 +667,49 @@ mod tests {
             .collect();
         let expected_value = hex::decode(expected_value).unwrap();
 
-        assert_eq!(
-            verify_trie_proof(expected_root, key, proof),
-            expected_value
-        );
+        let value = verify_trie_proof(expected_root, key, proof);
+        assert_eq!(value, expected_value);
+
+        let receipt = Receipt::decode_index(&value).unwrap();
+        assert_eq!(2, receipt.logs.len());
+        assert_eq!(expected_value, receipt.encode_index());
+        assert_eq!(receipt.bloom, logs_bloom(&receipt.logs));
+    }
+
+    #[test]
+    fn verifies_trie_exclusion_proof() {
+        let root = Hash::from_bytes(&hex::decode(TRIE_ROOT).unwrap()).unwrap().to_owned();
+        let proof: Vec<ProofEntry> = TRIE_PROOF
+            .iter()
+            .map(|x| ProofEntry(hex::decode(x).unwrap()))
+            .collect();
+
+        let value = get_trie_value(root, &hex::decode("820137").unwrap(), &proof).unwrap();
+        assert_eq!(Some(hex::decode(&TRIE_PROOF[5][14..]).unwrap()), value);
+
+        // the root branch has no child at 9 and the second branch has no child at 3
+        assert_eq!(None, get_trie_value(root, &[0x90], &proof[..1]).unwrap());
+        assert_eq!(None, get_trie_value(root, &[0x83], &proof[..2]).unwrap());
+        // diverges from the path of the extension node
+        assert_eq!(None, get_trie_value(root, &[0x82, 0x03], &proof[..3]).unwrap());
+        // ends at a leaf of another key
+        assert_eq!(None, get_trie_value(root, &[0x82, 0x01, 0x37, 0x00], &proof).unwrap());
+
+        // proofs of absence must end at the node which shows it
+        assert!(get_trie_value(root, &[0x90], &proof[..2]).is_err());
+        // truncated proof
+        assert!(get_trie_value(root, &hex::decode("820137").unwrap(), &proof[..5]).is_err());
+        // wrong root
+        assert!(get_trie_value(EMPTY_TRIE_ROOT, &[0x90], &proof[..1]).is_err());
+        // tampered node
+        let mut tampered = proof.clone();
+        tampered[2].0[5] ^= 1;
+        assert!(get_trie_value(root, &[0x82, 0x03], &tampered[..3]).is_err());
+
+        assert_eq!(EMPTY_TRIE_ROOT, near_keccak256(&[0x80]));
+        assert_eq!(None, get_trie_value(EMPTY_TRIE_ROOT, &[0x80], &[]).unwrap());
+        assert_eq!(None, get_trie_value(EMPTY_TRIE_ROOT, &[0x80], &[ProofEntry(vec![0x80])]).unwrap());
+        assert!(get_trie_value(root, &[0x80], &[]).is_err());
     }
 
     #[test]
@@ -0,0 +1,232 @@
+use crate::crypto::{pack_pairs, to_le_bytes, ALT_BN128_REGISTER, REGISTER_EXPECTED_ERR};
+use crate::types::header::Hash;
+use crate::types::istanbul::G1_PUBLIC_KEY_LENGTH;
+use crate::{Validator, G1, G2};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::env;
+use near_sdk::serde::{Deserialize, Serialize};
+
+/// Groth16 verifying key over bn254.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VerifyingKey {
+    pub alpha_g1: G1,
+    pub beta_g2: G2,
+    pub gamma_g2: G2,
+    pub delta_g2: G2,
+    /// `ic[0]` is the constant term, `ic[i]` is multiplied by the i-th public input.
+    pub ic: Vec<G1>,
+}
+
+/// Groth16 proof over bn254.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Groth16Proof {
+    pub a: G1,
+    pub b: G2,
+    pub c: G1,
+}
+
+/// Commitment to a validator set used as a public input of the header circuit:
+/// keccak256 over `g1.x || g1.y || weight (8 bytes, big endian) || address` of every validator.
+pub fn validators_commitment(validators: &[Validator]) -> Hash {
+    let data: Vec<u8> = validators
+        .iter()
+        .flat_map(|v| {
+            [
+                v.g1_pub_key.x.as_slice(),
+                v.g1_pub_key.y.as_slice(),
+                &v.weight.0.to_be_bytes(),
+                v.address.as_slice(),
+            ]
+            .concat()
+        })
+        .collect();
+
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(env::keccak256(data.as_slice()).as_slice());
+    hash
+}
+
+/// Public inputs of the header circuit: the commitment to the validators of the current epoch
+/// and the hash of the epoch header, each split into a high and a low 128-bit limb so that
+/// they fit into the scalar field.
+pub fn header_public_inputs(validators: &[Validator], header_hash: &Hash) -> Vec<[u8; 32]> {
+    let commitment = validators_commitment(validators);
+    [split_hash(&commitment), split_hash(header_hash)].concat()
+}
+
+fn split_hash(hash: &Hash) -> Vec<[u8; 32]> {
+    let mut hi = [0u8; 32];
+    hi[16..].copy_from_slice(&hash[..16]);
+    let mut lo = [0u8; 32];
+    lo[16..].copy_from_slice(&hash[16..]);
+    vec![hi, lo]
+}
+
+/// Checks `e(-A, B) * e(alpha, beta) * e(vk_x, gamma) * e(C, delta) == 1`, where
+/// `vk_x = ic[0] + sum(input[i] * ic[i + 1])`. Public inputs are 32 bytes big endian.
+pub fn verify_groth16(vk: &VerifyingKey, proof: &Groth16Proof, inputs: &[[u8; 32]]) -> bool {
+    assert_eq!(
+        vk.ic.len(),
+        inputs.len() + 1,
+        "public inputs do not match the verifying key"
+    );
+
+    let vk_x = linear_combination(&vk.ic, inputs);
+    let buf = pack_pairs(&[
+        (&proof.a.neg(), &proof.b),
+        (&vk.alpha_g1, &vk.beta_g2),
+        (&vk_x, &vk.gamma_g2),
+        (&proof.c, &vk.delta_g2),
+    ]);
+
+    let res;
+    unsafe {
+        res = near_sys::alt_bn128_pairing_check(buf.len() as _, buf.as_ptr() as _);
+    }
+
+    res == 1
+}
+
+fn linear_combination(ic: &[G1], inputs: &[[u8; 32]]) -> G1 {
+    let mut one = [0u8; 32];
+    one[31] = 1;
+
+    let buf: Vec<u8> = ic
+        .iter()
+        .zip([one].iter().chain(inputs.iter()))
+        .flat_map(|(point, scalar)| {
+            [
+                to_le_bytes(&point.x),
+                to_le_bytes(&point.y),
+                to_le_bytes(scalar),
+            ]
+            .concat()
+        })
+        .collect();
+
+    unsafe {
+        near_sys::alt_bn128_g1_multiexp(buf.len() as _, buf.as_ptr() as _, ALT_BN128_REGISTER);
+    }
+
+    let res = env::read_register(ALT_BN128_REGISTER).expect(REGISTER_EXPECTED_ERR);
+    assert_eq!(
+        G1_PUBLIC_KEY_LENGTH,
+        res.len(),
+        "result of alt_bn128_g1_multiexp get invalid length: {}",
+        res.len()
+    );
+
+    G1::from_le_slice(res.as_slice()).unwrap()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::serde_json;
+
+    // generated with known trapdoors for the public inputs [5, 7]
+    const VERIFYING_KEY: &str = r#"{
+        "alpha_g1": {
+            "x": "0x2a14705537b009189da8808651eecdb82482477fe92ac12ca8b71f80fc3d49ef",
+            "y": "0x2df7ee7f243ea8b38e1ddf14029258877a618c779fd4717db6177e19ea67ec38"
+        },
+        "beta_g2": {
+            "xr": "0x23ad66f3a7cca9dc75049635faebd124316244b91de5fb2764cd151572a905f7",
+            "xi": "0x009edaf0698a8c56f51139588acc094cee3c37d427bb6d2eab830aae529097d1",
+            "yr": "0x1ad4f87d3b4375a39988ac099b042b1e7c0c715678e4c2bea8905f607cf950f8",
+            "yi": "0x2700e8a29b7bb45f3022a18a07bdc66d0254559e17cce64e3b4ad21578fcf410"
+        },
+        "gamma_g2": {
+            "xr": "0x0c51a367b61d3119677b29739ddccbb78002b5558d8f49ff16e299c1b41f8098",
+            "xi": "0x227071bba5ff3b47ed8b504bb5b215bc701d7a3259b933bff1a4164eae499c2c",
+            "yr": "0x18fd2fd13be8494c39e8a91325d1ef3ba7d1a205d10788e38bc9e09d9be87769",
+            "yi": "0x08bb188b2a6187bb1e87834c85a6a917763d65b98febf2c45ea339dd77fac415"
+        },
+        "delta_g2": {
+            "xr": "0x15cbba9ab73bc73d0ba4ad132a15cb0c73107a9c19b040c4c73d89f6bf75404d",
+            "xi": "0x25407be35f18c6594174374841311466c0e66ff003762448c06bca4fa5e9c54e",
+            "yr": "0x2c750c045112e4ab07f18b12475309cebdcb726bda1ca9948bacd498a28cf411",
+            "yi": "0x1edef86c1a42fa85ab6ae8d268a7e9b46890b2130dd83b91c86c504cf1f93fbf"
+        },
+        "ic": [
+            {
+                "x": "0x1e28260f0ee971dec1e84cf81ff2776ad314d2cfb9ef81d4c970620c29b811f1",
+                "y": "0x28fc8a72d4ff12654c3c39dab54eaef9638d28de738959779fcd3e7ac918b396"
+            },
+            {
+                "x": "0x2d3884ee594280d7510d2d0d5e732e53085668e1b6d8f91de3942f922ec35fb2",
+                "y": "0x152fb4da392da5c238d1e65a12a41f89c9ab5f5e7a77107ad38e34a1b835ce5b"
+            },
+            {
+                "x": "0x2de898859c789fa5beaea4903464a93bb7e8ebddc623ee0f4f6cb34aea8fdb80",
+                "y": "0x05675bec1a5051d14a177bfa6f36073c6903ef399f1a516d1499410b8f9c1490"
+            }
+        ]
+    }"#;
+
+    const PROOF: &str = r#"{
+        "a": {
+            "x": "0x1605ffc1ea2e1aef15d774d3207176420c5cc454b19b55558562b0c7ddf00a7d",
+            "y": "0x0cf605873faa8028df38ec2d0800d5ddc67f1776338d675491fe87f6bb7354b3"
+        },
+        "b": {
+            "xr": "0x1ae8bf2213b5f02d55e2ef7cb6aa28e4a5c0b10f8145e5f3e365e5d6e7938e99",
+            "xi": "0x2aeec44fe814d216dbf395d867287888ff68759bb9d5a0167109501aa9eb15dd",
+            "yr": "0x033ccc1affd4d27701ea9dc524dd3e7a8848bcae31f9eeade63b613d662fd900",
+            "yi": "0x012ba32451dff8aba8f20e5294fc0e4a53f9dabbfcabe7e595bf95abdfa25be8"
+        },
+        "c": {
+            "x": "0x152a8b6aabec44d31f946a278f741e0358a9ab43656a95167f2ae7cae9aef4bb",
+            "y": "0x19b8345331ffe490e253a5441d681305186575e00e0d55dd1bfe2981b7926d2a"
+        }
+    }"#;
+
+    fn scalar(v: u8) -> [u8; 32] {
+        let mut s = [0u8; 32];
+        s[31] = v;
+        s
+    }
+
+    #[test]
+    fn verifies_groth16_proof() {
+        let vk: VerifyingKey = serde_json::from_str(VERIFYING_KEY).unwrap();
+        let proof: Groth16Proof = serde_json::from_str(PROOF).unwrap();
+
+        assert!(verify_groth16(&vk, &proof, &[scalar(5), scalar(7)]));
+    }
+
+    #[test]
+    fn rejects_groth16_proof_with_wrong_inputs() {
+        let vk: VerifyingKey = serde_json::from_str(VERIFYING_KEY).unwrap();
+        let proof: Groth16Proof = serde_json::from_str(PROOF).unwrap();
+
+        assert!(!verify_groth16(&vk, &proof, &[scalar(5), scalar(8)]));
+        assert!(!verify_groth16(&vk, &proof, &[scalar(7), scalar(5)]));
+    }
+
+    #[test]
+    #[should_panic(expected = "public inputs do not match the verifying key")]
+    fn rejects_groth16_proof_with_wrong_input_count() {
+        let vk: VerifyingKey = serde_json::from_str(VERIFYING_KEY).unwrap();
+        let proof: Groth16Proof = serde_json::from_str(PROOF).unwrap();
+
+        verify_groth16(&vk, &proof, &[scalar(5)]);
+    }
+
+    #[test]
+    fn splits_hash_into_limbs() {
+        let mut hash: Hash = [0u8; 32];
+        for (i, b) in hash.iter_mut().enumerate() {
+            *b = i as u8 + 1;
+        }
+        let limbs = split_hash(&hash);
+
+        assert_eq!([0u8; 16], limbs[0][..16]);
+        assert_eq!(hash[..16], limbs[0][16..]);
+        assert_eq!([0u8; 16], limbs[1][..16]);
+        assert_eq!(hash[16..], limbs[1][16..]);
+    }
+}
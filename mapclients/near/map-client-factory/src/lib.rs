@@ -1,7 +1,8 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::serde_json::json;
 use near_sdk::{env, near_bindgen, Promise, Gas, AccountId};
-use near_sdk::json_types::U64;
+use near_sdk::json_types::{U128, U64};
+use map_light_client::header::Header;
 use map_light_client::Validator;
 
 const MAP_CLIENT_BINARY: &'static [u8] = include_bytes!("../../target/wasm32-unknown-unknown/release/map_light_client.wasm");
@@ -23,7 +24,8 @@ impl Factory {
         validators: Vec<Validator>,
         epoch: U64,
         epoch_size: U64,
-        owner: AccountId
+        owner: AccountId,
+        chain_id: Option<U128>
     ) -> Promise {
         let account_id = format!("{}.{}", name, env::current_account_id());
         Promise::new(account_id.parse().unwrap())
@@ -37,7 +39,37 @@ impl Factory {
                     "validators": validators,
                     "epoch": epoch,
                     "epoch_size": epoch_size,
-                    "owner": owner})
+                    "owner": owner,
+                    "chain_id": chain_id})
+                    .to_string()
+                    .as_bytes()
+                    .to_vec(),
+                0,
+                env::prepaid_gas() - CREATE_CALL_GAS,
+            )
+    }
+
+    #[payable]
+    pub fn create_map_client_from_epoch_header(
+        &mut self,
+        name: String,
+        header: Header,
+        epoch_size: U64,
+        owner: AccountId,
+        chain_id: Option<U128>
+    ) -> Promise {
+        let account_id = format!("{}.{}", name, env::current_account_id());
+        Promise::new(account_id.parse().unwrap())
+            .create_account()
+            .deploy_contract(MAP_CLIENT_BINARY.to_vec())
+            .transfer(env::attached_deposit())
+            .function_call(
+                "new_from_epoch_header".to_string(),
+                json!({
+                    "header": header,
+                    "epoch_size": epoch_size,
+                    "owner": owner,
+                    "chain_id": chain_id})
                     .to_string()
                     .as_bytes()
                     .to_vec(),
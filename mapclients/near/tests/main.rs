@@ -1,5 +1,9 @@
+use map_light_client::header::Header;
+use map_light_client::istanbul::IstanbulExtra;
+use map_light_client::proof::Receipt;
+use map_light_client::traits::ToRlp;
 use map_light_client::{EpochRecord, Validator};
-use near_sdk::json_types::U64;
+use near_sdk::json_types::{U128, U64};
 use serde_json::json;
 use std::fs;
 use workspaces::network::Sandbox;
@@ -440,6 +444,91 @@ async fn test_verify_proof_single_receipt() -> anyhow::Result<()> {
 
     assert!(res.is_success());
 
+    let mut header: serde_json::Value = serde_json::from_str(HEADER_0_012).unwrap();
+    let agg_pk: serde_json::Value = serde_json::from_str(AGG_PK_012).unwrap();
+    header["receiptHash"] =
+        json!("0xd090197904157b8a7b8a72e6b15fc9d149fc80b3a3bc8b3599083568967f4276");
+    header["bloom"] = json!("0x00000000000000000080000000000000000000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000020000000000000000000800000000000000000000000000000000000000000000000000000000000000000100000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000000000");
+    header["extra"] = json!("0x0000000000000000000000000000000000000000000000000000000000000000f891c0c0c080b841e0dbb5877abaa1a38bb4b4e221b51ef46b6d9067c41c57f496700afa0b3554aa4164abf592e4b205b2d8755c30cc66999dafd23b801d083ca21583acfe4c360701f84407b8402ec7a880873b670f887588b19f4c480d6e468203efcfb2011431b83155c34460092139d969c94aec32774c019a8d0ba8639cd7e382ed835ed75927352789804301c3808080");
+    let res = contract
+        .call(&worker, "verify_proof_data")
+        .args_json(json!({
+            "receipt_proof" : {
+            "header": header,
+            "agg_pk": agg_pk,
+            "receipt": {
+                    "receipt_type": "1",
+                    "post_state_or_status": "0x00",
+                    "cumulative_gas_used": "2000",
+                    "bloom": "0x00000000000000000080000000000000000000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000020000000000000000000800000000000000000000000000000000000000000000000000000000000000000100000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000000000",
+                    "logs": [{
+                        "address": "0x0000000000000000000000000000000000000000",
+                        "topics": ["0x0000000000000000000000000000000000000000000000000000000000000000"],
+                        "data": "0x00"}]
+            },
+                "key_index":"0x80",
+                "proof":["0xf9014d822080b9014701f90143008207d0b9010000000000000000000080000000000000000000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000020000000000000000000800000000000000000000000000000000000000000000000000000000000000000100000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000000000f83af838940000000000000000000000000000000000000000e1a0000000000000000000000000000000000000000000000000000000000000000000"]
+        }
+,
+        }))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    assert!(res.is_success());
+
+    let res = contract
+        .call(&worker, "verify_proof_data")
+        .args_json(json!({
+            "receipt_proof" : {
+            "header": header,
+            "agg_pk": agg_pk,
+            "receipt": {
+                    "receipt_type": "1",
+                    "post_state_or_status": "0x00",
+                    "cumulative_gas_used": "2000",
+                    "bloom": "0x00000000000000000080000000000000000000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000020000000000000000000800000000000000000000000000000000000000000000000000000000000000000100000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000000000",
+                    "logs": [{
+                        "address": "0x0000000000000000000000000000000000000000",
+                        "topics": ["0x0000000000000000000000000000000000000000000000000000000000000000"],
+                        "data": "0x00"}]
+            },
+                "key_index":"0x80",
+                "proof":["0xf9014d822080b9014701f90143008207d0b9010000000000000000000080000000000000000000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000020000000000000000000800000000000000000000000000000000000000000000000000000000000000000100000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000000000f83af838940000000000000000000000000000000000000000e1a0000000000000000000000000000000000000000000000000000000000000000000"]
+        }
+,
+            "strict": true
+        }))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+
+    assert!(res.is_err(), "strict verification of a failed receipt should fail");
+    assert!(
+        res.err()
+            .unwrap()
+            .to_string()
+            .contains("receipt of a failed transaction is not accepted"),
+        "get unexpected error"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_verify_proof_receipt_bloom_mismatch() -> anyhow::Result<()> {
+    let (worker, contract) = deploy_contract().await?;
+
+    let init_args: serde_json::Value = serde_json::from_str(INIT_VALUE).unwrap();
+    let res = contract
+        .call(&worker, "new")
+        .args_json(json!(init_args))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    assert!(res.is_success());
+
     let mut header: serde_json::Value = serde_json::from_str(HEADER_0_012).unwrap();
     let agg_pk: serde_json::Value = serde_json::from_str(AGG_PK_012).unwrap();
     header["receiptHash"] =
@@ -468,9 +557,16 @@ async fn test_verify_proof_single_receipt() -> anyhow::Result<()> {
         }))?
         .gas(300_000_000_000_000)
         .transact()
-        .await?;
+        .await;
 
-    assert!(res.is_success());
+    assert!(res.is_err(), "verify_proof_data should fail");
+    assert!(
+        res.err()
+            .unwrap()
+            .to_string()
+            .contains("receipt bloom does not match its logs"),
+        "get unexpected error"
+    );
 
     Ok(())
 }
@@ -490,6 +586,10 @@ async fn test_verify_proof_multi_receipt() -> anyhow::Result<()> {
 
     let mut header: serde_json::Value = serde_json::from_str(HEADER_0_012).unwrap();
     let agg_pk: serde_json::Value = serde_json::from_str(AGG_PK_012).unwrap();
+    header["receiptHash"] =
+        json!("0x8f3f5b9121857970cd1c5ab65359a14f55bfe2364bf944246c0f166ec1630674");
+    header["bloom"] = json!("0x00000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000040000000020000000040000000000000000000000000000000000000000000000040000000000000000000000000000000000000000000000000000000040000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000008000000000000000000000000000000000000000000000000000000000000000000000040000000000000000000000000000000000000000000000000000000000000000000");
+    header["extra"] = json!("0x0000000000000000000000000000000000000000000000000000000000000000f891c0c0c001b8414ad00f590d5b43908a16f3c3660638a212f0dbe46d5f417f580da27c71aee9dd2490a2c04ae8b749dee5c71ca848f11eff25238e5737e818cdf64bb33efb64bb01f84407b8402447cfcc172f5f09f4c8d7a09c151660148e5d6e98c08944e6dfb06424a57b5a094611a99c3baf5dbdcfd4be6432dfe1b87ebf114903fa2513e7d84a4d81cb6b01c3808080");
     let res = contract
         .call(&worker, "verify_proof_data")
         .args_json(json!({
@@ -500,16 +600,16 @@ async fn test_verify_proof_multi_receipt() -> anyhow::Result<()> {
                     "receipt_type": "1",
                     "post_state_or_status": "0x01",
                     "cumulative_gas_used": "2000",
-                    "bloom": "0x01000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+                    "bloom": "0x00000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000040000000020000000040000000000000000000000000000000000000000000000040000000000000000000000000000000000000000000000000000000040000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000008000000000000000000000000000000000000000000000000000000000000000000000040000000000000000000000000000000000000000000000000000000000000000000",
                     "logs": [{
                         "address": "0x0100000000000000000000000000000000000000",
                         "topics": ["0x0000000000000000000000000000000000000000000000000000000000000001", "0x000000000000000000000000000000000000000000000000000000000000000a"],
                         "data": "0x01"}]
             },
                 "key_index":"0x01",
-                "proof":["0xf851a0141842b8380e7bf9240f0d0e46856a2675b85df570e95bdceec55b495629d4ca80808080808080a0a0a3ab578877d4702cfb44ffa01ae96b2365aacbe55715c8d6222174233dd6208080808080808080",
-                "0xf85180a01ee4849328db1b6425ffeb9c54f70604fa6e855a7d0d55a0471645e14ab7cae9a0275c5d8aa15b6a2f8b17619ce74fbf1bc92d11f0bae44f226b14dde1a603e25a8080808080808080808080808080",
-                "0xf9016d20b9016901f90165018207d0b9010001000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000f85cf85a940100000000000000000000000000000000000000f842a00000000000000000000000000000000000000000000000000000000000000001a0000000000000000000000000000000000000000000000000000000000000000a01"]
+                "proof":["0xf851a090bf7af24fe5e7c649db30f4eda00c6ebdfdf0a4e265ad0c80e0a24d6688085480808080808080a0a0a3ab578877d4702cfb44ffa01ae96b2365aacbe55715c8d6222174233dd6208080808080808080",
+                "0xf85180a02ae7d3222914b2060170e8afafd46eaca29fb3ef07df799135aa26522bd03f3aa0275c5d8aa15b6a2f8b17619ce74fbf1bc92d11f0bae44f226b14dde1a603e25a8080808080808080808080808080",
+                "0xf9016d20b9016901f90165018207d0b9010000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000040000000020000000040000000000000000000000000000000000000000000000040000000000000000000000000000000000000000000000000000000040000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000008000000000000000000000000000000000000000000000000000000000000000000000040000000000000000000000000000000000000000000000000000000000000000000f85cf85a940100000000000000000000000000000000000000f842a00000000000000000000000000000000000000000000000000000000000000001a0000000000000000000000000000000000000000000000000000000000000000a01"]
         }
 ,
         }))?
@@ -727,6 +827,7 @@ async fn test_get_header_height() -> anyhow::Result<()> {
 
     let height: U64 = contract
         .call(&worker, "get_header_height")
+        .args_json(json!({}))?
         .view()
         .await?
         .json()?;
@@ -755,7 +856,7 @@ async fn test_get_header_height() -> anyhow::Result<()> {
 
     let height: U64 = contract
         .call(&worker, "get_header_height")
-        .args_json({})?
+        .args_json(json!({}))?
         .gas(300_000_000_000_000)
         .transact()
         .await?
@@ -788,6 +889,7 @@ async fn test_get_verifiable_header_range() -> anyhow::Result<()> {
 
     let range: (U64, U64) = contract
         .call(&worker, "get_verifiable_header_range")
+        .args_json(json!({}))?
         .view()
         .await?
         .json()?;
@@ -815,6 +917,7 @@ async fn test_get_verifiable_header_range() -> anyhow::Result<()> {
 
     let range: (U64, U64) = contract
         .call(&worker, "get_verifiable_header_range")
+        .args_json(json!({}))?
         .view()
         .await?
         .json()?;
@@ -840,6 +943,7 @@ async fn test_get_epoch_size() -> anyhow::Result<()> {
 
     let epoch_size: U64 = contract
         .call(&worker, "get_epoch_size")
+        .args_json(json!({}))?
         .view()
         .await?
         .json()?;
@@ -1229,16 +1333,1211 @@ async fn test_verify_proof_after_remove_validator() -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn deploy_contract() -> anyhow::Result<(Worker<Sandbox>, Contract)> {
-    std::env::var(NEAR_SANDBOX_BIN_PATH)
-        .expect("environment variable NEAR_SANDBOX_BIN_PATH should be set");
+#[tokio::test]
+async fn test_verify_proof_rlp() -> anyhow::Result<()> {
+    let (worker, contract) = deploy_contract().await?;
 
-    let worker = workspaces::sandbox().await?;
-    let contract = worker
-        .dev_deploy(&std::fs::read(MAP_CLIENT_WASM_FILEPATH)?)
+    let file = fs::File::open("./tests/data/init_value.json").unwrap();
+    let mut init_args: serde_json::Value = serde_json::from_reader(file).unwrap();
+    init_args["epoch"] = json!("187");
+    let res = contract
+        .call(&worker, "new")
+        .args_json(json!(init_args))?
+        .gas(300_000_000_000_000)
+        .transact()
         .await?;
 
-    println!("deploy contract id: {:?}", contract.id());
+    assert!(res.is_success(), "init contract failed!");
 
-    Ok((worker, contract))
+    let file = fs::File::open("./tests/data/header.json").unwrap();
+    let headers: serde_json::Value = serde_json::from_reader(file).unwrap();
+    let header: Header = serde_json::from_str(&headers["187000"]["header"].to_string()).unwrap();
+
+    let res = contract
+        .call(&worker, "update_block_header_rlp")
+        .args_json(json!({
+            "header": format!("0x{}", hex::encode(header.to_rlp())),
+            "agg_pk": headers["187000"]["agg_pk"]
+        }))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    println!("logs {:?}", res.logs());
+    assert!(res.is_success(), "update_block_header_rlp 187000 failed");
+
+    let file = fs::File::open("./tests/data/proof.json").unwrap();
+    let proofs: serde_json::Value = serde_json::from_reader(file).unwrap();
+    let proof = &proofs["187133"];
+    let header: Header = serde_json::from_str(&proof["header"].to_string()).unwrap();
+    let receipt: Receipt = serde_json::from_str(&proof["receipt"].to_string()).unwrap();
+
+    let res = contract
+        .call(&worker, "verify_proof_data_rlp")
+        .args_json(json!({
+            "receipt_proof": {
+                "header": format!("0x{}", hex::encode(header.to_rlp())),
+                "agg_pk": proof["agg_pk"],
+                "receipt": format!("0x{}", hex::encode(receipt.encode_index())),
+                "key_index": proof["key_index"],
+                "proof": proof["proof"]
+            }
+        }))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    println!("logs {:?}", res.logs());
+    assert!(
+        res.is_success(),
+        "verify_proof_data_rlp for block 187133 failed"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_multiple_chains() -> anyhow::Result<()> {
+    let (worker, contract) = deploy_contract().await?;
+    let account = worker.dev_create_account().await?;
+
+    let mut init_args: serde_json::Value = serde_json::from_str(INIT_VALUE).unwrap();
+    init_args["owner"] = json!(contract.id());
+    let res = contract
+        .call(&worker, "new")
+        .args_json(json!(init_args))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    assert!(res.is_success(), "new contract failed");
+
+    let mut chain_args = init_args.clone();
+    chain_args.as_object_mut().unwrap().remove("owner");
+    chain_args["chain_id"] = json!("212");
+    let res = account
+        .call(&worker, contract.id(), "add_chain")
+        .args_json(json!(chain_args))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+    assert!(res.is_err(), "add_chain by non-owner should fail");
+
+    let res = contract
+        .call(&worker, "add_chain")
+        .args_json(json!(chain_args))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "add_chain failed");
+
+    let res = contract
+        .call(&worker, "add_chain")
+        .args_json(json!(chain_args))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+    assert!(res.is_err(), "add_chain with existing chain id should fail");
+
+    let chain_ids: Vec<U128> = contract
+        .call(&worker, "get_chain_ids")
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(vec![U128(22776), U128(212)], chain_ids);
+
+    let header: serde_json::Value = serde_json::from_str(HEADER_0_012).unwrap();
+    let agg_pk: serde_json::Value = serde_json::from_str(AGG_PK_012).unwrap();
+    let res = contract
+        .call(&worker, "update_block_header")
+        .args_json(json!({
+            "header": header,
+            "agg_pk": agg_pk,
+            "chain_id": "212"
+        }))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "update_block_header for chain 212 failed");
+
+    let height: U64 = contract
+        .call(&worker, "get_header_height")
+        .args_json(json!({"chain_id": "212"}))?
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(1000, height.0, "header height of chain 212 is not updated");
+
+    let height: U64 = contract
+        .call(&worker, "get_header_height")
+        .args_json(json!({}))?
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(0, height.0, "header height of the default chain should not change");
+
+    let res = contract
+        .call(&worker, "update_block_header")
+        .args_json(json!({
+            "header": header,
+            "agg_pk": agg_pk,
+            "chain_id": "1"
+        }))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+    assert!(res.is_err(), "update_block_header for unknown chain should fail");
+
+    let res = contract
+        .call(&worker, "update_chain_id")
+        .args_json(json!({"chain_id": "212", "new_chain_id": "213"}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "update_chain_id failed");
+
+    let res = contract
+        .call(&worker, "add_chain")
+        .args_json(json!(chain_args))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "add_chain with the moved chain id failed");
+
+    let height: U64 = contract
+        .call(&worker, "get_header_height")
+        .args_json(json!({"chain_id": "213"}))?
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(1000, height.0, "the moved chain should keep its state");
+
+    let record: serde_json::Value = contract
+        .call(&worker, "get_record_for_epoch")
+        .args_json(json!({"epoch": "2", "chain_id": "212"}))?
+        .view()
+        .await?
+        .json()?;
+    assert!(record.is_null(), "the added chain should not share the storage of the moved chain");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stale_client() -> anyhow::Result<()> {
+    let (worker, contract) = deploy_contract().await?;
+
+    let file = fs::File::open("./tests/data/init_value.json").unwrap();
+    let mut init_args: serde_json::Value = serde_json::from_reader(file).unwrap();
+    init_args["epoch"] = json!("187");
+    init_args["owner"] = json!(contract.id());
+    let res = contract
+        .call(&worker, "new")
+        .args_json(json!(init_args))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    assert!(res.is_success(), "init contract failed!");
+
+    let file = fs::File::open("./tests/data/header.json").unwrap();
+    let headers: serde_json::Value = serde_json::from_reader(file).unwrap();
+    let res = contract
+        .call(&worker, "update_block_header")
+        .args_json(json!(headers["187000"]))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    assert!(res.is_success(), "update_block_header 187000 failed");
+
+    let stale: bool = contract
+        .call(&worker, "is_stale")
+        .args_json(json!({}))?
+        .view()
+        .await?
+        .json()?;
+    assert!(!stale, "client should not be stale without max staleness");
+
+    let res = contract
+        .call(&worker, "set_max_staleness")
+        .args_json(json!({"max_staleness": "1"}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "set_max_staleness failed");
+
+    let stale: bool = contract
+        .call(&worker, "is_stale")
+        .args_json(json!({}))?
+        .view()
+        .await?
+        .json()?;
+    assert!(stale, "client should be stale");
+
+    let file = fs::File::open("./tests/data/proof.json").unwrap();
+    let proofs: serde_json::Value = serde_json::from_reader(file).unwrap();
+    let res = contract
+        .call(&worker, "verify_proof_data")
+        .args_json(json!({"receipt_proof": proofs["187133"]}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+
+    assert!(res.is_err(), "verify_proof_data should fail on stale client");
+    assert!(
+        res.err()
+            .unwrap()
+            .to_string()
+            .contains("light client is stale"),
+        "should be stale client error"
+    );
+
+    let res = contract
+        .call(&worker, "set_max_staleness")
+        .args_json(json!({"max_staleness": "0"}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "set_max_staleness failed");
+
+    let res = contract
+        .call(&worker, "verify_proof_data")
+        .args_json(json!({"receipt_proof": proofs["187133"]}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    assert!(
+        res.is_success(),
+        "verify_proof_data for block 187133 failed"
+    );
+
+    let res = contract
+        .call(&worker, "set_max_staleness")
+        .args_json(json!({"max_staleness": u64::MAX.to_string()}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "set_max_staleness failed");
+
+    let stale: bool = contract
+        .call(&worker, "is_stale")
+        .args_json(json!({}))?
+        .view()
+        .await?
+        .json()?;
+    assert!(!stale, "client should not be stale with the maximum staleness");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_update_block_header_zk() -> anyhow::Result<()> {
+    let (worker, contract) = deploy_contract().await?;
+
+    let file = fs::File::open("./tests/data/init_value.json").unwrap();
+    let mut init_args: serde_json::Value = serde_json::from_reader(file).unwrap();
+    init_args["epoch"] = json!("187");
+    init_args["owner"] = json!(contract.id());
+    let res = contract
+        .call(&worker, "new")
+        .args_json(json!(init_args))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    assert!(res.is_success(), "init contract failed!");
+
+    let file = fs::File::open("./tests/data/header.json").unwrap();
+    let headers: serde_json::Value = serde_json::from_reader(file).unwrap();
+    let file = fs::File::open("./tests/data/zk_proof.json").unwrap();
+    let zk_proofs: serde_json::Value = serde_json::from_reader(file).unwrap();
+
+    let res = contract
+        .call(&worker, "update_block_header_zk")
+        .args_json(json!({
+            "header": headers["187000"]["header"],
+            "proof": zk_proofs["187000"]["proof"]
+        }))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+    assert!(
+        res.is_err(),
+        "update_block_header_zk should fail without verifying key"
+    );
+
+    let res = contract
+        .call(&worker, "set_zk_verifying_key")
+        .args_json(json!({
+            "verifying_key": zk_proofs["187000"]["verifying_key"]
+        }))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "set_zk_verifying_key failed");
+
+    let mut bad_proof = zk_proofs["187000"]["proof"].clone();
+    bad_proof["c"] = bad_proof["a"].clone();
+    let res = contract
+        .call(&worker, "update_block_header_zk")
+        .args_json(json!({
+            "header": headers["187000"]["header"],
+            "proof": bad_proof
+        }))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+    assert!(res.is_err(), "update_block_header_zk with bad proof should fail");
+    assert!(
+        res.err()
+            .unwrap()
+            .to_string()
+            .contains("check zk proof failed"),
+        "should be zk proof error"
+    );
+
+    let res = contract
+        .call(&worker, "update_block_header_zk")
+        .args_json(json!({
+            "header": headers["187000"]["header"],
+            "proof": zk_proofs["187000"]["proof"]
+        }))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    println!("logs {:?}", res.logs());
+    assert!(res.is_success(), "update_block_header_zk 187000 failed");
+
+    let height: U64 = contract
+        .call(&worker, "get_header_height")
+        .args_json(json!({}))?
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(187000, height.0, "header height is not updated");
+
+    let record: Option<EpochRecord> = contract
+        .call(&worker, "get_record_for_epoch")
+        .args_json(json!({"epoch": "188"}))?
+        .view()
+        .await?
+        .json()?;
+    assert!(record.is_some(), "validators of epoch 188 should be set");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_new_from_epoch_header() -> anyhow::Result<()> {
+    let (worker, contract) = deploy_contract().await?;
+
+    let file = fs::File::open("./tests/data/init_value.json").unwrap();
+    let init_args: serde_json::Value = serde_json::from_reader(file).unwrap();
+    let file = fs::File::open("./tests/data/header.json").unwrap();
+    let headers: serde_json::Value = serde_json::from_reader(file).unwrap();
+
+    let res = contract
+        .call(&worker, "new_from_epoch_header")
+        .args_json(json!({
+            "header": headers["3000"]["header"],
+            "epoch_size": "3000",
+            "owner": init_args["owner"]
+        }))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+    assert!(res.is_err(), "new_from_epoch_header should fail");
+    assert!(
+        res.err()
+            .unwrap()
+            .to_string()
+            .contains("block 3000 is not the genesis block"),
+        "should be not genesis block error"
+    );
+
+    // the validators of epoch 3 are the initial validators of a chain with epochs of 3000 blocks
+    let genesis = genesis_header(&init_args["validators"], &headers["3000"]["header"]);
+    let res = contract
+        .call(&worker, "new_from_epoch_header")
+        .args_json(json!({
+            "header": genesis,
+            "epoch_size": "3000",
+            "owner": init_args["owner"]
+        }))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    println!("logs {:?}", res.logs());
+    assert!(res.is_success(), "new_from_epoch_header failed");
+
+    let record: EpochRecord = contract
+        .call(&worker, "get_record_for_epoch")
+        .args_json(json!({"epoch": "1"}))?
+        .view()
+        .await?
+        .json()?;
+    let validators: Vec<Validator> = serde_json::from_str(&init_args["validators"].to_string())?;
+    assert_eq!(validators, record.validators, "validators are not derived from genesis header");
+    assert_eq!(3, record.threshold.0, "threshold should be total - total / 3");
+
+    let height: U64 = contract
+        .call(&worker, "get_header_height")
+        .args_json(json!({}))?
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(0, height.0, "get_header_height get unexpected result");
+
+    let res = contract
+        .call(&worker, "update_block_header")
+        .args_json(json!(headers["3000"]))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "update_block_header 3000 failed");
+
+    let height: U64 = contract
+        .call(&worker, "get_header_height")
+        .args_json(json!({}))?
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(3000, height.0, "get_header_height get unexpected result");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_new_from_epoch_header_mismatched_keys() -> anyhow::Result<()> {
+    let (worker, contract) = deploy_contract().await?;
+
+    let file = fs::File::open("./tests/data/init_value.json").unwrap();
+    let init_args: serde_json::Value = serde_json::from_reader(file).unwrap();
+    let file = fs::File::open("./tests/data/header.json").unwrap();
+    let headers: serde_json::Value = serde_json::from_reader(file).unwrap();
+
+    let mut validators = init_args["validators"].clone();
+    validators[0]["_BLSG1PublicKey"] = validators[1]["_BLSG1PublicKey"].clone();
+    let genesis = genesis_header(&validators, &headers["3000"]["header"]);
+    let res = contract
+        .call(&worker, "new_from_epoch_header")
+        .args_json(json!({
+            "header": genesis,
+            "epoch_size": "3000",
+            "owner": init_args["owner"]
+        }))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+    assert!(res.is_err(), "new_from_epoch_header should fail");
+    assert!(
+        res.err()
+            .unwrap()
+            .to_string()
+            .contains("g1 and g2 public keys of added validator"),
+        "should be mismatched keys error"
+    );
+
+    Ok(())
+}
+
+/// Builds a genesis header adding the validators in its istanbul extra from another header.
+fn genesis_header(validators: &serde_json::Value, header: &serde_json::Value) -> serde_json::Value {
+    let validators = validators.as_array().unwrap();
+    let extra: IstanbulExtra = serde_json::from_str(
+        &json!({
+            "addedValidators": validators.iter().map(|v| v["address"].clone()).collect::<Vec<_>>(),
+            "addedPublicKeys": validators.iter().map(|v| v["_BLSPublicKey"].clone()).collect::<Vec<_>>(),
+            "addedG1PublicKeys": validators.iter().map(|v| v["_BLSG1PublicKey"].clone()).collect::<Vec<_>>(),
+            "removedValidators": "0x0",
+            "seal": "",
+            "aggregatedSeal": {"bitmap": "0x0", "signature": "", "round": "0x0"},
+            "parentAggregatedSeal": {"bitmap": "0x0", "signature": "", "round": "0x0"}
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    let mut genesis = header.clone();
+    genesis["number"] = json!("0x0");
+    genesis["extra"] = json!(format!("0x{}", hex::encode(extra.to_rlp(&[0u8; 32]))));
+    genesis
+}
+
+async fn deploy_contract() -> anyhow::Result<(Worker<Sandbox>, Contract)> {
+    std::env::var(NEAR_SANDBOX_BIN_PATH)
+        .expect("environment variable NEAR_SANDBOX_BIN_PATH should be set");
+
+    let worker = workspaces::sandbox().await?;
+    let contract = worker
+        .dev_deploy(&std::fs::read(MAP_CLIENT_WASM_FILEPATH)?)
+        .await?;
+
+    println!("deploy contract id: {:?}", contract.id());
+
+    Ok((worker, contract))
+}
+
+#[tokio::test]
+async fn test_fork_by_height() -> anyhow::Result<()> {
+    let (worker, contract) = deploy_contract().await?;
+
+    let file = fs::File::open("./tests/data/init_value.json").unwrap();
+    let mut init_args: serde_json::Value = serde_json::from_reader(file).unwrap();
+    init_args["epoch"] = json!("187");
+    init_args["owner"] = json!(contract.id());
+    let res = contract
+        .call(&worker, "new")
+        .args_json(json!(init_args))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    assert!(res.is_success(), "init contract failed!");
+
+    let res = contract
+        .call(&worker, "add_fork")
+        .args_json(json!({"fork": {"height": "188000", "seal_version": "V0", "signature_scheme": "Bls12381"}}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "add_fork failed");
+
+    let res = contract
+        .call(&worker, "add_fork")
+        .args_json(json!({"fork": {"height": "187000", "seal_version": "V0"}}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+    assert!(res.is_err(), "add_fork should fail on decreasing height");
+    assert!(
+        res.err()
+            .unwrap()
+            .to_string()
+            .contains("fork height should be greater than 188000"),
+        "should be fork height error"
+    );
+
+    let forks: serde_json::Value = contract
+        .call(&worker, "get_forks")
+        .args_json(json!({}))?
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(
+        json!([{"height": "188000", "seal_version": "V0", "signature_scheme": "Bls12381"}]),
+        forks
+    );
+
+    let file = fs::File::open("./tests/data/header.json").unwrap();
+    let headers: serde_json::Value = serde_json::from_reader(file).unwrap();
+
+    // blocks before the fork are still sealed over bn254
+    let res = contract
+        .call(&worker, "update_block_header")
+        .args_json(json!(headers["187000"]))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "update_block_header 187000 failed");
+
+    let res = contract
+        .call(&worker, "update_block_header")
+        .args_json(json!(headers["188000"]))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+    assert!(res.is_err(), "update_block_header 188000 should fail after the fork");
+    assert!(
+        res.err()
+            .unwrap()
+            .to_string()
+            .contains("is not registered"),
+        "should be bls12381 key not registered error"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_update_block_header_compressed_agg_pk() -> anyhow::Result<()> {
+    let (worker, contract) = deploy_contract().await?;
+
+    let file = fs::File::open("./tests/data/init_value.json").unwrap();
+    let mut init_args: serde_json::Value = serde_json::from_reader(file).unwrap();
+    init_args["epoch"] = json!("187");
+    let res = contract
+        .call(&worker, "new")
+        .args_json(json!(init_args))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    assert!(res.is_success(), "init contract failed!");
+
+    let file = fs::File::open("./tests/data/header.json").unwrap();
+    let headers: serde_json::Value = serde_json::from_reader(file).unwrap();
+    let mut args = headers["187000"].clone();
+    // compressed form of the agg_pk of block 187000
+    args["agg_pk"] = json!("0xd73740e787cf48a62d209cc28726f6f61a4f24d5d937c64f5c464635f9606c250828c2278ae207b748700324fca1d766ca80c694bcdb7ac1469646e95b595609");
+
+    let res = contract
+        .call(&worker, "update_block_header")
+        .args_json(args)?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "update_block_header 187000 failed");
+
+    let record: serde_json::Value = contract
+        .call(&worker, "get_record_for_epoch")
+        .args_json(json!({"epoch": "188"}))?
+        .view()
+        .await?
+        .json()?;
+    assert!(!record.is_null(), "epoch 188 should be recorded");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bls12381_fork() -> anyhow::Result<()> {
+    let (worker, contract) = deploy_contract().await?;
+
+    let file = fs::File::open("./tests/data/init_value.json").unwrap();
+    let mut init_args: serde_json::Value = serde_json::from_reader(file).unwrap();
+    init_args["epoch"] = json!("187");
+    init_args["owner"] = json!(contract.id());
+    let res = contract
+        .call(&worker, "new")
+        .args_json(json!(init_args))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    assert!(res.is_success(), "init contract failed!");
+
+    let res = contract
+        .call(&worker, "add_fork")
+        .args_json(json!({"fork": {"height": "187001", "seal_version": "V0", "signature_scheme": "Bls12381"}}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "add_fork failed");
+
+    let file = fs::File::open("./tests/data/header.json").unwrap();
+    let headers: serde_json::Value = serde_json::from_reader(file).unwrap();
+    let res = contract
+        .call(&worker, "update_block_header")
+        .args_json(json!(headers["187000"]))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "update_block_header 187000 failed");
+
+    let validator = headers["188000"]["header"]["coinbase"].clone();
+    let registration = json!({
+        "validator": validator,
+        "key": format!("0x{}", "00".repeat(192)),
+        "pop": format!("0x{}", "00".repeat(96)),
+        "signature": format!("0x{}", "00".repeat(65)),
+    });
+    let res = contract
+        .call(&worker, "register_bls12381_key")
+        .args_json(json!({ "registration": registration }))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+    assert!(res.is_err(), "register_bls12381_key without validator signature should fail");
+
+    let keys: Option<Vec<Option<serde_json::Value>>> = contract
+        .call(&worker, "get_bls12381_keys")
+        .args_json(json!({"epoch": "188"}))?
+        .view()
+        .await?
+        .json()?;
+    assert!(
+        keys.unwrap().iter().all(|key| key.is_none()),
+        "no bls12381 key should be registered"
+    );
+
+    let res = contract
+        .call(&worker, "update_block_header")
+        .args_json(json!(headers["188000"]))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+    assert!(res.is_err(), "update_block_header 188000 should fail without bls12381 keys");
+    assert!(
+        res.err()
+            .unwrap()
+            .to_string()
+            .contains("is not registered"),
+        "should be missing bls12381 key error"
+    );
+
+    Ok(())
+}
+
+/// Rewrites a header of the fixtures in the layout returned by `eth_getBlockByNumber`.
+fn to_rpc_header(header: &serde_json::Value) -> serde_json::Value {
+    json!({
+        "hash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+        "parentHash": header["parentHash"],
+        "miner": header["coinbase"],
+        "stateRoot": header["root"],
+        "transactionsRoot": header["txHash"],
+        "receiptsRoot": header["receiptHash"],
+        "logsBloom": header["bloom"],
+        "number": header["number"],
+        "gasLimit": header["gasLimit"],
+        "gasUsed": header["gasUsed"],
+        "timestamp": header["time"],
+        "extraData": header["extra"],
+        "mixHash": header["mixDigest"],
+        "nonce": header["nonce"],
+        "baseFeePerGas": header["baseFee"],
+        "transactions": []
+    })
+}
+
+/// Rewrites a receipt of the fixtures in the layout returned by `eth_getTransactionReceipt`.
+fn to_rpc_receipt(receipt: &serde_json::Value) -> serde_json::Value {
+    let quantity = |v: &serde_json::Value| {
+        format!("0x{:x}", v.as_str().unwrap().parse::<u64>().unwrap())
+    };
+    let status = match receipt["post_state_or_status"].as_str().unwrap() {
+        "0x01" => "0x1",
+        _ => "0x0",
+    };
+
+    json!({
+        "type": quantity(&receipt["receipt_type"]),
+        "status": status,
+        "cumulativeGasUsed": quantity(&receipt["cumulative_gas_used"]),
+        "logsBloom": receipt["bloom"],
+        "logs": receipt["logs"],
+        "transactionIndex": "0x0"
+    })
+}
+
+#[tokio::test]
+async fn test_verify_proof_rpc() -> anyhow::Result<()> {
+    let (worker, contract) = deploy_contract().await?;
+
+    let file = fs::File::open("./tests/data/init_value.json").unwrap();
+    let mut init_args: serde_json::Value = serde_json::from_reader(file).unwrap();
+    init_args["epoch"] = json!("187");
+    let res = contract
+        .call(&worker, "new")
+        .args_json(json!(init_args))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    assert!(res.is_success(), "init contract failed!");
+
+    let file = fs::File::open("./tests/data/header.json").unwrap();
+    let headers: serde_json::Value = serde_json::from_reader(file).unwrap();
+
+    let res = contract
+        .call(&worker, "update_block_header_rpc")
+        .args_json(json!({
+            "header": to_rpc_header(&headers["187000"]["header"]),
+            "agg_pk": headers["187000"]["agg_pk"]
+        }))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    println!("logs {:?}", res.logs());
+    assert!(res.is_success(), "update_block_header_rpc 187000 failed");
+
+    let file = fs::File::open("./tests/data/proof.json").unwrap();
+    let proofs: serde_json::Value = serde_json::from_reader(file).unwrap();
+    let proof = &proofs["187133"];
+
+    let res = contract
+        .call(&worker, "verify_proof_data_rpc")
+        .args_json(json!({
+            "receipt_proof": {
+                "header": to_rpc_header(&proof["header"]),
+                "agg_pk": proof["agg_pk"],
+                "receipt": to_rpc_receipt(&proof["receipt"]),
+                "key_index": proof["key_index"],
+                "proof": proof["proof"]
+            }
+        }))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    println!("logs {:?}", res.logs());
+    assert!(
+        res.is_success(),
+        "verify_proof_data_rpc for block 187133 failed"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_verify_receipt_absent() -> anyhow::Result<()> {
+    let (worker, contract) = deploy_contract().await?;
+
+    let file = fs::File::open("./tests/data/init_value.json").unwrap();
+    let mut init_args: serde_json::Value = serde_json::from_reader(file).unwrap();
+    init_args["epoch"] = json!("187");
+    let res = contract
+        .call(&worker, "new")
+        .args_json(json!(init_args))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    assert!(res.is_success(), "init contract failed!");
+
+    let file = fs::File::open("./tests/data/header.json").unwrap();
+    let headers: serde_json::Value = serde_json::from_reader(file).unwrap();
+    let res = contract
+        .call(&worker, "update_block_header")
+        .args_json(json!(headers["187000"]))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    assert!(res.is_success(), "update_block_header 187000 failed");
+
+    // the receipt trie of block 187133 only has the receipt of index 0
+    let file = fs::File::open("./tests/data/proof.json").unwrap();
+    let proofs: serde_json::Value = serde_json::from_reader(file).unwrap();
+    let proof = &proofs["187133"];
+
+    let res = contract
+        .call(&worker, "verify_receipt_absent")
+        .args_json(json!({
+            "absence_proof": {
+                "header": proof["header"],
+                "agg_pk": proof["agg_pk"],
+                "key_index": "0x01",
+                "proof": proof["proof"]
+            }
+        }))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    println!("logs {:?}", res.logs());
+    assert!(res.is_success(), "verify_receipt_absent for index 1 failed");
+
+    let res = contract
+        .call(&worker, "verify_receipt_absent")
+        .args_json(json!({
+            "absence_proof": {
+                "header": proof["header"],
+                "agg_pk": proof["agg_pk"],
+                "key_index": proof["key_index"],
+                "proof": proof["proof"]
+            }
+        }))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+
+    assert!(res.is_err(), "verify_receipt_absent for index 0 should fail");
+    assert!(
+        res.err()
+            .unwrap()
+            .to_string()
+            .contains("receipt 80 is included in block 187133"),
+        "should be receipt included error"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_verification_fee() -> anyhow::Result<()> {
+    let (worker, contract) = deploy_contract().await?;
+    let relayer = worker.dev_create_account().await?;
+
+    let file = fs::File::open("./tests/data/init_value.json").unwrap();
+    let mut init_args: serde_json::Value = serde_json::from_reader(file).unwrap();
+    init_args["epoch"] = json!("187");
+    init_args["owner"] = json!(contract.id());
+    let res = contract
+        .call(&worker, "new")
+        .args_json(json!(init_args))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    assert!(res.is_success(), "init contract failed!");
+
+    // 0.01 NEAR
+    let fee: u128 = 10_000_000_000_000_000_000_000;
+    let res = relayer
+        .call(&worker, contract.id(), "set_verification_fee")
+        .args_json(json!({"fee": fee.to_string()}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+    assert!(res.is_err(), "set_verification_fee should fail for non-owner");
+
+    let res = contract
+        .call(&worker, "set_verification_fee")
+        .args_json(json!({"fee": "1000"}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+    assert!(res.is_err(), "set_verification_fee should fail below the storage cost of a reward entry");
+    assert!(
+        res.err()
+            .unwrap()
+            .to_string()
+            .contains("verification fee 1000 is less than the storage cost"),
+        "should be verification fee storage error"
+    );
+
+    let res = contract
+        .call(&worker, "set_verification_fee")
+        .args_json(json!({"fee": fee.to_string()}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "set_verification_fee failed");
+
+    // the relayer submits the header starting epoch 188
+    let file = fs::File::open("./tests/data/header.json").unwrap();
+    let headers: serde_json::Value = serde_json::from_reader(file).unwrap();
+    let res = relayer
+        .call(&worker, contract.id(), "update_block_header")
+        .args_json(json!(headers["187000"]))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "update_block_header 187000 failed");
+
+    let epoch_relayer: Option<String> = contract
+        .call(&worker, "get_epoch_relayer")
+        .args_json(json!({"epoch": "188"}))?
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(Some(relayer.id().to_string()), epoch_relayer);
+
+    let file = fs::File::open("./tests/data/proof.json").unwrap();
+    let proofs: serde_json::Value = serde_json::from_reader(file).unwrap();
+
+    let res = contract
+        .call(&worker, "verify_proof_data")
+        .args_json(json!({"receipt_proof": proofs["187133"]}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+    assert!(res.is_err(), "verify_proof_data without fee should fail");
+    assert!(
+        res.err()
+            .unwrap()
+            .to_string()
+            .contains(&format!("attached deposit 0 is less than the verification fee {}", fee)),
+        "should be verification fee error"
+    );
+
+    let res = contract
+        .call(&worker, "verify_proof_data")
+        .args_json(json!({"receipt_proof": proofs["187133"]}))?
+        .gas(300_000_000_000_000)
+        .deposit(fee)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "verify_proof_data with fee failed");
+
+    let reward: U128 = contract
+        .call(&worker, "get_reward")
+        .args_json(json!({"account_id": relayer.id()}))?
+        .view()
+        .await?
+        .json()?;
+    // the first fee pays for the storage of the reward entry
+    let storage_cost = 302 * 10_000_000_000_000_000_000;
+    assert!(
+        reward.0 < fee && reward.0 >= fee - storage_cost,
+        "relayer should earn the verification fee less the storage of its reward entry"
+    );
+
+    let res = contract
+        .call(&worker, "verify_proof_data")
+        .args_json(json!({"receipt_proof": proofs["187133"]}))?
+        .gas(300_000_000_000_000)
+        .deposit(fee)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "verify_proof_data with fee failed");
+
+    let last_reward = reward.0;
+    let reward: U128 = contract
+        .call(&worker, "get_reward")
+        .args_json(json!({"account_id": relayer.id()}))?
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(last_reward + fee, reward.0, "relayer should earn the whole verification fee");
+
+    let res = relayer
+        .call(&worker, contract.id(), "withdraw_reward")
+        .args_json(json!({"amount": (reward.0 + 1).to_string()}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+    assert!(res.is_err(), "withdraw_reward should fail for more than the reward");
+
+    let res = relayer
+        .call(&worker, contract.id(), "withdraw_reward")
+        .args_json(json!({}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "withdraw_reward failed");
+
+    let reward: U128 = contract
+        .call(&worker, "get_reward")
+        .args_json(json!({"account_id": relayer.id()}))?
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(0, reward.0, "reward should be withdrawn");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_consumers() -> anyhow::Result<()> {
+    let (worker, contract) = deploy_contract().await?;
+    let consumer = worker.dev_create_account().await?;
+
+    let file = fs::File::open("./tests/data/init_value.json").unwrap();
+    let mut init_args: serde_json::Value = serde_json::from_reader(file).unwrap();
+    init_args["epoch"] = json!("187");
+    init_args["owner"] = json!(contract.id());
+    let res = contract
+        .call(&worker, "new")
+        .args_json(json!(init_args))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+
+    assert!(res.is_success(), "init contract failed!");
+
+    let file = fs::File::open("./tests/data/header.json").unwrap();
+    let headers: serde_json::Value = serde_json::from_reader(file).unwrap();
+    let res = contract
+        .call(&worker, "update_block_header")
+        .args_json(json!(headers["187000"]))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "update_block_header 187000 failed");
+
+    let res = consumer
+        .call(&worker, contract.id(), "set_restricted")
+        .args_json(json!({"restricted": true}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+    assert!(res.is_err(), "set_restricted should fail for non-owner");
+
+    let res = contract
+        .call(&worker, "set_restricted")
+        .args_json(json!({"restricted": true}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "set_restricted failed");
+
+    let file = fs::File::open("./tests/data/proof.json").unwrap();
+    let proofs: serde_json::Value = serde_json::from_reader(file).unwrap();
+
+    let res = consumer
+        .call(&worker, contract.id(), "verify_proof_data")
+        .args_json(json!({"receipt_proof": proofs["187133"]}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await;
+    assert!(res.is_err(), "verify_proof_data should fail for unauthorized consumer");
+    assert!(
+        res.err()
+            .unwrap()
+            .to_string()
+            .contains("is not an authorized consumer"),
+        "should be unauthorized consumer error"
+    );
+
+    let res = contract
+        .call(&worker, "add_consumer")
+        .args_json(json!({"account_id": consumer.id()}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "add_consumer failed");
+
+    let consumers: Vec<String> = contract
+        .call(&worker, "get_consumers")
+        .args_json(json!({"from_index": 0, "limit": 10}))?
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(vec![consumer.id().to_string()], consumers);
+
+    let consumers: Vec<String> = contract
+        .call(&worker, "get_consumers")
+        .args_json(json!({"from_index": 1, "limit": 10}))?
+        .view()
+        .await?
+        .json()?;
+    assert!(consumers.is_empty(), "no consumer after the first one");
+
+    let res = consumer
+        .call(&worker, contract.id(), "verify_proof_data")
+        .args_json(json!({"receipt_proof": proofs["187133"]}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "verify_proof_data for consumer failed");
+
+    let res = contract
+        .call(&worker, "remove_consumer")
+        .args_json(json!({"account_id": consumer.id()}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "remove_consumer failed");
+
+    let res = contract
+        .call(&worker, "set_restricted")
+        .args_json(json!({"restricted": false}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "set_restricted failed");
+
+    let restricted: bool = contract
+        .call(&worker, "is_restricted")
+        .args_json(json!({}))?
+        .view()
+        .await?
+        .json()?;
+    assert!(!restricted, "client should be open");
+
+    let res = consumer
+        .call(&worker, contract.id(), "verify_proof_data")
+        .args_json(json!({"receipt_proof": proofs["187133"]}))?
+        .gas(300_000_000_000_000)
+        .transact()
+        .await?;
+    assert!(res.is_success(), "verify_proof_data should succeed in open mode");
+
+    Ok(())
 }
@@ -1,5 +1,6 @@
 use crate::prover::{Address, MapEvent, EthEventParams};
 use ethabi::ParamType;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::CryptoHash;
 use near_sdk::json_types::U128;
 use near_sdk::serde::{Serialize, Deserialize};
@@ -7,7 +8,7 @@ use map_light_client::proof::LogEntry;
 use rlp::{Encodable, RlpStream};
 
 /// Data that was emitted by the Ethereum Locked event.
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Eq, PartialEq)]
 #[serde(crate = "near_sdk::serde")]
 pub struct MapTransferOutEvent {
     #[serde(with = "crate::bytes::hexstring")]
@@ -21,9 +22,13 @@ pub struct MapTransferOutEvent {
     pub to: Vec<u8>,
     pub amount: U128,
     pub to_chain_token: Vec<u8>,
+    /// Message to call the receiver with, only emitted by mapTransferOutCall event.
+    #[serde(default)]
+    pub message: Vec<u8>,
 }
 /*
 event mapTransferOut(uint256 indexed fromChain, uint256 indexed toChain, bytes32 orderId, bytes token, bytes from, bytes to, uint256 amount, bytes toChainToken);
+event mapTransferOutCall(uint256 indexed fromChain, uint256 indexed toChain, bytes32 orderId, bytes token, bytes from, bytes to, uint256 amount, bytes toChainToken, bytes message);
  */
 impl MapTransferOutEvent {
     fn event_params() -> EthEventParams {
@@ -39,9 +44,22 @@ impl MapTransferOutEvent {
         ]
     }
 
-    /// Parse raw log entry data.
+    fn call_event_params() -> EthEventParams {
+        let mut params = MapTransferOutEvent::event_params();
+        params.push(("message".to_string(), ParamType::Bytes, false));
+        params
+    }
+
+    /// Parse raw log entry data of either mapTransferOut or mapTransferOutCall event.
     pub fn from_log_entry_data(data: &LogEntry) -> Option<Self> {
-        let event = MapEvent::from_log_entry_data("mapTransferOut", MapTransferOutEvent::event_params(), data)?;
+        let (event, message) = match MapEvent::from_log_entry_data("mapTransferOut", MapTransferOutEvent::event_params(), data) {
+            Some(event) => (event, vec![]),
+            None => {
+                let event = MapEvent::from_log_entry_data("mapTransferOutCall", MapTransferOutEvent::call_event_params(), data)?;
+                let message = event.log.params[8].value.clone().to_bytes()?;
+                (event, message)
+            }
+        };
         let from_chain = event.log.params[0].value.clone().to_uint()?.as_u128().into();
         let to_chain = event.log.params[1].value.clone().to_uint()?.as_u128().into();
         let order_id: CryptoHash = event.log.params[2].value.clone().to_fixed_bytes()?.try_into().ok()?;
@@ -60,6 +78,7 @@ impl MapTransferOutEvent {
             to,
             amount,
             to_chain_token,
+            message,
         })
     }
 }
@@ -70,6 +89,189 @@ impl std::fmt::Display for MapTransferOutEvent {
     }
 }
 
+/// Data that was emitted by the MAP mapTransferRelay event, once the transfer out order from NEAR is relayed on MAP.
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MapTransferRelayEvent {
+    #[serde(with = "crate::bytes::hexstring")]
+    pub map_bridge_address: Address,
+    pub from_chain: U128,
+    pub to_chain: U128,
+    #[serde(with = "crate::bytes::hexstring")]
+    pub order_id: CryptoHash,
+    #[serde(with = "crate::bytes::hexstring")]
+    pub token: Address,
+    pub from: Vec<u8>,
+    pub to: Vec<u8>,
+    pub amount: U128,
+}
+/*
+event mapTransferRelay(uint256 indexed fromChain, uint256 indexed toChain, bytes32 orderId, address token, bytes from, bytes to, uint256 amount);
+ */
+impl MapTransferRelayEvent {
+    fn event_params() -> EthEventParams {
+        vec![
+            ("fromChain".to_string(), ParamType::Uint(256), true),
+            ("toChain".to_string(), ParamType::Uint(256), true),
+            ("orderId".to_string(), ParamType::FixedBytes(32), false),
+            ("token".to_string(), ParamType::Address, false),
+            ("from".to_string(), ParamType::Bytes, false),
+            ("to".to_string(), ParamType::Bytes, false),
+            ("amount".to_string(), ParamType::Uint(256), false),
+        ]
+    }
+
+    /// Parse raw log entry data.
+    pub fn from_log_entry_data(data: &LogEntry) -> Option<Self> {
+        let event = MapEvent::from_log_entry_data("mapTransferRelay", MapTransferRelayEvent::event_params(), data)?;
+        let from_chain = event.log.params[0].value.clone().to_uint()?.as_u128().into();
+        let to_chain = event.log.params[1].value.clone().to_uint()?.as_u128().into();
+        let order_id: CryptoHash = event.log.params[2].value.clone().to_fixed_bytes()?.try_into().ok()?;
+        let token = event.log.params[3].value.clone().to_address()?.0;
+        let from = event.log.params[4].value.clone().to_bytes()?;
+        let to = event.log.params[5].value.clone().to_bytes()?;
+        let amount = event.log.params[6].value.clone().to_uint()?.as_u128().into();
+        Some(Self {
+            map_bridge_address: event.mcs_address,
+            from_chain,
+            to_chain,
+            order_id,
+            token,
+            from,
+            to,
+            amount,
+        })
+    }
+}
+
+impl std::fmt::Display for MapTransferRelayEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).unwrap())
+    }
+}
+
+/// Data that was emitted by the MAP mapDepositIn event, once the deposit out order from NEAR is deposited on MAP.
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MapDepositInEvent {
+    #[serde(with = "crate::bytes::hexstring")]
+    pub map_bridge_address: Address,
+    pub from_chain: U128,
+    pub to_chain: U128,
+    #[serde(with = "crate::bytes::hexstring")]
+    pub token: Address,
+    #[serde(with = "crate::bytes::hexstring")]
+    pub order_id: CryptoHash,
+    pub from: Vec<u8>,
+    #[serde(with = "crate::bytes::hexstring")]
+    pub to: Address,
+    pub amount: U128,
+}
+/*
+event mapDepositIn(uint256 indexed fromChain, uint256 indexed toChain, address indexed token, bytes32 orderId, bytes from, address to, uint256 amount);
+ */
+impl MapDepositInEvent {
+    fn event_params() -> EthEventParams {
+        vec![
+            ("fromChain".to_string(), ParamType::Uint(256), true),
+            ("toChain".to_string(), ParamType::Uint(256), true),
+            ("token".to_string(), ParamType::Address, true),
+            ("orderId".to_string(), ParamType::FixedBytes(32), false),
+            ("from".to_string(), ParamType::Bytes, false),
+            ("to".to_string(), ParamType::Address, false),
+            ("amount".to_string(), ParamType::Uint(256), false),
+        ]
+    }
+
+    /// Parse raw log entry data.
+    pub fn from_log_entry_data(data: &LogEntry) -> Option<Self> {
+        let event = MapEvent::from_log_entry_data("mapDepositIn", MapDepositInEvent::event_params(), data)?;
+        let from_chain = event.log.params[0].value.clone().to_uint()?.as_u128().into();
+        let to_chain = event.log.params[1].value.clone().to_uint()?.as_u128().into();
+        let token = event.log.params[2].value.clone().to_address()?.0;
+        let order_id: CryptoHash = event.log.params[3].value.clone().to_fixed_bytes()?.try_into().ok()?;
+        let from = event.log.params[4].value.clone().to_bytes()?;
+        let to = event.log.params[5].value.clone().to_address()?.0;
+        let amount = event.log.params[6].value.clone().to_uint()?.as_u128().into();
+        Some(Self {
+            map_bridge_address: event.mcs_address,
+            from_chain,
+            to_chain,
+            token,
+            order_id,
+            from,
+            to,
+            amount,
+        })
+    }
+}
+
+impl std::fmt::Display for MapDepositInEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).unwrap())
+    }
+}
+
+/// Data that was emitted by the MAP mapMessageOut event.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MapMessageOutEvent {
+    #[serde(with = "crate::bytes::hexstring")]
+    pub map_bridge_address: Address,
+    pub from_chain: U128,
+    pub to_chain: U128,
+    #[serde(with = "crate::bytes::hexstring")]
+    pub order_id: CryptoHash,
+    pub from: Vec<u8>,
+    pub to: Vec<u8>,
+    pub payload: Vec<u8>,
+    pub gas_limit: U128,
+}
+/*
+event mapMessageOut(uint256 indexed fromChain, uint256 indexed toChain, bytes32 orderId, bytes from, bytes to, bytes payload, uint256 gasLimit);
+ */
+impl MapMessageOutEvent {
+    fn event_params() -> EthEventParams {
+        vec![
+            ("fromChain".to_string(), ParamType::Uint(256), true),
+            ("toChain".to_string(), ParamType::Uint(256), true),
+            ("orderId".to_string(), ParamType::FixedBytes(32), false),
+            ("from".to_string(), ParamType::Bytes, false),
+            ("to".to_string(), ParamType::Bytes, false),
+            ("payload".to_string(), ParamType::Bytes, false),
+            ("gasLimit".to_string(), ParamType::Uint(256), false),
+        ]
+    }
+
+    /// Parse raw log entry data.
+    pub fn from_log_entry_data(data: &LogEntry) -> Option<Self> {
+        let event = MapEvent::from_log_entry_data("mapMessageOut", MapMessageOutEvent::event_params(), data)?;
+        let from_chain = event.log.params[0].value.clone().to_uint()?.as_u128().into();
+        let to_chain = event.log.params[1].value.clone().to_uint()?.as_u128().into();
+        let order_id: CryptoHash = event.log.params[2].value.clone().to_fixed_bytes()?.try_into().ok()?;
+        let from = event.log.params[3].value.clone().to_bytes()?;
+        let to = event.log.params[4].value.clone().to_bytes()?;
+        let payload = event.log.params[5].value.clone().to_bytes()?;
+        let gas_limit = event.log.params[6].value.clone().to_uint()?.as_u128().into();
+        Some(Self {
+            map_bridge_address: event.mcs_address,
+            from_chain,
+            to_chain,
+            order_id,
+            from,
+            to,
+            payload,
+            gas_limit,
+        })
+    }
+}
+
+impl std::fmt::Display for MapMessageOutEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).unwrap())
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub struct TransferOutEvent {
@@ -151,21 +353,70 @@ mod tests {
 
     impl MapTransferOutEvent {
         pub fn to_log_entry_data(&self) -> LogEntry {
+            let mut values = vec![
+                Token::FixedBytes(self.order_id.to_vec()),
+                Token::Bytes(self.token.clone()),
+                Token::Bytes(self.from.clone()),
+                Token::Bytes(self.to.clone()),
+                Token::Uint(self.amount.0.into()),
+                Token::Bytes(self.to_chain_token.clone()),
+            ];
+            let (name, params) = if self.message.is_empty() {
+                ("mapTransferOut", MapTransferOutEvent::event_params())
+            } else {
+                values.push(Token::Bytes(self.message.clone()));
+                ("mapTransferOutCall", MapTransferOutEvent::call_event_params())
+            };
             MapEvent::to_log_entry_data(
-                "mapTransferOut",
-                MapTransferOutEvent::event_params(),
+                name,
+                params,
                 self.map_bridge_address,
                 vec![
-                    self.from_chain.0.clone().to_be_bytes().to_vec(),
-                    self.to_chain.0.clone().to_be_bytes().to_vec(),
+                    self.from_chain.0.to_be_bytes().to_vec(),
+                    self.to_chain.0.to_be_bytes().to_vec(),
+                ],
+                values,
+            )
+        }
+    }
+
+    impl MapTransferRelayEvent {
+        pub fn to_log_entry_data(&self) -> LogEntry {
+            MapEvent::to_log_entry_data(
+                "mapTransferRelay",
+                MapTransferRelayEvent::event_params(),
+                self.map_bridge_address,
+                vec![
+                    self.from_chain.0.to_be_bytes().to_vec(),
+                    self.to_chain.0.to_be_bytes().to_vec(),
                 ],
                 vec![
-                    Token::FixedBytes(self.order_id.clone().to_vec()),
-                    Token::Bytes(self.token.clone()),
+                    Token::FixedBytes(self.order_id.to_vec()),
+                    Token::Address(self.token.into()),
                     Token::Bytes(self.from.clone()),
                     Token::Bytes(self.to.clone()),
                     Token::Uint(self.amount.0.into()),
-                    Token::Bytes(self.to_chain_token.clone()),
+                ],
+            )
+        }
+    }
+
+    impl MapMessageOutEvent {
+        pub fn to_log_entry_data(&self) -> LogEntry {
+            MapEvent::to_log_entry_data(
+                "mapMessageOut",
+                MapMessageOutEvent::event_params(),
+                self.map_bridge_address,
+                vec![
+                    self.from_chain.0.to_be_bytes().to_vec(),
+                    self.to_chain.0.to_be_bytes().to_vec(),
+                ],
+                vec![
+                    Token::FixedBytes(self.order_id.to_vec()),
+                    Token::Bytes(self.from.clone()),
+                    Token::Bytes(self.to.clone()),
+                    Token::Bytes(self.payload.clone()),
+                    Token::Uint(self.gas_limit.0.into()),
                 ],
             )
         }
@@ -230,7 +481,8 @@ mod tests {
             from: hex::decode("223e016916ba9f10762e33e03e8556409d096f22").unwrap(),
             to: "pandarr.test.near".as_bytes().to_vec(),
             amount: U128(100),
-            to_chain_token: "wrap.test.near".as_bytes().to_vec()
+            to_chain_token: "wrap.test.near".as_bytes().to_vec(),
+            message: vec![],
         };
 
         let data = event.to_log_entry_data();
@@ -239,4 +491,123 @@ mod tests {
 
         println!("{:?}", serde_json::to_string(&data).unwrap());
     }
+
+    #[test]
+    fn test_gen_call_event() {
+        let mut event = MapTransferOutEvent{
+            map_bridge_address: Address::try_from(hex::decode("765a5a86411ab8627516cbb77d5db00b74fe610d").unwrap()).unwrap(),
+            from_chain: U128(212),
+            to_chain: U128(1313161555),
+            order_id: keccak256("123".as_bytes()),
+            token: hex::decode("ec3e016916ba9f10762e33e03e8556409d096fb4").unwrap(),
+            from: hex::decode("223e016916ba9f10762e33e03e8556409d096f22").unwrap(),
+            to: "pool.test.near".as_bytes().to_vec(),
+            amount: U128(100),
+            to_chain_token: "wrap.test.near".as_bytes().to_vec(),
+            message: r#"{"action":"swap"}"#.as_bytes().to_vec(),
+        };
+
+        let data = event.to_log_entry_data();
+        let result = MapTransferOutEvent::from_log_entry_data(&data).unwrap();
+        assert_eq!(result, event);
+
+        event.message = vec![];
+        assert_ne!(data.topics[0], event.to_log_entry_data().topics[0], "call event should have different signature");
+    }
+
+    #[test]
+    fn test_gen_message_event() {
+        let event = MapMessageOutEvent{
+            map_bridge_address: Address::try_from(hex::decode("765a5a86411ab8627516cbb77d5db00b74fe610d").unwrap()).unwrap(),
+            from_chain: U128(212),
+            to_chain: U128(1313161555),
+            order_id: keccak256("123".as_bytes()),
+            from: hex::decode("223e016916ba9f10762e33e03e8556409d096f22").unwrap(),
+            to: "app.test.near".as_bytes().to_vec(),
+            payload: "hello".as_bytes().to_vec(),
+            gas_limit: U128(20_000_000_000_000),
+        };
+
+        let data = event.to_log_entry_data();
+        let result = MapMessageOutEvent::from_log_entry_data(&data).unwrap();
+        assert_eq!(result, event);
+        assert!(MapTransferOutEvent::from_log_entry_data(&data).is_none());
+    }
+
+    fn relay_log_entry(topics: Vec<&str>, data: &str) -> LogEntry {
+        LogEntry {
+            address: Address::try_from(hex::decode("765a5a86411ab8627516cbb77d5db00b74fe610d").unwrap()).unwrap(),
+            topics: topics.into_iter().map(|t| hex::decode(t).unwrap().try_into().unwrap()).collect(),
+            data: hex::decode(data).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_parse_transfer_relay_event() {
+        // emitted by MAPOmnichainServiceRelayV2 when relaying an order from NEAR to ethereum, abi encoded
+        let log = relay_log_entry(
+            vec![
+                "e23247b10e5cf2e2a6a13352598fae25c889731f3b5eff5fb52184e121b41d54",
+                "000000000000000000000000000000000000000000000000000000004e454153",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+            ],
+            "f337aec2053f6b36bc4917013ec3b4f32daf92ff945677d0cb35aa0d8b939773\
+             0000000000000000000000009f722b2cb30093f766221fd0d37964949ed66918\
+             00000000000000000000000000000000000000000000000000000000000000a0\
+             00000000000000000000000000000000000000000000000000000000000000e0\
+             0000000000000000000000000000000000000000000000000000000000000064\
+             000000000000000000000000000000000000000000000000000000000000000a\
+             616c6963652e6e65617200000000000000000000000000000000000000000000\
+             0000000000000000000000000000000000000000000000000000000000000014\
+             223e016916ba9f10762e33e03e8556409d096f22000000000000000000000000",
+        );
+
+        let event = MapTransferRelayEvent::from_log_entry_data(&log).unwrap();
+        assert_eq!(MapTransferRelayEvent {
+            map_bridge_address: log.address,
+            from_chain: U128(1313161555),
+            to_chain: U128(1),
+            order_id: keccak256("near order".as_bytes()),
+            token: Address::try_from(hex::decode("9f722b2cb30093f766221fd0d37964949ed66918").unwrap()).unwrap(),
+            from: "alice.near".as_bytes().to_vec(),
+            to: hex::decode("223e016916ba9f10762e33e03e8556409d096f22").unwrap(),
+            amount: U128(100),
+        }, event);
+        let data = event.to_log_entry_data();
+        assert_eq!((log.topics.clone(), log.data.clone()), (data.topics, data.data));
+        assert!(MapDepositInEvent::from_log_entry_data(&log).is_none());
+        assert!(MapTransferOutEvent::from_log_entry_data(&log).is_none());
+    }
+
+    #[test]
+    fn test_parse_deposit_in_event() {
+        // emitted by MAPOmnichainServiceRelayV2 when depositing an order from NEAR on MAP, abi encoded
+        let log = relay_log_entry(
+            vec![
+                "5bf7c8229e485fc1aa764dc0e95bb88be5d3589f08d0fb5fca73bd195aecde0e",
+                "000000000000000000000000000000000000000000000000000000004e454153",
+                "00000000000000000000000000000000000000000000000000000000000058f8",
+                "0000000000000000000000009f722b2cb30093f766221fd0d37964949ed66918",
+            ],
+            "f337aec2053f6b36bc4917013ec3b4f32daf92ff945677d0cb35aa0d8b939773\
+             0000000000000000000000000000000000000000000000000000000000000080\
+             000000000000000000000000223e016916ba9f10762e33e03e8556409d096f22\
+             0000000000000000000000000000000000000000000000000000000000000064\
+             000000000000000000000000000000000000000000000000000000000000000a\
+             616c6963652e6e65617200000000000000000000000000000000000000000000",
+        );
+
+        let event = MapDepositInEvent::from_log_entry_data(&log).unwrap();
+        assert_eq!(MapDepositInEvent {
+            map_bridge_address: log.address,
+            from_chain: U128(1313161555),
+            to_chain: U128(22776),
+            token: Address::try_from(hex::decode("9f722b2cb30093f766221fd0d37964949ed66918").unwrap()).unwrap(),
+            order_id: keccak256("near order".as_bytes()),
+            from: "alice.near".as_bytes().to_vec(),
+            to: Address::try_from(hex::decode("223e016916ba9f10762e33e03e8556409d096f22").unwrap()).unwrap(),
+            amount: U128(100),
+        }, event);
+        assert!(MapTransferRelayEvent::from_log_entry_data(&log).is_none());
+    }
 }
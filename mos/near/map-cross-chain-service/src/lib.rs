@@ -2,7 +2,7 @@ use std::collections::HashSet;
 use admin_controlled::{AdminControlled, Mask};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::serde::{Serialize, Deserialize};
-use near_sdk::collections::{UnorderedMap, UnorderedSet};
+use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet, Vector};
 use near_sdk::json_types::{Base64VecU8, U128};
 use near_sdk::{env, ext_contract, near_bindgen, AccountId, Balance, Gas, PanicOnDefault, Promise, PromiseOrValue, PromiseResult, CryptoHash, log, assert_one_yocto};
 use event::*;
@@ -13,7 +13,8 @@ use near_contract_standards::storage_management::{StorageBalance, StorageBalance
 use near_sdk::env::panic_str;
 use near_sdk::serde_json::json;
 use map_light_client::proof::ReceiptProof;
-use crate::ChainType::{EvmChain, Unknown};
+use bech32::{FromBase32, Variant};
+use crate::ChainType::{AptosChain, BitcoinChain, EvmChain, NearChain, SolanaChain, Unknown};
 
 mod event;
 pub mod prover;
@@ -60,8 +61,11 @@ const NEAR_DEPOSIT_GAS: Gas = Gas(20_000_000_000_000);
 /// Gas to call storage_deposit_for_mcs on mcs contract
 const STORAGE_DEPOSIT_FOR_MCS_GAS: Gas = Gas(20_000_000_000_000);
 
-/// Gas to call finish_verify_proof method.
-const TRANSFER_IN_SINGLE_EVENT_GAS: Gas = Gas(60_000_000_000_000);
+/// Gas to call finish_verify_proof or finish_verify_proof_batch method, without taking into account the gas of the events.
+const TRANSFER_IN_BATCH_GAS: Gas = Gas(10_000_000_000_000);
+
+/// Gas to process a transfer in event, without taking into account the gas of the calls it makes.
+const PROCESS_TRANSFER_IN_EVENT_GAS: Gas = Gas(5_000_000_000_000);
 
 /// Gas to call transfer_in_native_token method.
 const TRANSFER_IN_NATIVE_TOKEN_GAS: Gas = Gas(30_000_000_000_000);
@@ -69,15 +73,45 @@ const TRANSFER_IN_NATIVE_TOKEN_GAS: Gas = Gas(30_000_000_000_000);
 /// Gas to call finish_transfer_in method.
 const FINISH_TRANSFER_IN_GAS: Gas = Gas(30_000_000_000_000);
 
+/// Gas to call ft_transfer_call on ext fungible contract or on_mcs_native_received on the receiver.
+const TRANSFER_CALL_GAS: Gas = Gas(60_000_000_000_000);
+
+/// Gas to call finish_mint_transfer_in_call method, without taking into account the gas of the calls it makes.
+const FINISH_MINT_TRANSFER_IN_CALL_GAS: Gas = Gas(10_000_000_000_000);
+
+/// Gas to call finish_transfer_in_call method.
+const FINISH_TRANSFER_IN_CALL_GAS: Gas = Gas(40_000_000_000_000);
+
 /// Gas to call finish_transfer_out method.
 const FINISH_TRANSFER_OUT_GAS: Gas = Gas(30_000_000_000_000);
 
 /// Gas to call finish_deposit_out method.
 const FINISH_DEPOSIT_OUT_GAS: Gas = Gas(30_000_000_000_000);
 
+/// Gas to call finish_mark_delivered method.
+const FINISH_MARK_DELIVERED_GAS: Gas = Gas(10_000_000_000_000);
+
+/// Gas to call finish_withdraw_fee method.
+const FINISH_WITHDRAW_FEE_GAS: Gas = Gas(10_000_000_000_000);
+
+/// Gas to call finish_claim method.
+const FINISH_CLAIM_GAS: Gas = Gas(10_000_000_000_000);
+
 /// Gas to call report_fail method.
 const REPORT_FAIL_GAS: Gas = Gas(10_000_000_000_000);
 
+/// Gas to call finish_verify_message method, without taking into account the gas of the target contract.
+const FINISH_VERIFY_MESSAGE_GAS: Gas = Gas(20_000_000_000_000);
+
+/// Gas to call finish_message_in method.
+const FINISH_MESSAGE_IN_GAS: Gas = Gas(20_000_000_000_000);
+
+/// Gas to call finish_retry_message method.
+const FINISH_RETRY_MESSAGE_GAS: Gas = Gas(10_000_000_000_000);
+
+/// Max gas the source chain can request for calling the target contract of a message.
+const MAX_MESSAGE_IN_GAS: Gas = Gas(150_000_000_000_000);
+
 /// Gas to call verify_log_entry on prover.
 const VERIFY_LOG_ENTRY_GAS: Gas = Gas(40_000_000_000_000);
 
@@ -91,6 +125,9 @@ const TRANSFER_OUT_TYPE: &str = "2ef1cdf83614a69568ed2c96a275dd7fb2e63a464aa3a0f
 const DEPOSIT_OUT_TYPE: &str = "150bd848adaf4e3e699dcac82d75f111c078ce893375373593cc1b9208998377";
 
 const MIN_TRANSFER_OUT_AMOUNT: f64 = 0.001;
+const FEE_RATE_DENOMINATOR: u128 = 10000;
+/// Number of buckets the rate limit window is divided into.
+const RATE_LIMIT_BUCKETS: u64 = 12;
 const NEAR_DECIMAL: u8 = 24;
 
 const PAUSE_DEPLOY_TOKEN: Mask = 1 << 0;
@@ -99,14 +136,169 @@ const PAUSE_TRANSFER_OUT_TOKEN: Mask = 1 << 2;
 const PAUSE_TRANSFER_OUT_NATIVE: Mask = 1 << 3;
 const PAUSE_DEPOSIT_OUT_TOKEN: Mask = 1 << 4;
 const PAUSE_DEPOSIT_OUT_NATIVE: Mask = 1 << 5;
+const PAUSE_MESSAGE_IN: Mask = 1 << 6;
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, PartialEq)]
 #[serde(crate = "near_sdk::serde")]
 pub enum ChainType {
     EvmChain,
     Unknown,
+    /// Address is a NEAR account id
+    NearChain,
+    /// Address is a 32-byte public key
+    SolanaChain,
+    /// Address is a 32-byte public key
+    AptosChain,
+    /// Address is a segwit address or a legacy P2PKH/P2SH address of the bitcoin network set in mcs contract
+    BitcoinChain,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum BitcoinNetwork {
+    /// Segwit address starts with "bc", and legacy address has version 0x00 (P2PKH) or 0x05 (P2SH)
+    Mainnet,
+    /// Segwit address starts with "tb", and legacy address has version 0x6f (P2PKH) or 0xc4 (P2SH)
+    Testnet,
+}
+
+impl BitcoinNetwork {
+    fn hrp(&self) -> &'static str {
+        match self {
+            BitcoinNetwork::Mainnet => "bc",
+            BitcoinNetwork::Testnet => "tb",
+        }
+    }
+
+    fn legacy_versions(&self) -> [u8; 2] {
+        match self {
+            BitcoinNetwork::Mainnet => [0x00, 0x05],
+            BitcoinNetwork::Testnet => [0x6f, 0xc4],
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeConfig {
+    /// Fee rate in basis points of the transfer amount
+    pub rate: u16,
+    /// Min fee of a transfer
+    pub min: U128,
+    /// Max fee of a transfer
+    pub max: U128,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Direction {
+    In,
+    Out,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RateLimit {
+    /// Max amount of token transferred in the window
+    pub limit: U128,
+    /// Length of the sliding window in seconds, the window slides by 1/RATE_LIMIT_BUCKETS of it
+    pub window: u64,
+}
+
+/// Amount of token transferred in the buckets of the rate limit window.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq)]
+pub struct Volume {
+    /// Amount of each bucket, indexed by the bucket number modulo RATE_LIMIT_BUCKETS
+    pub amounts: Vec<u128>,
+    /// Number of the latest bucket with transferred amount, the time divided by the bucket length
+    pub last_bucket: u64,
 }
 
+impl Volume {
+    fn new() -> Self {
+        Self {
+            amounts: vec![0; RATE_LIMIT_BUCKETS as usize],
+            last_bucket: 0,
+        }
+    }
+
+    /// Return the amount of the window ending with the bucket.
+    fn total(&self, bucket: u64) -> u128 {
+        let first = (bucket + 1).saturating_sub(RATE_LIMIT_BUCKETS);
+        (first..=self.last_bucket).map(|b| self.amounts[(b % RATE_LIMIT_BUCKETS) as usize]).sum()
+    }
+
+    /// Move the window to end with the bucket, the buckets out of the window are cleared.
+    fn advance(&mut self, bucket: u64) {
+        if bucket <= self.last_bucket {
+            return;
+        }
+        let first = (self.last_bucket + 1).max((bucket + 1).saturating_sub(RATE_LIMIT_BUCKETS));
+        for b in first..=bucket {
+            self.amounts[(b % RATE_LIMIT_BUCKETS) as usize] = 0;
+        }
+        self.last_bucket = bucket;
+    }
+
+    /// Subtract the amount from the latest buckets of the window ending with the bucket.
+    fn release(&mut self, bucket: u64, mut amount: u128) {
+        let first = (bucket + 1).saturating_sub(RATE_LIMIT_BUCKETS);
+        for b in (first..=self.last_bucket).rev() {
+            let index = (b % RATE_LIMIT_BUCKETS) as usize;
+            let released = amount.min(self.amounts[index]);
+            self.amounts[index] -= released;
+            amount -= released;
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum OrderStatus {
+    /// The token is burned or locked, and the event is emitted for MAP
+    Sent,
+    /// The order is transferred in on MAP
+    Delivered,
+    /// The token is being burned or locked
+    Pending,
+    /// Burning or locking the token failed, and no event is emitted
+    Failed,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OutOrder {
+    pub token: String,
+    pub from: String,
+    pub to: Vec<u8>,
+    pub to_chain: U128,
+    pub amount: U128,
+    pub status: OrderStatus,
+    /// Block timestamp in seconds when the status is updated
+    pub timestamp: u64,
+}
+
+impl OutOrder {
+    pub fn new(token: &str, from: &str, to: &[u8], to_chain: U128, amount: U128, status: OrderStatus) -> Self {
+        Self {
+            token: token.to_string(),
+            from: from.to_string(),
+            to: to.to_vec(),
+            to_chain,
+            amount,
+            status,
+            timestamp: env::block_timestamp() / 1_000_000_000,
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Claimable {
+    /// The mcs or fungible token to claim, native token is claimed as wrapped token
+    pub token: String,
+    pub amount: U128,
+}
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
@@ -143,16 +335,80 @@ pub struct MapCrossChainService {
     pub nonce: u128,
     /// Mask determining all paused functions
     pub paused: Mask,
+    /// Message events whose target contract call failed, by order id.
+    pub failed_messages: UnorderedMap<CryptoHash, MapMessageOutEvent>,
+    /// Map of (token, to chain) and its fee config
+    pub fees: UnorderedMap<(String, u128), FeeConfig>,
+    /// Account receiving the withdrawn fees
+    pub fee_receiver: AccountId,
+    /// Map of token and its fees which are not withdrawn
+    pub accrued_fees: UnorderedMap<String, u128>,
+    /// Map of (token, direction) and its rate limit
+    pub rate_limits: UnorderedMap<(String, Direction), RateLimit>,
+    /// Map of (token, direction) and the amount of token transferred in the rate limit window
+    pub volumes: UnorderedMap<(String, Direction), Volume>,
+    /// Transfer in events which failed or exceeded the rate limit, by order id.
+    pub pending_transfers_in: UnorderedMap<CryptoHash, MapTransferOutEvent>,
+    /// Transfer out and deposit out orders, by order id.
+    pub out_orders: UnorderedMap<CryptoHash, OutOrder>,
+    /// Map of sender and its out order ids
+    pub sender_out_orders: LookupMap<String, Vector<CryptoHash>>,
+    /// Balance funded for the storage of the out orders
+    pub order_storage_balance: Balance,
+    /// Token of the failed transfer in, by order id.
+    pub claimables: LookupMap<CryptoHash, Claimable>,
+    /// Map of receiver and the order ids of its claimable tokens
+    pub recipient_claimables: LookupMap<String, UnorderedSet<CryptoHash>>,
+    /// Account which can claim the token of the failed transfer in on behalf of the receiver
+    pub recovery_account: Option<AccountId>,
+    /// Verification fee of the map light client, paid from the deposit attached to each proof
+    pub map_client_verification_fee: Balance,
+    /// Bitcoin network whose addresses are accepted for the bitcoin chain type
+    pub bitcoin_network: BitcoinNetwork,
+}
+
+/// State of the mcs contract before the message in support is added.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct MapCrossChainServiceV0 {
+    pub map_client_account: AccountId,
+    pub map_bridge_address: Address,
+    pub mcs_tokens: UnorderedMap<String, HashSet<u128>>,
+    pub fungible_tokens: UnorderedMap<String, HashSet<u128>>,
+    pub fungible_tokens_storage_balance: UnorderedMap<String, u128>,
+    pub token_decimals: UnorderedMap<String, u8>,
+    pub native_to_chains: HashSet<u128>,
+    pub chain_id_type_map: UnorderedMap<u128, ChainType>,
+    pub used_events: UnorderedSet<CryptoHash>,
+    pub owner: AccountId,
+    pub mcs_storage_transfer_in_required: Balance,
+    pub wrapped_token: String,
+    pub near_chain_id: u128,
+    pub map_chain_id: u128,
+    pub nonce: u128,
+    pub paused: Mask,
 }
 
 #[ext_contract(ext_fungible_token)]
 pub trait FungibleToken {
     fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+    fn ft_transfer_call(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>, msg: String) -> PromiseOrValue<U128>;
     fn storage_deposit(&mut self, account_id: Option<AccountId>, registration_only: Option<bool>) -> StorageBalance;
     fn storage_balance_bounds(&self) -> StorageBalanceBounds;
     fn ft_metadata(&self) -> FungibleTokenMetadata;
 }
 
+/// Interface of the NEAR contracts receiving native token with a message from other chains.
+#[ext_contract(ext_native_receiver)]
+pub trait NativeReceiver {
+    fn on_mcs_native_received(&mut self, from_chain: U128, from: Vec<u8>, amount: U128, msg: String);
+}
+
+/// Interface of the NEAR contracts receiving messages from other chains.
+#[ext_contract(ext_message_receiver)]
+pub trait MessageReceiver {
+    fn on_map_message(&mut self, from_chain: U128, from: Vec<u8>, order_id: String, payload: Vec<u8>);
+}
+
 #[ext_contract(ext_wnear_token)]
 pub trait ExtWNearToken {
     fn near_deposit(&mut self);
@@ -179,6 +435,52 @@ pub fn assert_self() {
     assert_eq!(env::predecessor_account_id(), env::current_account_id(), "caller is not self");
 }
 
+fn parse_order_id(order_id: &str) -> CryptoHash {
+    hex::decode(order_id).ok().and_then(|id| id.try_into().ok())
+        .unwrap_or_else(|| panic_str(&format!("invalid order id {}", order_id)))
+}
+
+/// Check the bitcoin address is a segwit address or a legacy P2PKH/P2SH address of the network.
+fn is_valid_bitcoin_address(address: &[u8], network: BitcoinNetwork) -> bool {
+    is_valid_segwit_address(address, network.hrp()) || is_valid_legacy_address(address, network.legacy_versions())
+}
+
+/// Check the segwit address according to BIP-173 and BIP-350, witness version 0 is encoded by bech32 and others by
+/// bech32m.
+fn is_valid_segwit_address(address: &[u8], expected_hrp: &str) -> bool {
+    let (hrp, data, variant) = match std::str::from_utf8(address).ok().and_then(|address| bech32::decode(address).ok()) {
+        Some(decoded) => decoded,
+        None => return false,
+    };
+    if hrp != expected_hrp {
+        return false;
+    }
+    let (version, program) = match data.split_first() {
+        Some((version, program)) => (version.to_u8(), program),
+        None => return false,
+    };
+    let program = match Vec::<u8>::from_base32(program) {
+        Ok(program) => program,
+        Err(_) => return false,
+    };
+    match version {
+        0 => variant == Variant::Bech32 && (program.len() == 20 || program.len() == 32),
+        1..=16 => variant == Variant::Bech32m && (2..=40).contains(&program.len()),
+        _ => false,
+    }
+}
+
+/// Check the base58check encoded legacy address, which is the version byte, the 20-byte hash and the first 4 bytes
+/// of the double sha256 of them.
+fn is_valid_legacy_address(address: &[u8], versions: [u8; 2]) -> bool {
+    let data = match bs58::decode(address).into_vec() {
+        Ok(data) if data.len() == 25 => data,
+        _ => return false,
+    };
+    let checksum = env::sha256(&env::sha256(&data[..21]));
+    versions.contains(&data[0]) && checksum[..4] == data[21..]
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub struct FungibleTokenMsg {
@@ -228,25 +530,72 @@ impl MapCrossChainService {
             native_to_chains: Default::default(),
             chain_id_type_map: UnorderedMap::new(b"c".to_vec()),
             used_events: UnorderedSet::new(b"u".to_vec()),
-            owner,
+            owner: owner.clone(),
             mcs_storage_transfer_in_required: storage_balance.into(),
             wrapped_token,
             near_chain_id: near_chain_id.into(),  // 1313161555 for testnet
             map_chain_id: map_chain_id.into(),
             nonce: 0,
             paused: Mask::default(),
+            failed_messages: UnorderedMap::new(b"m".to_vec()),
+            fees: UnorderedMap::new(b"e".to_vec()),
+            fee_receiver: owner,
+            accrued_fees: UnorderedMap::new(b"a".to_vec()),
+            rate_limits: UnorderedMap::new(b"l".to_vec()),
+            volumes: UnorderedMap::new(b"v".to_vec()),
+            pending_transfers_in: UnorderedMap::new(b"p".to_vec()),
+            out_orders: UnorderedMap::new(b"o".to_vec()),
+            sender_out_orders: LookupMap::new(b"n".to_vec()),
+            order_storage_balance: 0,
+            claimables: LookupMap::new(b"k".to_vec()),
+            recipient_claimables: LookupMap::new(b"r".to_vec()),
+            recovery_account: None,
+            map_client_verification_fee: 0,
+            bitcoin_network: BitcoinNetwork::Mainnet,
         }
     }
 
     #[private]
     #[init(ignore_state)]
     pub fn migrate() -> Self {
-        let mcs: MapCrossChainService = env::state_read().expect("ERR_CONTRACT_IS_NOT_INITIALIZED");
-        mcs
+        let mcs: MapCrossChainServiceV0 = env::state_read().expect("ERR_CONTRACT_IS_NOT_INITIALIZED");
+        Self {
+            map_client_account: mcs.map_client_account,
+            map_bridge_address: mcs.map_bridge_address,
+            mcs_tokens: mcs.mcs_tokens,
+            fungible_tokens: mcs.fungible_tokens,
+            fungible_tokens_storage_balance: mcs.fungible_tokens_storage_balance,
+            token_decimals: mcs.token_decimals,
+            native_to_chains: mcs.native_to_chains,
+            chain_id_type_map: mcs.chain_id_type_map,
+            used_events: mcs.used_events,
+            owner: mcs.owner.clone(),
+            mcs_storage_transfer_in_required: mcs.mcs_storage_transfer_in_required,
+            wrapped_token: mcs.wrapped_token,
+            near_chain_id: mcs.near_chain_id,
+            map_chain_id: mcs.map_chain_id,
+            nonce: mcs.nonce,
+            paused: mcs.paused,
+            failed_messages: UnorderedMap::new(b"m".to_vec()),
+            fees: UnorderedMap::new(b"e".to_vec()),
+            fee_receiver: mcs.owner,
+            accrued_fees: UnorderedMap::new(b"a".to_vec()),
+            rate_limits: UnorderedMap::new(b"l".to_vec()),
+            volumes: UnorderedMap::new(b"v".to_vec()),
+            pending_transfers_in: UnorderedMap::new(b"p".to_vec()),
+            out_orders: UnorderedMap::new(b"o".to_vec()),
+            sender_out_orders: LookupMap::new(b"n".to_vec()),
+            order_storage_balance: 0,
+            claimables: LookupMap::new(b"k".to_vec()),
+            recipient_claimables: LookupMap::new(b"r".to_vec()),
+            recovery_account: None,
+            map_client_verification_fee: 0,
+            bitcoin_network: BitcoinNetwork::Mainnet,
+        }
     }
 
     pub fn version() -> &'static str {
-        "0.1.1"
+        "0.1.2"
     }
 
     /// Transfer from Map to NEAR based on the proof of the locked tokens or messages.
@@ -255,11 +604,95 @@ impl MapCrossChainService {
     pub fn transfer_in(&mut self, receipt_proof: ReceiptProof, index: usize) -> Promise {
         self.check_not_paused(PAUSE_TRANSFER_IN);
 
+        let event = self.check_transfer_in_event(&receipt_proof, index);
+        let (verify_promise, deposit) = self.verify_proof_data(receipt_proof);
+        verify_promise
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(TRANSFER_IN_BATCH_GAS + self.transfer_in_event_gas(&event))
+                    .with_attached_deposit(deposit)
+                    .finish_verify_proof(&event)
+            )
+    }
+
+    /// Transfer from Map to NEAR based on the proof of several events in one receipt, the proof is verified once.
+    /// The attached deposit is assigned to the events in order by the deposit each of them requires, and the events
+    /// are processed independently.
+    #[payable]
+    pub fn transfer_in_batch(&mut self, receipt_proof: ReceiptProof, indexes: Vec<usize>) -> Promise {
+        self.check_not_paused(PAUSE_TRANSFER_IN);
+        assert!(!indexes.is_empty(), "indexes should not be empty");
+
+        let mut events: Vec<MapTransferOutEvent> = Vec::new();
+        let mut gas = Gas(0);
+        for index in indexes {
+            let event = self.check_transfer_in_event(&receipt_proof, index);
+            assert!(events.iter().all(|e| e.order_id != event.order_id),
+                    "the event with order id {} is duplicated", hex::encode(event.order_id));
+            gas += self.transfer_in_event_gas(&event);
+            events.push(event);
+        }
+        assert!(env::prepaid_gas() >= VERIFY_LOG_ENTRY_GAS + gas + TRANSFER_IN_BATCH_GAS,
+                "not enough gas for {} events, exp: {:?}, cur: {:?}", events.len(), VERIFY_LOG_ENTRY_GAS + gas + TRANSFER_IN_BATCH_GAS, env::prepaid_gas());
+
+        let (verify_promise, deposit) = self.verify_proof_data(receipt_proof);
+        verify_promise
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(gas + TRANSFER_IN_BATCH_GAS)
+                    .with_attached_deposit(deposit)
+                    .finish_verify_proof_batch(events)
+            )
+    }
+
+    /// Verify the receipt proof by the map light client, whose verification fee is paid from the attached deposit.
+    /// Returns the promise and the remaining deposit.
+    fn verify_proof_data(&self, receipt_proof: ReceiptProof) -> (Promise, Balance) {
+        let deposit = env::attached_deposit();
+        assert!(deposit >= self.map_client_verification_fee,
+                "attached deposit {} is less than the verification fee {}", deposit, self.map_client_verification_fee);
+
+        let promise = ext_map_light_client::ext(self.map_client_account.clone())
+            .with_static_gas(VERIFY_LOG_ENTRY_GAS)
+            .with_attached_deposit(self.map_client_verification_fee)
+            .verify_proof_data(receipt_proof, Some(self.map_chain_id.into()), Some(true));
+        (promise, deposit - self.map_client_verification_fee)
+    }
+
+    /// Gas to process the transfer in event after the proof is verified, which is the gas of the calls made
+    /// to deliver the token of the event.
+    fn transfer_in_event_gas(&self, event: &MapTransferOutEvent) -> Gas {
+        let to_chain_token = String::from_utf8(event.to_chain_token.clone()).unwrap();
+        let with_call = !event.message.is_empty();
+        let gas = if self.is_native_token(event.to_chain_token.clone()) {
+            let mut gas = NEAR_WITHDRAW_GAS + TRANSFER_IN_NATIVE_TOKEN_GAS;
+            if with_call {
+                gas += TRANSFER_CALL_GAS + FINISH_TRANSFER_IN_CALL_GAS;
+            }
+            gas
+        } else if self.mcs_tokens.get(&to_chain_token).is_some() {
+            if with_call {
+                MINT_GAS + FINISH_MINT_TRANSFER_IN_CALL_GAS + STORAGE_DEPOSIT_GAS + TRANSFER_CALL_GAS + FINISH_TRANSFER_IN_CALL_GAS
+            } else {
+                MINT_GAS + FINISH_TRANSFER_IN_GAS
+            }
+        } else if with_call {
+            STORAGE_DEPOSIT_GAS + TRANSFER_CALL_GAS + FINISH_TRANSFER_IN_CALL_GAS
+        } else {
+            STORAGE_DEPOSIT_GAS + FT_TRANSFER_GAS + FINISH_TRANSFER_IN_GAS
+        };
+        PROCESS_TRANSFER_IN_EVENT_GAS + gas
+    }
+
+    /// Parse the transfer in event at index of the receipt and check it can be transferred in.
+    fn check_transfer_in_event(&self, receipt_proof: &ReceiptProof, index: usize) -> MapTransferOutEvent {
         assert!(index < receipt_proof.receipt.logs.len(), "index exceeds event size");
         let event_opt = MapTransferOutEvent::from_log_entry_data(receipt_proof.receipt.logs.get(index).unwrap());
         assert!(event_opt.is_some(), "not map transfer out event");
         let event = event_opt.unwrap();
         assert_eq!(self.map_bridge_address, event.map_bridge_address, "unexpected map mcs address: {}", hex::encode(event.map_bridge_address));
+        assert!(receipt_proof.header.may_contain(&self.map_bridge_address, &receipt_proof.receipt.logs[index].topics),
+                "the event is not in the header bloom");
 
         log!("get transfer in event: {}", event);
 
@@ -272,15 +705,39 @@ impl MapCrossChainService {
                     || self.fungible_tokens.get(&to_chain_token).is_some() || self.is_native_token(event.to_chain_token.clone()),
                 "to_chain_token {} is not mcs token or fungible token or native token", to_chain_token);
         assert_eq!(false, self.is_used_event(&event.order_id), "the event with order id {} is used", hex::encode(event.order_id));
+        assert!(String::from_utf8(event.message.clone()).is_ok(), "invalid message: {:?}", event.message);
+        event
+    }
 
-        ext_map_light_client::ext(self.map_client_account.clone())
-            .with_static_gas(VERIFY_LOG_ENTRY_GAS)
-            .verify_proof_data(receipt_proof)
+    /// Deliver a message from MAP to the target NEAR contract based on the proof of the mapMessageOut event.
+    /// Must attach enough NEAR funds to cover for storage of the proof.
+    #[payable]
+    pub fn message_in(&mut self, receipt_proof: ReceiptProof, index: usize) -> Promise {
+        self.check_not_paused(PAUSE_MESSAGE_IN);
+
+        assert!(index < receipt_proof.receipt.logs.len(), "index exceeds event size");
+        let event_opt = MapMessageOutEvent::from_log_entry_data(receipt_proof.receipt.logs.get(index).unwrap());
+        assert!(event_opt.is_some(), "not map message out event");
+        let event = event_opt.unwrap();
+        assert_eq!(self.map_bridge_address, event.map_bridge_address, "unexpected map mcs address: {}", hex::encode(event.map_bridge_address));
+        assert!(receipt_proof.header.may_contain(&self.map_bridge_address, &receipt_proof.receipt.logs[index].topics),
+                "the event is not in the header bloom");
+
+        log!("get message in event: {}", event);
+
+        assert!(env::is_valid_account_id(event.to.as_slice()), "invalid to address: {:?}", event.to);
+        assert_eq!(self.near_chain_id, event.to_chain.0, "unexpected to chain: {}", event.to_chain.0);
+        assert!(event.gas_limit.0 <= MAX_MESSAGE_IN_GAS.0 as u128,
+                "gas limit {} exceeds max message in gas {}", event.gas_limit.0, MAX_MESSAGE_IN_GAS.0);
+        assert!(!self.is_used_event(&event.order_id), "the event with order id {} is used", hex::encode(event.order_id));
+
+        let (verify_promise, deposit) = self.verify_proof_data(receipt_proof);
+        verify_promise
             .then(
                 Self::ext(env::current_account_id())
-                    .with_static_gas(TRANSFER_IN_SINGLE_EVENT_GAS + FINISH_TRANSFER_IN_GAS)
-                    .with_attached_deposit(env::attached_deposit())
-                    .finish_verify_proof(&event)
+                    .with_static_gas(FINISH_VERIFY_MESSAGE_GAS + Gas(event.gas_limit.0 as u64) + FINISH_MESSAGE_IN_GAS)
+                    .with_attached_deposit(deposit)
+                    .finish_verify_message(&event)
             )
     }
 
@@ -291,7 +748,10 @@ impl MapCrossChainService {
 
         if self.valid_mcs_token_out(&token, to_chain) {
             self.check_to_account(to.clone(), to_chain.into());
-            self.check_amount(token.clone(), amount.0, false);
+            let (net_amount, fee) = self.charge_fee(&token, to_chain.into(), amount.0);
+            self.check_amount(token.clone(), net_amount, false);
+            self.check_rate_limit(&token, Direction::Out, net_amount);
+            self.record_volume(&token, Direction::Out, net_amount);
             let from = env::signer_account_id().to_string();
             let order_id = self.get_order_id(&from, &to, to_chain.into());
 
@@ -303,16 +763,17 @@ impl MapCrossChainService {
                 order_id,
                 token: token.clone(),
                 to_chain_token: "".to_string(),
-                amount,
+                amount: net_amount.into(),
             };
+            self.record_out_order(&event.order_id, &OutOrder::new(&event.token, &event.from, &event.to, event.to_chain, event.amount, OrderStatus::Pending));
 
             ext_mcs_token::ext(event.token.parse().unwrap())
                 .with_static_gas(BURN_GAS)
-                .burn(from.parse().unwrap(), event.amount)
+                .burn(from.parse().unwrap(), amount)
                 .then(
                     Self::ext(env::current_account_id())
                         .with_static_gas(FINISH_TRANSFER_OUT_GAS)
-                        .finish_transfer_out(event)
+                        .finish_transfer_out(event, fee.into())
                 )
         } else if self.valid_fungible_token_out(&token, to_chain) {
             env::panic_str(format!("non mcs fungible token {} should called from fungible token directly", token).as_ref());
@@ -329,7 +790,10 @@ impl MapCrossChainService {
         let amount = env::attached_deposit();
         assert!(amount > 0, "amount should > 0");
         assert!(self.native_to_chains.contains(&to_chain.into()), "transfer out native to {} is not supported", to_chain.0);
-        self.check_amount("".to_string(), amount, true);
+        let (net_amount, fee) = self.charge_fee(&self.native_token_address().1, to_chain.into(), amount);
+        self.check_amount("".to_string(), net_amount, true);
+        self.check_rate_limit(&self.native_token_address().1, Direction::Out, net_amount);
+        self.record_volume(&self.native_token_address().1, Direction::Out, net_amount);
 
         let from = env::signer_account_id().to_string();
         let order_id = self.get_order_id(&from, &to, to_chain.into());
@@ -342,8 +806,9 @@ impl MapCrossChainService {
             order_id,
             token: self.native_token_address().1,
             to_chain_token: "".to_string(),
-            amount: amount.into(),
+            amount: net_amount.into(),
         };
+        self.record_out_order(&event.order_id, &OutOrder::new(&event.token, &event.from, &event.to, event.to_chain, event.amount, OrderStatus::Pending));
 
         ext_wnear_token::ext(self.wrapped_token.parse().unwrap())
             .with_static_gas(NEAR_DEPOSIT_GAS)
@@ -352,7 +817,7 @@ impl MapCrossChainService {
             .then(
                 Self::ext(env::current_account_id())
                     .with_static_gas(FINISH_TRANSFER_OUT_GAS)
-                    .finish_transfer_out(event)
+                    .finish_transfer_out(event, fee.into())
             )
     }
 
@@ -373,12 +838,95 @@ impl MapCrossChainService {
                         .with_static_gas(REPORT_FAIL_GAS)
                         .report_transfer_in_fail("verify proof failed".to_string()))
             }
-            PromiseResult::Successful(_) => { self.process_transfer_in(event) }
+            PromiseResult::Successful(_) => { self.process_transfer_in(event, env::attached_deposit()) }
         }
     }
 
-    fn process_transfer_in(&mut self, event: &MapTransferOutEvent) -> Promise {
-        let cur_deposit = env::attached_deposit();
+    /// Finish transfer in of several events once the proof was successfully validated. Each event is processed
+    /// with its share of the attached deposit, and the failure of an event does not affect the others. Can only be
+    /// called by the contract itself.
+    #[payable]
+    pub fn finish_verify_proof_batch(
+        &mut self,
+        events: Vec<MapTransferOutEvent>,
+    ) -> Promise {
+        assert_self();
+        assert_eq!(env::promise_results_count(), 1, "ERR_TOO_MANY_RESULTS");
+        match env::promise_result(0) {
+            PromiseResult::NotReady => env::abort(),
+            PromiseResult::Failed => {
+                Promise::new(env::signer_account_id()).transfer(env::attached_deposit())
+                    .then(Self::ext(env::current_account_id())
+                        .with_static_gas(REPORT_FAIL_GAS)
+                        .report_transfer_in_fail("verify proof failed".to_string()))
+            }
+            PromiseResult::Successful(_) => {
+                let required_deposits: Vec<Balance> = events.iter()
+                    .map(|event| self.transfer_in_required_deposit(event))
+                    .collect();
+                let mut remaining_deposit = env::attached_deposit();
+                let mut promise: Option<Promise> = None;
+                for (i, event) in events.iter().enumerate() {
+                    // the last event takes the deposit left, which is refunded once it is delivered
+                    let deposit = if i == events.len() - 1 {
+                        remaining_deposit
+                    } else {
+                        std::cmp::min(required_deposits[i], remaining_deposit)
+                    };
+                    remaining_deposit -= deposit;
+                    log!("start to process transfer in event {}", hex::encode(event.order_id));
+                    let event_promise = self.process_transfer_in(event, deposit);
+                    promise = Some(match promise {
+                        Some(p) => p.and(event_promise),
+                        None => event_promise,
+                    });
+                }
+                promise.unwrap()
+            }
+        }
+    }
+
+    /// Return the deposit required by process_transfer_in to deliver the event, which covers the storage of the
+    /// order id and the receiver, and of recording the token as claimable or queuing the event if the delivery fails.
+    fn transfer_in_required_deposit(&mut self, event: &MapTransferOutEvent) -> Balance {
+        if self.is_used_event(&event.order_id) {
+            return 0;
+        }
+
+        let initial_storage = env::storage_usage();
+        self.used_events.insert(&event.order_id);
+        let current_storage = env::storage_usage();
+        self.used_events.remove(&event.order_id);
+        let order_id_deposit = Balance::from(current_storage - initial_storage) * env::storage_byte_cost();
+
+        let to_chain_token = String::from_utf8(event.to_chain_token.clone()).unwrap();
+        let is_mcs_token = self.mcs_tokens.get(&to_chain_token).is_some();
+        let pending_deposit = self.pending_transfer_in_storage_cost(event);
+        let claimable_deposit = self.claimable_storage_cost(event);
+        let deliver_deposit = if self.is_native_token(event.to_chain_token.clone()) {
+            1 + claimable_deposit
+        } else if is_mcs_token && event.message.is_empty() {
+            self.mcs_storage_transfer_in_required + claimable_deposit
+        } else {
+            let storage_balance = if is_mcs_token {
+                self.mcs_storage_transfer_in_required
+            } else {
+                self.fungible_tokens_storage_balance.get(&to_chain_token).unwrap_or(0)
+            };
+            if event.message.is_empty() {
+                storage_balance + 1 + claimable_deposit
+            } else if is_mcs_token {
+                storage_balance + std::cmp::max(storage_balance + 2 + claimable_deposit, pending_deposit)
+            } else {
+                storage_balance + 2 + claimable_deposit
+            }
+        };
+        order_id_deposit + std::cmp::max(pending_deposit, deliver_deposit)
+    }
+
+    /// Process the transfer in event with cur_deposit, which covers the storage of the order id and the receiver.
+    /// The cur_deposit must also cover queuing the event for retry, so that the verified event is never dropped.
+    fn process_transfer_in(&mut self, event: &MapTransferOutEvent, cur_deposit: Balance) -> Promise {
         if self.is_used_event(&event.order_id) {
             return Promise::new(env::signer_account_id()).transfer(cur_deposit)
                 .then(Self::ext(env::current_account_id())
@@ -386,38 +934,96 @@ impl MapCrossChainService {
                     .report_transfer_in_fail("the transfer in event is already processed".to_string()));
         }
 
-        let required_deposit = self.record_order_id(&event.order_id);
+        let order_id_deposit = self.record_order_id(&event.order_id);
+        let required_deposit = order_id_deposit + self.pending_transfer_in_storage_cost(event);
         if cur_deposit < required_deposit {
-            return self.process_transfer_in_failure(cur_deposit, event,
-                                                    format!("not enough deposit for record proof, exp: {}, cur: {}", required_deposit, cur_deposit));
+            self.remove_order_id(&event.order_id);
+            return Promise::new(env::signer_account_id()).transfer(cur_deposit)
+                .then(Self::ext(env::current_account_id())
+                    .with_static_gas(REPORT_FAIL_GAS)
+                    .report_transfer_in_fail(format!("not enough deposit for record proof, exp: {}, cur: {}", required_deposit, cur_deposit)));
+        }
+
+        let ret_deposit = cur_deposit - order_id_deposit;
+        let to_chain_token = String::from_utf8(event.to_chain_token.clone()).unwrap();
+        if !self.is_within_rate_limit(&to_chain_token, Direction::In, event.amount.0) {
+            let pending_deposit = self.queue_transfer_in(event);
+            log!("transfer in {} of token {} exceeds the rate limit, order {} is pending", event.amount.0, to_chain_token, hex::encode(event.order_id));
+            return Promise::new(env::signer_account_id()).transfer(ret_deposit - pending_deposit);
         }
 
+        self.record_volume(&to_chain_token, Direction::In, event.amount.0);
+        self.deliver_transfer_in(event, ret_deposit)
+    }
+
+    /// Retry the transfer in event which failed or exceeded the rate limit, without verifying the proof again.
+    /// Can be called by anyone with enough deposit for delivering the token once the rate limit allows.
+    #[payable]
+    pub fn retry_transfer_in(&mut self, order_id: String) -> Promise {
+        self.check_not_paused(PAUSE_TRANSFER_IN);
+
+        let order_id = parse_order_id(&order_id);
+        let event = self.pending_transfers_in.get(&order_id)
+            .unwrap_or_else(|| panic_str(&format!("transfer in of order {} is not pending", hex::encode(order_id))));
+        let to_chain_token = String::from_utf8(event.to_chain_token.clone()).unwrap();
+        assert!(self.is_within_rate_limit(&to_chain_token, Direction::In, event.amount.0),
+                "transfer in {} of token {} still exceeds the rate limit", event.amount.0, to_chain_token);
+
+        let initial_storage = env::storage_usage();
+        self.pending_transfers_in.remove(&order_id);
+        let current_storage = env::storage_usage();
+        let released_deposit = Balance::from(initial_storage - current_storage) * env::storage_byte_cost();
+
+        self.record_volume(&to_chain_token, Direction::In, event.amount.0);
+        self.deliver_transfer_in(&event, env::attached_deposit() + released_deposit)
+    }
+
+    /// Return the transfer in events which failed or exceeded the rate limit.
+    pub fn get_pending_transfers_in(&self, from_index: u64, limit: u64) -> Vec<MapTransferOutEvent> {
+        self.pending_transfers_in.values()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    /// Deliver the token of the transfer in event to the receiver, the ret_deposit is used for the storage of the
+    /// receiver and refunded to the signer, and the event is queued for retry if the delivery can not start, so the
+    /// ret_deposit always covers queuing the event. The ret_deposit must also cover recording the token as claimable
+    /// in case the delivery fails.
+    fn deliver_transfer_in(&mut self, event: &MapTransferOutEvent, mut ret_deposit: Balance) -> Promise {
         let to = String::from_utf8(event.to.clone()).unwrap();
         let to_chain_token = String::from_utf8(event.to_chain_token.clone()).unwrap();
         env::log_str(&*format!("start to transfer in token: {}, to: {}, amount: {}", to_chain_token, to, event.amount.0));
 
-        let mut ret_deposit = cur_deposit - required_deposit;
-
         if self.is_native_token(event.to_chain_token.clone()) {
-            if ret_deposit < 1 {
-                return self.process_transfer_in_failure(cur_deposit, event,
-                                                        "not enough deposit for near withdraw".to_string());
+            let claimable_deposit = self.claimable_storage_cost(event);
+            if ret_deposit < 1 + claimable_deposit {
+                return self.process_transfer_in_failure(ret_deposit, event,
+                                                        format!("not enough deposit for near withdraw, exp: {}, cur: {}", 1 + claimable_deposit, ret_deposit));
             }
+            ret_deposit -= 1;
 
+            let mut gas = TRANSFER_IN_NATIVE_TOKEN_GAS;
+            if !event.message.is_empty() {
+                gas = gas + TRANSFER_CALL_GAS + FINISH_TRANSFER_IN_CALL_GAS;
+            }
             ext_wnear_token::ext(self.wrapped_token.parse().unwrap())
                 .with_static_gas(NEAR_WITHDRAW_GAS)
                 .with_attached_deposit(1)
                 .near_withdraw(event.amount)
                 .then(
                     Self::ext(env::current_account_id())
-                        .with_static_gas(TRANSFER_IN_NATIVE_TOKEN_GAS)
+                        .with_static_gas(gas)
                         .with_attached_deposit(ret_deposit)
                         .transfer_in_native_token(event)
                 )
+        } else if !event.message.is_empty() {
+            self.process_transfer_in_call(event, &to_chain_token, ret_deposit)
         } else if self.mcs_tokens.get(&to_chain_token).is_some() {
-            if ret_deposit < self.mcs_storage_transfer_in_required {
-                return self.process_transfer_in_failure(cur_deposit, event,
-                                                        format!("not enough deposit for mcs token mint, exp: {}, cur: {}", self.mcs_storage_transfer_in_required, ret_deposit));
+            let required_deposit = self.mcs_storage_transfer_in_required + self.claimable_storage_cost(event);
+            if ret_deposit < required_deposit {
+                return self.process_transfer_in_failure(ret_deposit, event,
+                                                        format!("not enough deposit for mcs token mint, exp: {}, cur: {}", required_deposit, ret_deposit));
             }
             ret_deposit = ret_deposit - self.mcs_storage_transfer_in_required;
 
@@ -432,9 +1038,10 @@ impl MapCrossChainService {
         } else if self.fungible_tokens.get(&to_chain_token).is_some() {
             // to_chain_token is fungible token
             let min_storage_balance = self.fungible_tokens_storage_balance.get(&to_chain_token).unwrap();
-            if ret_deposit < min_storage_balance + 1 {
-                return self.process_transfer_in_failure(cur_deposit, event,
-                                                        format!("not enough deposit for ft transfer, exp: {}, cur: {}", 1 + min_storage_balance, ret_deposit));
+            let required_deposit = min_storage_balance + 1 + self.claimable_storage_cost(event);
+            if ret_deposit < required_deposit {
+                return self.process_transfer_in_failure(ret_deposit, event,
+                                                        format!("not enough deposit for ft transfer, exp: {}, cur: {}", required_deposit, ret_deposit));
             }
             ret_deposit = ret_deposit - 1 - min_storage_balance;
 
@@ -456,14 +1063,118 @@ impl MapCrossChainService {
         }
     }
 
+    /// Deliver the mcs or fungible token to the receiver with ft_transfer_call. The mcs token is minted to
+    /// the mcs contract first, so the token not used by the receiver is refunded to the mcs contract. The
+    /// ret_deposit must also cover recording the token as claimable in case ft_transfer_call fails, and queuing the
+    /// event for retry in case minting the mcs token fails.
+    fn process_transfer_in_call(&mut self, event: &MapTransferOutEvent, to_chain_token: &String, ret_deposit: Balance) -> Promise {
+        let is_mcs_token = self.mcs_tokens.get(to_chain_token).is_some();
+        let storage_balance = if is_mcs_token {
+            self.mcs_storage_transfer_in_required
+        } else if self.fungible_tokens.get(to_chain_token).is_some() {
+            self.fungible_tokens_storage_balance.get(to_chain_token).unwrap()
+        } else {
+            panic_str(&format!("to_chain_token {} is not mcs token or fungible token or native token", to_chain_token))
+        };
+        let mint_deposit = if is_mcs_token { self.mcs_storage_transfer_in_required } else { 0 };
+        let claimable_deposit = self.claimable_storage_cost(event);
+        let pending_deposit = if is_mcs_token { self.pending_transfer_in_storage_cost(event) } else { 0 };
+
+        // 1 yocto for ft_transfer_call and 1 yocto for refunding the unused token to the receiver
+        let required_deposit = mint_deposit + std::cmp::max(storage_balance + 2 + claimable_deposit, pending_deposit);
+        if ret_deposit < required_deposit {
+            return self.process_transfer_in_failure(ret_deposit, event,
+                                                    format!("not enough deposit for ft transfer call, exp: {}, cur: {}", required_deposit, ret_deposit));
+        }
+
+        if is_mcs_token {
+            ext_mcs_token::ext(to_chain_token.parse().unwrap())
+                .with_static_gas(MINT_GAS)
+                .with_attached_deposit(mint_deposit)
+                .mint(env::current_account_id(), event.amount)
+                .then(Self::ext(env::current_account_id())
+                    .with_static_gas(FINISH_MINT_TRANSFER_IN_CALL_GAS + STORAGE_DEPOSIT_GAS + TRANSFER_CALL_GAS + FINISH_TRANSFER_IN_CALL_GAS)
+                    .with_attached_deposit(ret_deposit - mint_deposit)
+                    .finish_mint_transfer_in_call(event))
+        } else {
+            self.transfer_in_call(event, ret_deposit)
+        }
+    }
+
+    /// Continue the transfer in with call once the mcs token is minted to the mcs contract. If minting failed,
+    /// the event is queued for retry like a transfer in whose delivery can not start.
+    #[payable]
+    pub fn finish_mint_transfer_in_call(
+        &mut self,
+        event: &MapTransferOutEvent,
+    ) -> Promise {
+        assert_self();
+        assert_eq!(1, env::promise_results_count(), "ERR_TOO_MANY_RESULTS");
+
+        match env::promise_result(0) {
+            PromiseResult::NotReady => env::abort(),
+            PromiseResult::Successful(_) => self.transfer_in_call(event, env::attached_deposit()),
+            _ => self.process_transfer_in_failure(env::attached_deposit(), event,
+                                                  "mint mcs token for transfer in call failed".to_string()),
+        }
+    }
+
+    /// Register the receiver in the token contract and transfer the token held by the mcs contract to it with
+    /// ft_transfer_call.
+    fn transfer_in_call(&mut self, event: &MapTransferOutEvent, ret_deposit: Balance) -> Promise {
+        let to: AccountId = String::from_utf8(event.to.clone()).unwrap().parse().unwrap();
+        let to_chain_token = String::from_utf8(event.to_chain_token.clone()).unwrap();
+        let storage_balance = if self.mcs_tokens.get(&to_chain_token).is_some() {
+            self.mcs_storage_transfer_in_required
+        } else {
+            self.fungible_tokens_storage_balance.get(&to_chain_token).unwrap()
+        };
+        let token_account: AccountId = to_chain_token.parse().unwrap();
+
+        ext_fungible_token::ext(token_account.clone())
+            .with_static_gas(STORAGE_DEPOSIT_GAS)
+            .with_attached_deposit(storage_balance)
+            .storage_deposit(Some(to.clone()), Some(true))
+            .then(ext_fungible_token::ext(token_account)
+                .with_static_gas(TRANSFER_CALL_GAS)
+                .with_attached_deposit(1)
+                .ft_transfer_call(to, event.amount, None, String::from_utf8(event.message.clone()).unwrap()))
+            .then(Self::ext(env::current_account_id())
+                .with_static_gas(FINISH_TRANSFER_IN_CALL_GAS)
+                .with_attached_deposit(ret_deposit - storage_balance - 1)
+                .finish_transfer_in_call(event))
+    }
+
+    /// Queue the verified transfer in event to be retried by anyone with retry_transfer_in, and refund the
+    /// ret_deposit left after paying for its storage. The volume of the event is released as it is recorded again
+    /// when the event is retried.
     fn process_transfer_in_failure(&mut self, ret_deposit: Balance, event: &MapTransferOutEvent, err_msg: String) -> Promise {
-        self.remove_order_id(&event.order_id);
-        Promise::new(env::signer_account_id()).transfer(ret_deposit)
+        let to_chain_token = String::from_utf8(event.to_chain_token.clone()).unwrap();
+        self.release_volume(&to_chain_token, Direction::In, event.amount.0);
+        let pending_deposit = self.queue_transfer_in(event);
+        log!("transfer in of order {} failed, it can be retried", hex::encode(event.order_id));
+
+        Promise::new(env::signer_account_id()).transfer(ret_deposit - pending_deposit)
             .then(Self::ext(env::current_account_id())
                 .with_static_gas(REPORT_FAIL_GAS)
                 .report_transfer_in_fail(err_msg))
     }
 
+    /// Queue the transfer in event for retry, and return the storage cost.
+    fn queue_transfer_in(&mut self, event: &MapTransferOutEvent) -> Balance {
+        let initial_storage = env::storage_usage();
+        self.pending_transfers_in.insert(&event.order_id, event);
+        let current_storage = env::storage_usage();
+        Balance::from(current_storage - initial_storage) * env::storage_byte_cost()
+    }
+
+    /// Return the storage cost of queuing the transfer in event for retry.
+    fn pending_transfer_in_storage_cost(&mut self, event: &MapTransferOutEvent) -> Balance {
+        let storage_cost = self.queue_transfer_in(event);
+        self.pending_transfers_in.remove(&event.order_id);
+        storage_cost
+    }
+
     #[payable]
     pub fn transfer_in_native_token(
         &mut self,
@@ -476,6 +1187,17 @@ impl MapCrossChainService {
             PromiseResult::NotReady => env::abort(),
             PromiseResult::Successful(_) => {
                 let to = String::from_utf8(event.to.clone()).unwrap();
+                if !event.message.is_empty() {
+                    return ext_native_receiver::ext(to.parse().unwrap())
+                        .with_static_gas(TRANSFER_CALL_GAS)
+                        .with_attached_deposit(event.amount.into())
+                        .on_mcs_native_received(event.from_chain, event.from.clone(), event.amount,
+                                                String::from_utf8(event.message.clone()).unwrap())
+                        .then(Self::ext(env::current_account_id())
+                            .with_static_gas(FINISH_TRANSFER_IN_CALL_GAS)
+                            .with_attached_deposit(env::attached_deposit())
+                            .finish_transfer_in_call(event));
+                }
                 Promise::new(to.parse().unwrap()).transfer(event.amount.into())
                     .then(Self::ext(env::current_account_id())
                         .with_static_gas(FINISH_TRANSFER_IN_GAS)
@@ -483,10 +1205,12 @@ impl MapCrossChainService {
                         .finish_transfer_in(event))
             }
             _ => {
-                Promise::new(env::signer_account_id()).transfer(env::attached_deposit() + self.remove_order_id(&event.order_id))
+                // the deposit for recording the token as claimable is checked before the transfer in starts
+                let required_deposit = self.record_claimable(event);
+                Promise::new(env::signer_account_id()).transfer(env::attached_deposit() - required_deposit)
                     .then(Self::ext(env::current_account_id())
                         .with_static_gas(REPORT_FAIL_GAS)
-                        .report_transfer_in_fail("near withdraw failed".to_string()))
+                        .report_transfer_in_fail("near withdraw failed, the token is claimable by the receiver".to_string()))
             }
         }
     }
@@ -503,50 +1227,530 @@ impl MapCrossChainService {
             PromiseResult::NotReady => env::abort(),
             PromiseResult::Successful(_) => { Promise::new(env::signer_account_id()).transfer(env::attached_deposit()) }
             _ => {
-                let mut err_msg = "transfer in token failed".to_string();
-                let mut promise = Promise::new(env::signer_account_id());
+                // the deposit for recording the token as claimable is checked before the transfer in starts
+                let required_deposit = self.record_claimable(event);
+                let mut promise = Promise::new(env::signer_account_id())
+                    .transfer(env::attached_deposit() - required_deposit);
                 if self.is_native_token(event.to_chain_token.clone()) {
-                    promise = promise.transfer(env::attached_deposit())
-                        .then(ext_wnear_token::ext(self.wrapped_token.parse().unwrap())
-                            .with_static_gas(NEAR_DEPOSIT_GAS)
-                            .with_attached_deposit(event.amount.into())
-                            .near_deposit());
-                    err_msg = format!("transfer in token failed, maybe TO account does not exist")
-                } else {
-                    promise = promise.transfer(env::attached_deposit() + self.remove_order_id(&event.order_id))
+                    promise = promise.then(ext_wnear_token::ext(self.wrapped_token.parse().unwrap())
+                        .with_static_gas(NEAR_DEPOSIT_GAS)
+                        .with_attached_deposit(event.amount.into())
+                        .near_deposit());
                 }
                 promise.then(Self::ext(env::current_account_id())
                     .with_static_gas(REPORT_FAIL_GAS)
-                    .report_transfer_in_fail(err_msg))
+                    .report_transfer_in_fail("transfer in token failed, maybe TO account does not exist, the token is claimable by the receiver".to_string()))
             }
         }
     }
 
-    pub fn report_transfer_in_fail(err: String) {
+    /// Finish transfer in with call once the receiver was called. The token not used by the receiver is
+    /// transferred to it. For native token, the whole amount is transferred to the receiver if the call
+    /// failed.
+    #[payable]
+    pub fn finish_transfer_in_call(
+        &mut self,
+        event: &MapTransferOutEvent,
+    ) -> Promise {
         assert_self();
-        panic_str(err.as_str())
-    }
+        assert_eq!(1, env::promise_results_count(), "ERR_TOO_MANY_RESULTS");
 
-    /// Finish transfer out once the nep141 token is burned from MCSToken contract or native token is transferred.
-    pub fn finish_transfer_out(
-        &self,
-        event: TransferOutEvent,
-    ) {
-        assert_self();
-        assert_eq!(PromiseResult::Successful(vec![]), env::promise_result(0), "burn mcs token or call near_deposit() failed");
-        log!("transfer out: {}", serde_json::to_string(&event).unwrap());
-        log!("{}{}", TRANSFER_OUT_TYPE, event);
-    }
+        let to: AccountId = String::from_utf8(event.to.clone()).unwrap().parse().unwrap();
+        match env::promise_result(0) {
+            PromiseResult::NotReady => env::abort(),
+            PromiseResult::Successful(x) => {
+                if self.is_native_token(event.to_chain_token.clone()) {
+                    return Promise::new(env::signer_account_id()).transfer(env::attached_deposit());
+                }
 
-    /// Finish deposit out once the nep141 token is burned from MCSToken contract or native token is transferred.
+                let used = serde_json::from_slice::<U128>(&x).unwrap();
+                let unused = event.amount.0 - used.0;
+                if unused == 0 {
+                    return Promise::new(env::signer_account_id()).transfer(env::attached_deposit());
+                }
+
+                log!("refund unused {} to {}", unused, to);
+                let to_chain_token = String::from_utf8(event.to_chain_token.clone()).unwrap();
+                ext_fungible_token::ext(to_chain_token.parse().unwrap())
+                    .with_static_gas(FT_TRANSFER_GAS)
+                    .with_attached_deposit(1)
+                    .ft_transfer(to, unused.into(), None)
+                    .and(Promise::new(env::signer_account_id()).transfer(env::attached_deposit() - 1))
+            }
+            _ => {
+                if self.is_native_token(event.to_chain_token.clone()) {
+                    log!("call on_mcs_native_received failed, transfer {} to {}", event.amount.0, to);
+                    return Promise::new(to).transfer(event.amount.into())
+                        .then(Self::ext(env::current_account_id())
+                            .with_static_gas(FINISH_TRANSFER_IN_GAS)
+                            .with_attached_deposit(env::attached_deposit())
+                            .finish_transfer_in(event));
+                }
+
+                // the token is held by the mcs contract, and the deposit for recording it as claimable is checked
+                // before the transfer in starts
+                let required_deposit = self.record_claimable(event);
+                let mut promise = Promise::new(env::signer_account_id()).transfer(env::attached_deposit() - required_deposit);
+                let to_chain_token = String::from_utf8(event.to_chain_token.clone()).unwrap();
+                if self.mcs_tokens.get(&to_chain_token).is_some() {
+                    // the claimable mcs token is minted when it is claimed
+                    promise = promise.and(ext_mcs_token::ext(to_chain_token.parse().unwrap())
+                        .with_static_gas(BURN_GAS)
+                        .burn(env::current_account_id(), event.amount));
+                }
+                promise.then(Self::ext(env::current_account_id())
+                    .with_static_gas(REPORT_FAIL_GAS)
+                    .report_transfer_in_fail("transfer in token with call failed, the token is claimable by the receiver".to_string()))
+            }
+        }
+    }
+
+    pub fn report_transfer_in_fail(err: String) {
+        assert_self();
+        panic_str(err.as_str())
+    }
+
+    pub fn report_transfer_out_fail(err: String) {
+        assert_self();
+        panic_str(err.as_str())
+    }
+
+    /// Call the target contract of the message once the proof was successfully validated. Can only be
+    /// called by the contract itself.
+    #[payable]
+    pub fn finish_verify_message(
+        &mut self,
+        event: &MapMessageOutEvent,
+    ) -> Promise {
+        assert_self();
+        assert_eq!(env::promise_results_count(), 1, "ERR_TOO_MANY_RESULTS");
+        let cur_deposit = env::attached_deposit();
+        match env::promise_result(0) {
+            PromiseResult::NotReady => env::abort(),
+            PromiseResult::Failed => {
+                return Promise::new(env::signer_account_id()).transfer(cur_deposit)
+                    .then(Self::ext(env::current_account_id())
+                        .with_static_gas(REPORT_FAIL_GAS)
+                        .report_transfer_in_fail("verify proof failed".to_string()));
+            }
+            PromiseResult::Successful(_) => {}
+        }
+
+        if self.is_used_event(&event.order_id) {
+            return Promise::new(env::signer_account_id()).transfer(cur_deposit)
+                .then(Self::ext(env::current_account_id())
+                    .with_static_gas(REPORT_FAIL_GAS)
+                    .report_transfer_in_fail("the message in event is already processed".to_string()));
+        }
+
+        let required_deposit = self.record_order_id(&event.order_id);
+        let failed_message_deposit = self.failed_message_storage_cost(event);
+        if cur_deposit < required_deposit + failed_message_deposit {
+            self.remove_order_id(&event.order_id);
+            return Promise::new(env::signer_account_id()).transfer(cur_deposit)
+                .then(Self::ext(env::current_account_id())
+                    .with_static_gas(REPORT_FAIL_GAS)
+                    .report_transfer_in_fail(format!("not enough deposit for record proof, exp: {}, cur: {}",
+                                                     required_deposit + failed_message_deposit, cur_deposit)));
+        }
+
+        let to = String::from_utf8(event.to.clone()).unwrap();
+        env::log_str(&format!("start to deliver message {} to: {}", hex::encode(event.order_id), to));
+
+        ext_message_receiver::ext(to.parse().unwrap())
+            .with_static_gas(Gas(event.gas_limit.0 as u64))
+            .on_map_message(event.from_chain, event.from.clone(), hex::encode(event.order_id), event.payload.clone())
+            .then(Self::ext(env::current_account_id())
+                .with_static_gas(FINISH_MESSAGE_IN_GAS)
+                .with_attached_deposit(cur_deposit - required_deposit)
+                .finish_message_in(event))
+    }
+
+    /// Record the message as failed if the target contract call failed. The order id is kept used, so the
+    /// message can not be replayed.
+    #[payable]
+    pub fn finish_message_in(
+        &mut self,
+        event: &MapMessageOutEvent,
+    ) -> Promise {
+        assert_self();
+        assert_eq!(1, env::promise_results_count(), "ERR_TOO_MANY_RESULTS");
+
+        let mut ret_deposit = env::attached_deposit();
+        match env::promise_result(0) {
+            PromiseResult::NotReady => env::abort(),
+            PromiseResult::Successful(_) => {
+                log!("message in: {}", hex::encode(event.order_id));
+            }
+            _ => {
+                // the deposit for recording the failed message is checked before calling the target contract
+                ret_deposit -= self.failed_message_storage_cost(event);
+                self.failed_messages.insert(&event.order_id, event);
+                log!("message in failed: {}", hex::encode(event.order_id));
+            }
+        }
+        Promise::new(env::signer_account_id()).transfer(ret_deposit)
+    }
+
+    /// Return the storage cost of recording the message as failed.
+    fn failed_message_storage_cost(&mut self, event: &MapMessageOutEvent) -> Balance {
+        let initial_storage = env::storage_usage();
+        self.failed_messages.insert(&event.order_id, event);
+        let current_storage = env::storage_usage();
+        self.failed_messages.remove(&event.order_id);
+        Balance::from(current_storage - initial_storage) * env::storage_byte_cost()
+    }
+
+    /// Call the target contract of the failed message again. Can be called by anyone, and the storage of the
+    /// failed message is refunded to the caller if the call succeeds.
+    pub fn retry_message(&mut self, order_id: String) -> Promise {
+        self.check_not_paused(PAUSE_MESSAGE_IN);
+
+        let order_id = parse_order_id(&order_id);
+        let event = self.failed_messages.get(&order_id)
+            .unwrap_or_else(|| panic_str(format!("message of order {} is not failed", hex::encode(order_id)).as_str()));
+
+        // removed during the call, so the message can not be delivered twice by concurrent retries
+        let initial_storage = env::storage_usage();
+        self.failed_messages.remove(&order_id);
+        let current_storage = env::storage_usage();
+        let released_deposit = Balance::from(initial_storage - current_storage) * env::storage_byte_cost();
+
+        let to = String::from_utf8(event.to.clone()).unwrap();
+        env::log_str(&format!("start to retry message {} to: {}", hex::encode(event.order_id), to));
+
+        ext_message_receiver::ext(to.parse().unwrap())
+            .with_static_gas(Gas(event.gas_limit.0 as u64))
+            .on_map_message(event.from_chain, event.from.clone(), hex::encode(event.order_id), event.payload.clone())
+            .then(Self::ext(env::current_account_id())
+                .with_static_gas(FINISH_RETRY_MESSAGE_GAS)
+                .finish_retry_message(&event, released_deposit.into()))
+    }
+
+    /// Refund the storage of the failed message if the retry succeeded, or record it as failed again.
+    pub fn finish_retry_message(&mut self, event: &MapMessageOutEvent, released_deposit: U128) -> PromiseOrValue<()> {
+        assert_self();
+        assert_eq!(1, env::promise_results_count(), "ERR_TOO_MANY_RESULTS");
+
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            log!("message in: {}", hex::encode(event.order_id));
+            PromiseOrValue::Promise(Promise::new(env::signer_account_id()).transfer(released_deposit.0))
+        } else {
+            self.failed_messages.insert(&event.order_id, event);
+            log!("retry message in failed: {}", hex::encode(event.order_id));
+            PromiseOrValue::Value(())
+        }
+    }
+
+    /// Remove the failed message which will not be retried. Can only be called by the owner.
+    pub fn remove_failed_message(&mut self, order_id: String) {
+        assert!(self.is_owner(), "unexpected caller {}", env::predecessor_account_id());
+
+        let order_id = parse_order_id(&order_id);
+        assert!(self.failed_messages.remove(&order_id).is_some(), "message of order {} is not failed", hex::encode(order_id));
+    }
+
+    /// Record the token of the failed transfer in as claimable by the receiver, and return the storage cost.
+    fn record_claimable(&mut self, event: &MapTransferOutEvent) -> Balance {
+        let to = String::from_utf8(event.to.clone()).unwrap();
+        let claimable = Claimable { token: self.claimable_token(event), amount: event.amount };
+        let storage_cost = self.insert_claimable(&to, &event.order_id, &claimable);
+        log!("transfer in {} of token {} to {} failed, it is claimable with order id {}", event.amount.0, claimable.token, to, hex::encode(event.order_id));
+        storage_cost
+    }
+
+    /// Insert the claimable token of the receiver, and return the storage cost.
+    fn insert_claimable(&mut self, recipient: &String, order_id: &CryptoHash, claimable: &Claimable) -> Balance {
+        let initial_storage = env::storage_usage();
+        self.claimables.insert(order_id, claimable);
+        let mut order_ids = self.recipient_claimables.get(recipient).unwrap_or_else(|| {
+            UnorderedSet::new([b"w".to_vec(), env::sha256(recipient.as_bytes())].concat())
+        });
+        order_ids.insert(order_id);
+        self.recipient_claimables.insert(recipient, &order_ids);
+        let current_storage = env::storage_usage();
+        Balance::from(current_storage - initial_storage) * env::storage_byte_cost()
+    }
+
+    /// Remove the claimable token of the receiver. The entry of the receiver is kept even if it has no claimable
+    /// token, so that recording a claimable token never costs more than the deposit reserved for it.
+    fn remove_claimable(&mut self, recipient: &String, order_id: &CryptoHash) -> Option<Claimable> {
+        let mut order_ids = self.recipient_claimables.get(recipient)?;
+        if !order_ids.remove(order_id) {
+            return None;
+        }
+        self.recipient_claimables.insert(recipient, &order_ids);
+        self.claimables.remove(order_id)
+    }
+
+    /// Return the token claimable by the receiver of the transfer in, the native token is claimed as wrapped token.
+    fn claimable_token(&self, event: &MapTransferOutEvent) -> String {
+        if self.is_native_token(event.to_chain_token.clone()) {
+            self.wrapped_token.clone()
+        } else {
+            String::from_utf8(event.to_chain_token.clone()).unwrap()
+        }
+    }
+
+    /// Return the storage cost of recording the token of the transfer in as claimable.
+    fn claimable_storage_cost(&mut self, event: &MapTransferOutEvent) -> Balance {
+        let to = String::from_utf8(event.to.clone()).unwrap();
+        let is_new_recipient = self.recipient_claimables.get(&to).is_none();
+        let claimable = Claimable { token: self.claimable_token(event), amount: event.amount };
+        let storage_cost = self.insert_claimable(&to, &event.order_id, &claimable);
+        self.remove_claimable(&to, &event.order_id);
+        if is_new_recipient {
+            self.recipient_claimables.remove(&to);
+        }
+        storage_cost
+    }
+
+    /// Claim the token of the failed transfer in by the receiver, or by the recovery account on behalf of the
+    /// receiver. The token is always transferred to the receiver, the recovery account only pays for the storage of
+    /// the receiver in the token contract with the attached deposit, e.g. when the receiver account is created later.
+    #[payable]
+    pub fn claim(&mut self, recipient: String, order_id: String) -> Promise {
+        let claimer = env::predecessor_account_id();
+        assert!(claimer.as_str() == recipient || Some(&claimer) == self.recovery_account.as_ref(),
+                "unexpected caller {}", claimer);
+        let receiver: AccountId = recipient.parse()
+            .unwrap_or_else(|_| panic_str(&format!("invalid recipient {}", recipient)));
+        let order_id = parse_order_id(&order_id);
+        let claimable = self.remove_claimable(&recipient, &order_id)
+            .unwrap_or_else(|| panic_str(&format!("no claimable token of order {} for {}", hex::encode(order_id), recipient)));
+
+        let token: AccountId = claimable.token.parse().unwrap();
+        let promise = if self.mcs_tokens.get(&claimable.token).is_some() {
+            ext_mcs_token::ext(token)
+                .with_static_gas(MINT_GAS)
+                .with_attached_deposit(env::attached_deposit())
+                .mint(receiver, claimable.amount)
+        } else {
+            ext_fungible_token::ext(token.clone())
+                .with_static_gas(STORAGE_DEPOSIT_GAS)
+                .with_attached_deposit(env::attached_deposit())
+                .storage_deposit(Some(receiver.clone()), Some(true))
+                .then(ext_fungible_token::ext(token)
+                    .with_static_gas(FT_TRANSFER_GAS)
+                    .with_attached_deposit(1)
+                    .ft_transfer(receiver, claimable.amount, None))
+        };
+        promise.then(Self::ext(env::current_account_id())
+            .with_static_gas(FINISH_CLAIM_GAS)
+            .finish_claim(recipient, order_id, claimer, claimable))
+    }
+
+    /// Restore the claimable token if claiming failed.
+    pub fn finish_claim(&mut self, recipient: String, order_id: CryptoHash, claimer: AccountId, claimable: Claimable) {
+        assert_self();
+        assert_eq!(1, env::promise_results_count(), "ERR_TOO_MANY_RESULTS");
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            log!("{} of token {} of order {} is claimed to {} by {}", claimable.amount.0, claimable.token, hex::encode(order_id), recipient, claimer);
+        } else {
+            log!("claim {} of token {} of order {} failed", claimable.amount.0, claimable.token, hex::encode(order_id));
+            self.insert_claimable(&recipient, &order_id, &claimable);
+        }
+    }
+
+    /// Return the claimable tokens of the receiver with their order ids, from the from_index-th one.
+    pub fn get_claimables(&self, recipient: String, from_index: u64, limit: u64) -> Vec<(String, Claimable)> {
+        let order_ids = match self.recipient_claimables.get(&recipient) {
+            Some(order_ids) => order_ids,
+            None => return vec![],
+        };
+        let order_ids = order_ids.as_vector();
+        (from_index..std::cmp::min(from_index + limit, order_ids.len()))
+            .map(|i| {
+                let order_id = order_ids.get(i).unwrap();
+                (hex::encode(order_id), self.claimables.get(&order_id).unwrap())
+            })
+            .collect()
+    }
+
+    pub fn set_recovery_account(&mut self, recovery_account: Option<AccountId>) {
+        assert!(self.is_owner(), "unexpected caller {}", env::predecessor_account_id());
+        self.recovery_account = recovery_account;
+    }
+
+    pub fn get_recovery_account(&self) -> Option<AccountId> {
+        self.recovery_account.clone()
+    }
+
+    /// Return the message events whose target contract call failed, from the from_index-th one.
+    pub fn get_failed_messages(&self, from_index: u64, limit: u64) -> Vec<MapMessageOutEvent> {
+        self.failed_messages.values()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    /// Finish transfer out once the nep141 token is burned from MCSToken contract or native token is transferred.
+    pub fn finish_transfer_out(
+        &mut self,
+        event: TransferOutEvent,
+        fee: U128,
+    ) -> PromiseOrValue<()> {
+        assert_self();
+        if env::promise_result(0) != PromiseResult::Successful(vec![]) {
+            // the volume and the order are recorded when the transfer out starts
+            self.release_volume(&event.token, Direction::Out, event.amount.0);
+            self.update_out_order_status(&event.order_id, OrderStatus::Failed);
+            return PromiseOrValue::Promise(Self::ext(env::current_account_id())
+                .with_static_gas(REPORT_FAIL_GAS)
+                .report_transfer_out_fail("burn mcs token or call near_deposit() failed".to_string()));
+        }
+        self.accrue_fee(&event.token, fee.0);
+        self.update_out_order_status(&event.order_id, OrderStatus::Sent);
+        log!("transfer out: {}", serde_json::to_string(&event).unwrap());
+        log!("{}{}", TRANSFER_OUT_TYPE, event);
+        PromiseOrValue::Value(())
+    }
+
+    /// Finish deposit out once the nep141 token is burned from MCSToken contract or native token is transferred.
     pub fn finish_deposit_out(
-        &self,
+        &mut self,
         event: DepositOutEvent,
-    ) {
+        fee: U128,
+    ) -> PromiseOrValue<()> {
         assert_self();
-        assert_eq!(PromiseResult::Successful(vec![]), env::promise_result(0), "burn mcs token or call near_deposit() failed");
+        if env::promise_result(0) != PromiseResult::Successful(vec![]) {
+            // the volume and the order are recorded when the deposit out starts
+            self.release_volume(&event.token, Direction::Out, event.amount.0);
+            self.update_out_order_status(&event.order_id, OrderStatus::Failed);
+            return PromiseOrValue::Promise(Self::ext(env::current_account_id())
+                .with_static_gas(REPORT_FAIL_GAS)
+                .report_transfer_out_fail("burn mcs token or call near_deposit() failed".to_string()));
+        }
+        self.accrue_fee(&event.token, fee.0);
+        self.update_out_order_status(&event.order_id, OrderStatus::Sent);
         log!("deposit out: {}", serde_json::to_string(&event).unwrap());
         log!("{}{}", DEPOSIT_OUT_TYPE, event);
+        PromiseOrValue::Value(())
+    }
+
+    /// Record the out order, its storage is paid from the order storage balance. The order is not recorded if the
+    /// balance is not enough, so that the transfer out or deposit out never fails for it.
+    fn record_out_order(&mut self, order_id: &CryptoHash, order: &OutOrder) {
+        let initial_storage = env::storage_usage();
+        self.out_orders.insert(order_id, order);
+
+        let from = &order.from;
+        let mut order_ids = self.sender_out_orders.get(from).unwrap_or_else(|| {
+            Vector::new([b"q".to_vec(), env::sha256(from.as_bytes())].concat())
+        });
+        order_ids.push(order_id);
+        self.sender_out_orders.insert(from, &order_ids);
+
+        let current_storage = env::storage_usage();
+        let required_deposit = Balance::from(current_storage - initial_storage) * env::storage_byte_cost();
+        if self.order_storage_balance < required_deposit {
+            order_ids.pop();
+            if order_ids.is_empty() {
+                self.sender_out_orders.remove(from);
+            } else {
+                self.sender_out_orders.insert(from, &order_ids);
+            }
+            self.out_orders.remove(order_id);
+            log!("order storage balance {} is not enough for {}, out order {} is not recorded",
+                self.order_storage_balance, required_deposit, hex::encode(order_id));
+            return;
+        }
+        self.order_storage_balance -= required_deposit;
+    }
+
+    fn update_out_order_status(&mut self, order_id: &CryptoHash, status: OrderStatus) {
+        if let Some(mut order) = self.out_orders.get(order_id) {
+            order.status = status;
+            order.timestamp = env::block_timestamp() / 1_000_000_000;
+            self.out_orders.insert(order_id, &order);
+        }
+    }
+
+    /// Fund the storage of the out orders with the attached deposit, which can be called by anyone.
+    #[payable]
+    pub fn fund_order_storage(&mut self) {
+        self.order_storage_balance += env::attached_deposit();
+    }
+
+    pub fn get_order_storage_balance(&self) -> U128 {
+        self.order_storage_balance.into()
+    }
+
+    /// Mark the out order as delivered based on the proof of the mapTransferRelay event of a transfer out order, or
+    /// the mapDepositIn event of a deposit out order, which MAP relay chain emits once the order is relayed.
+    /// Must attach the verification fee of the map light client.
+    #[payable]
+    pub fn mark_delivered(&mut self, receipt_proof: ReceiptProof, index: usize) -> Promise {
+        assert!(index < receipt_proof.receipt.logs.len(), "index exceeds event size");
+        let log_entry = receipt_proof.receipt.logs.get(index).unwrap();
+        let (map_bridge_address, from_chain, to_chain, order_id) = if let Some(event) = MapTransferRelayEvent::from_log_entry_data(log_entry) {
+            log!("get transfer relay event: {}", event);
+            (event.map_bridge_address, event.from_chain, event.to_chain, event.order_id)
+        } else if let Some(event) = MapDepositInEvent::from_log_entry_data(log_entry) {
+            log!("get deposit in event: {}", event);
+            (event.map_bridge_address, event.from_chain, event.to_chain, event.order_id)
+        } else {
+            panic!("not map transfer relay or deposit in event")
+        };
+        assert_eq!(self.map_bridge_address, map_bridge_address, "unexpected map mcs address: {}", hex::encode(map_bridge_address));
+        assert!(receipt_proof.header.may_contain(&self.map_bridge_address, &log_entry.topics),
+                "the event is not in the header bloom");
+        assert_eq!(self.near_chain_id, from_chain.0, "unexpected from chain: {}", from_chain.0);
+
+        let order = self.out_orders.get(&order_id)
+            .unwrap_or_else(|| panic!("out order {} does not exist", hex::encode(order_id)));
+        assert_eq!(OrderStatus::Sent, order.status, "out order {} is not sent, status: {:?}", hex::encode(order_id), order.status);
+        assert_eq!(order.to_chain, to_chain, "unexpected to chain {} of out order {}", to_chain.0, hex::encode(order_id));
+
+        let (verify_promise, deposit) = self.verify_proof_data(receipt_proof);
+        if deposit > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(deposit);
+        }
+        verify_promise
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(FINISH_MARK_DELIVERED_GAS)
+                    .finish_mark_delivered(order_id)
+            )
+    }
+
+    /// Update the out order status once the proof was successfully validated. Can only be called by the contract
+    /// itself.
+    pub fn finish_mark_delivered(&mut self, order_id: CryptoHash) {
+        assert_self();
+        assert_eq!(env::promise_results_count(), 1, "ERR_TOO_MANY_RESULTS");
+        assert!(matches!(env::promise_result(0), PromiseResult::Successful(_)), "verify proof failed");
+
+        self.update_out_order_status(&order_id, OrderStatus::Delivered);
+        log!("out order {} is delivered", hex::encode(order_id));
+    }
+
+    pub fn get_out_order(&self, order_id: String) -> Option<OutOrder> {
+        let order_id = parse_order_id(&order_id);
+        self.out_orders.get(&order_id)
+    }
+
+    /// Return the out orders with their order ids, from the from_index-th order.
+    pub fn get_out_orders(&self, from_index: u64, limit: u64) -> Vec<(String, OutOrder)> {
+        self.out_orders.iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|(order_id, order)| (hex::encode(order_id), order))
+            .collect()
+    }
+
+    /// Return the out orders of sender with their order ids, from the from_index-th order of the sender.
+    pub fn get_out_orders_by_sender(&self, sender: String, from_index: u64, limit: u64) -> Vec<(String, OutOrder)> {
+        let order_ids = match self.sender_out_orders.get(&sender) {
+            Some(order_ids) => order_ids,
+            None => return vec![],
+        };
+        (from_index..std::cmp::min(from_index + limit, order_ids.len()))
+            .map(|i| {
+                let order_id = order_ids.get(i).unwrap();
+                (hex::encode(order_id), self.out_orders.get(&order_id).unwrap())
+            })
+            .collect()
     }
 
     #[payable]
@@ -556,7 +1760,10 @@ impl MapCrossChainService {
 
         if self.valid_mcs_token_out(&token, self.map_chain_id.into()) {
             self.check_to_account(to.clone(), self.map_chain_id);
-            self.check_amount(token.clone(), amount.0, false);
+            let (net_amount, fee) = self.charge_fee(&token, self.map_chain_id, amount.0);
+            self.check_amount(token.clone(), net_amount, false);
+            self.check_rate_limit(&token, Direction::Out, net_amount);
+            self.record_volume(&token, Direction::Out, net_amount);
             let from = env::signer_account_id().to_string();
             let order_id = self.get_order_id(&from, &to, self.map_chain_id);
 
@@ -567,15 +1774,17 @@ impl MapCrossChainService {
                 from_chain: self.near_chain_id.into(),
                 to_chain: self.map_chain_id.into(),
                 to,
-                amount,
+                amount: net_amount.into(),
             };
+            self.record_out_order(&event.order_id, &OutOrder::new(&event.token, &event.from, &event.to, event.to_chain, event.amount, OrderStatus::Pending));
+
             ext_mcs_token::ext(event.token.parse().unwrap())
                 .with_static_gas(BURN_GAS)
-                .burn(from.parse().unwrap(), event.amount)
+                .burn(from.parse().unwrap(), amount)
                 .then(
                     Self::ext(env::current_account_id())
                         .with_static_gas(FINISH_DEPOSIT_OUT_GAS)
-                        .finish_deposit_out(event)
+                        .finish_deposit_out(event, fee.into())
                 )
         } else if self.valid_fungible_token_out(&token, self.map_chain_id.into()) {
             env::panic_str(format!("non mcs fungible token {} should called from fungible token directly", token).as_ref());
@@ -591,7 +1800,10 @@ impl MapCrossChainService {
 
         let amount = env::attached_deposit();
         assert!(amount > 0, "amount should > 0");
-        self.check_amount("".to_string(), amount, true);
+        let (net_amount, fee) = self.charge_fee(&self.native_token_address().1, self.map_chain_id, amount);
+        self.check_amount("".to_string(), net_amount, true);
+        self.check_rate_limit(&self.native_token_address().1, Direction::Out, net_amount);
+        self.record_volume(&self.native_token_address().1, Direction::Out, net_amount);
 
         let from = env::signer_account_id().to_string();
 
@@ -604,8 +1816,9 @@ impl MapCrossChainService {
             from_chain: self.near_chain_id.into(),
             to_chain: self.map_chain_id.into(),
             order_id,
-            amount: amount.into(),
+            amount: net_amount.into(),
         };
+        self.record_out_order(&event.order_id, &OutOrder::new(&event.token, &event.from, &event.to, event.to_chain, event.amount, OrderStatus::Pending));
 
         ext_wnear_token::ext(self.wrapped_token.parse().unwrap())
             .with_static_gas(NEAR_DEPOSIT_GAS)
@@ -614,7 +1827,7 @@ impl MapCrossChainService {
             .then(
                 Self::ext(env::current_account_id())
                     .with_static_gas(FINISH_DEPOSIT_OUT_GAS)
-                    .finish_deposit_out(event)
+                    .finish_deposit_out(event, fee.into())
             )
     }
 
@@ -678,59 +1891,242 @@ impl MapCrossChainService {
         required_deposit
     }
 
-    /// Remove order id if transfer in failed.
-    fn remove_order_id(&mut self, order_id: &CryptoHash) -> Balance {
-        let initial_storage = env::storage_usage();
+    /// Remove order id if transfer in failed.
+    fn remove_order_id(&mut self, order_id: &CryptoHash) -> Balance {
+        let initial_storage = env::storage_usage();
+
+        if !self.used_events.contains(order_id) {
+            return 0;
+        }
+
+        self.used_events.remove_raw(order_id);
+        let current_storage = env::storage_usage();
+        Balance::from(initial_storage - current_storage) * env::storage_byte_cost()
+    }
+
+    /// Admin method to set metadata with admin/controller access
+    pub fn set_metadata(
+        &mut self,
+        address: String,
+        name: Option<String>,
+        symbol: Option<String>,
+        reference: Option<String>,
+        reference_hash: Option<Base64VecU8>,
+        decimals: Option<u8>,
+        icon: Option<String>,
+    ) -> Promise {
+        assert!(self.is_owner(), "unexpected caller {}", env::predecessor_account_id());
+        assert!(self.mcs_tokens.get(&address).is_some(), "token {} is not mcs token", address);
+
+        if let Some(value) = decimals {
+            self.token_decimals.insert(&address, &value);
+        }
+
+        ext_mcs_token::ext(address.parse().unwrap())
+            .with_static_gas(env::prepaid_gas() - OUTER_SET_METADATA_GAS)
+            .set_metadata(
+                name,
+                symbol,
+                reference,
+                reference_hash,
+                decimals,
+                icon,
+            )
+    }
+
+    fn check_amount(&self, token: String, amount: Balance, is_native: bool) {
+        let mut decimal = NEAR_DECIMAL;
+        if !is_native {
+            let decimal_op = self.token_decimals.get(&token);
+            assert!(decimal_op.is_some(), "the decimal of token {} is not set", token);
+            decimal = decimal_op.unwrap();
+        }
+
+        let min_amount = ((10 as u128).pow(decimal as _) as f64) * MIN_TRANSFER_OUT_AMOUNT;
+        assert!(amount >= min_amount as Balance, "amount too small, min amount for {} is {}", token, min_amount);
+    }
+
+    /// Return the net amount and fee of transferring amount of token to to_chain.
+    fn charge_fee(&self, token: &str, to_chain: u128, amount: Balance) -> (Balance, Balance) {
+        let fee = self.get_fee(token.to_string(), to_chain.into(), amount.into()).0;
+        assert!(amount > fee, "amount {} is not enough to pay fee {}", amount, fee);
+        (amount - fee, fee)
+    }
+
+    fn accrue_fee(&mut self, token: &String, fee: Balance) {
+        if fee == 0 {
+            return;
+        }
+        let accrued = self.accrued_fees.get(token).unwrap_or(0);
+        self.accrued_fees.insert(token, &(accrued + fee));
+    }
+
+    /// Return the fee of transferring amount of token to to_chain. For native token, the token is the wrap near
+    /// contract account id.
+    pub fn get_fee(&self, token: String, to_chain: U128, amount: U128) -> U128 {
+        let config = match self.fees.get(&(token, to_chain.into())) {
+            Some(config) => config,
+            None => return U128(0),
+        };
+        let rate = config.rate as u128;
+        let fee = amount.0 / FEE_RATE_DENOMINATOR * rate + amount.0 % FEE_RATE_DENOMINATOR * rate / FEE_RATE_DENOMINATOR;
+        fee.max(config.min.0).min(config.max.0).into()
+    }
+
+    pub fn set_fee(&mut self, token: String, to_chain: U128, fee: FeeConfig) {
+        assert!(self.is_owner(), "unexpected caller {}", env::predecessor_account_id());
+        assert!(fee.rate as u128 <= FEE_RATE_DENOMINATOR, "fee rate {} should not be greater than {}", fee.rate, FEE_RATE_DENOMINATOR);
+        assert!(fee.min.0 <= fee.max.0, "min fee {} should not be greater than max fee {}", fee.min.0, fee.max.0);
+
+        self.fees.insert(&(token, to_chain.into()), &fee);
+    }
+
+    pub fn remove_fee(&mut self, token: String, to_chain: U128) {
+        assert!(self.is_owner(), "unexpected caller {}", env::predecessor_account_id());
+
+        self.fees.remove(&(token, to_chain.into()));
+    }
+
+    pub fn get_fee_config(&self, token: String, to_chain: U128) -> Option<FeeConfig> {
+        self.fees.get(&(token, to_chain.into()))
+    }
+
+    pub fn set_fee_receiver(&mut self, fee_receiver: AccountId) {
+        assert!(self.is_owner(), "unexpected caller {}", env::predecessor_account_id());
+        self.fee_receiver = fee_receiver;
+    }
+
+    pub fn get_fee_receiver(&self) -> AccountId {
+        self.fee_receiver.clone()
+    }
+
+    /// Return the fee of token which is not withdrawn
+    pub fn get_accrued_fee(&self, token: String) -> U128 {
+        self.accrued_fees.get(&token).unwrap_or(0).into()
+    }
+
+    /// Withdraw the accrued fee of token to the fee receiver, all of it if amount is not specified. The mcs contract
+    /// pays the storage deposit for registering the fee receiver in the token, which is refunded to it if the fee
+    /// receiver is already registered.
+    pub fn withdraw_fee(&mut self, token: String, amount: Option<U128>) -> Promise {
+        assert!(self.is_owner(), "unexpected caller {}", env::predecessor_account_id());
+        let accrued = self.accrued_fees.get(&token).unwrap_or(0);
+        let amount = amount.map(|a| a.0).unwrap_or(accrued);
+        assert!(amount > 0 && amount <= accrued, "invalid amount {}, accrued fee of {} is {}", amount, token, accrued);
+        self.accrued_fees.insert(&token, &(accrued - amount));
+
+        let promise = if self.mcs_tokens.get(&token).is_some() {
+            ext_mcs_token::ext(token.parse().unwrap())
+                .with_static_gas(MINT_GAS)
+                .with_attached_deposit(self.mcs_storage_transfer_in_required)
+                .mint(self.fee_receiver.clone(), amount.into())
+        } else {
+            let token_account: AccountId = token.parse().unwrap();
+            ext_fungible_token::ext(token_account.clone())
+                .with_static_gas(STORAGE_DEPOSIT_GAS)
+                // the wrapped token for the native token fee is not in the fungible tokens
+                .with_attached_deposit(self.fungible_tokens_storage_balance.get(&token).unwrap_or(self.mcs_storage_transfer_in_required))
+                .storage_deposit(Some(self.fee_receiver.clone()), Some(true))
+                .then(ext_fungible_token::ext(token_account)
+                    .with_static_gas(FT_TRANSFER_GAS)
+                    .with_attached_deposit(1)
+                    .ft_transfer(self.fee_receiver.clone(), amount.into(), None))
+        };
+        promise.then(Self::ext(env::current_account_id())
+            .with_static_gas(FINISH_WITHDRAW_FEE_GAS)
+            .finish_withdraw_fee(token, amount.into()))
+    }
+
+    /// Restore the accrued fee if withdrawing fee failed.
+    pub fn finish_withdraw_fee(&mut self, token: String, amount: U128) {
+        assert_self();
+        assert_eq!(1, env::promise_results_count(), "ERR_TOO_MANY_RESULTS");
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            log!("withdraw fee {} of token {} to {}", amount.0, token, self.fee_receiver);
+        } else {
+            self.accrue_fee(&token, amount.0);
+            log!("withdraw fee {} of token {} failed", amount.0, token);
+        }
+    }
+
+    /// Return the bucket number of the current time for the rate limit window.
+    fn current_bucket(window: u64) -> u64 {
+        env::block_timestamp() / 1_000_000_000 / window.div_ceil(RATE_LIMIT_BUCKETS)
+    }
+
+    /// Return the amount of token transferred in the rate limit window.
+    fn window_volume(&self, token: &str, direction: Direction, window: u64) -> Balance {
+        self.volumes.get(&(token.to_string(), direction))
+            .map(|volume| volume.total(Self::current_bucket(window)))
+            .unwrap_or(0)
+    }
 
-        if !self.used_events.contains(order_id) {
-            return 0;
+    fn is_within_rate_limit(&self, token: &str, direction: Direction, amount: Balance) -> bool {
+        match self.rate_limits.get(&(token.to_string(), direction)) {
+            Some(rate_limit) => self.window_volume(token, direction, rate_limit.window) + amount <= rate_limit.limit.0,
+            None => true,
         }
+    }
 
-        self.used_events.remove_raw(order_id);
-        let current_storage = env::storage_usage();
-        Balance::from(initial_storage - current_storage) * env::storage_byte_cost()
+    fn check_rate_limit(&self, token: &str, direction: Direction, amount: Balance) {
+        assert!(self.is_within_rate_limit(token, direction, amount),
+                "transfer {} of token {} exceeds the rate limit", amount, token);
     }
 
-    /// Admin method to set metadata with admin/controller access
-    pub fn set_metadata(
-        &mut self,
-        address: String,
-        name: Option<String>,
-        symbol: Option<String>,
-        reference: Option<String>,
-        reference_hash: Option<Base64VecU8>,
-        decimals: Option<u8>,
-        icon: Option<String>,
-    ) -> Promise {
+    /// Record the transferred amount of token if its rate limit is set.
+    fn record_volume(&mut self, token: &str, direction: Direction, amount: Balance) {
+        let rate_limit = match self.rate_limits.get(&(token.to_string(), direction)) {
+            Some(rate_limit) => rate_limit,
+            None => return,
+        };
+        let key = (token.to_string(), direction);
+        let bucket = Self::current_bucket(rate_limit.window);
+        let mut volume = self.volumes.get(&key).unwrap_or_else(Volume::new);
+        volume.advance(bucket);
+        volume.amounts[(bucket % RATE_LIMIT_BUCKETS) as usize] += amount;
+        self.volumes.insert(&key, &volume);
+    }
+
+    /// Release the recorded amount of token if the transfer failed.
+    fn release_volume(&mut self, token: &str, direction: Direction, amount: Balance) {
+        let key = (token.to_string(), direction);
+        if let (Some(rate_limit), Some(mut volume)) = (self.rate_limits.get(&key), self.volumes.get(&key)) {
+            volume.release(Self::current_bucket(rate_limit.window), amount);
+            self.volumes.insert(&key, &volume);
+        }
+    }
+
+    pub fn set_rate_limit(&mut self, token: String, direction: Direction, rate_limit: RateLimit) {
         assert!(self.is_owner(), "unexpected caller {}", env::predecessor_account_id());
-        assert!(self.mcs_tokens.get(&address).is_some(), "token {} is not mcs token", address);
+        assert!(rate_limit.window > 0, "rate limit window should > 0");
 
-        if let Some(value) = decimals {
-            self.token_decimals.insert(&address, &value);
+        let key = (token, direction);
+        if let Some(old) = self.rate_limits.insert(&key, &rate_limit) {
+            // the buckets depend on the window length
+            if old.window != rate_limit.window {
+                self.volumes.remove(&key);
+            }
         }
+    }
 
-        ext_mcs_token::ext(address.parse().unwrap())
-            .with_static_gas(env::prepaid_gas() - OUTER_SET_METADATA_GAS)
-            .set_metadata(
-                name,
-                symbol,
-                reference,
-                reference_hash,
-                decimals,
-                icon,
-            )
+    pub fn remove_rate_limit(&mut self, token: String, direction: Direction) {
+        assert!(self.is_owner(), "unexpected caller {}", env::predecessor_account_id());
+
+        let key = (token, direction);
+        self.rate_limits.remove(&key);
+        self.volumes.remove(&key);
     }
 
-    fn check_amount(&self, token: String, amount: Balance, is_native: bool) {
-        let mut decimal = NEAR_DECIMAL;
-        if !is_native {
-            let decimal_op = self.token_decimals.get(&token);
-            assert!(decimal_op.is_some(), "the decimal of token {} is not set", token);
-            decimal = decimal_op.unwrap();
-        }
+    pub fn get_rate_limit(&self, token: String, direction: Direction) -> Option<RateLimit> {
+        self.rate_limits.get(&(token, direction))
+    }
 
-        let min_amount = ((10 as u128).pow(decimal as _) as f64) * MIN_TRANSFER_OUT_AMOUNT;
-        assert!(amount >= min_amount as Balance, "amount too small, min amount for {} is {}", token, min_amount);
+    /// Return the amount of token transferred in the current rate limit window, 0 if no rate limit is set.
+    pub fn get_volume(&self, token: String, direction: Direction) -> U128 {
+        match self.rate_limits.get(&(token.clone(), direction)) {
+            Some(rate_limit) => self.window_volume(&token, direction, rate_limit.window).into(),
+            None => U128(0),
+        }
     }
 
     pub fn owner(&self) -> AccountId {
@@ -949,7 +2345,20 @@ impl MapCrossChainService {
             EvmChain => {
                 assert_eq!(20, to.len(), "address length is incorrect for evm chain type")
             }
-            _ => {
+            NearChain => {
+                let account = String::from_utf8(to).ok().and_then(|account| account.parse::<AccountId>().ok());
+                assert!(account.is_some(), "invalid account id for near chain type")
+            }
+            SolanaChain => {
+                assert_eq!(32, to.len(), "address length is incorrect for solana chain type")
+            }
+            AptosChain => {
+                assert_eq!(32, to.len(), "address length is incorrect for aptos chain type")
+            }
+            BitcoinChain => {
+                assert!(is_valid_bitcoin_address(&to, self.bitcoin_network), "invalid address for bitcoin chain type")
+            }
+            Unknown => {
                 panic_str(&*format!("unknown chain type for chain {}", chain_id))
             }
         }
@@ -966,8 +2375,8 @@ impl MapCrossChainService {
 
     pub fn set_map_light_client(&mut self, map_client_account: AccountId) {
         assert!(self.is_owner(), "unexpected caller {}", env::predecessor_account_id());
-        assert!(self.is_paused(PAUSE_TRANSFER_IN),
-                "transfer in should be paused when setting map light client account");
+        assert!(self.is_paused(PAUSE_TRANSFER_IN) && self.is_paused(PAUSE_MESSAGE_IN),
+                "transfer in and message in should be paused when setting map light client account");
 
         self.map_client_account = map_client_account;
     }
@@ -976,6 +2385,25 @@ impl MapCrossChainService {
         self.map_client_account.clone()
     }
 
+    /// Set the verification fee to attach when verifying proofs, should be the same as the map light client's.
+    pub fn set_map_client_verification_fee(&mut self, fee: U128) {
+        assert!(self.is_owner(), "unexpected caller {}", env::predecessor_account_id());
+        self.map_client_verification_fee = fee.0;
+    }
+
+    pub fn get_map_client_verification_fee(&self) -> U128 {
+        self.map_client_verification_fee.into()
+    }
+
+    pub fn set_bitcoin_network(&mut self, bitcoin_network: BitcoinNetwork) {
+        assert!(self.is_owner(), "unexpected caller {}", env::predecessor_account_id());
+        self.bitcoin_network = bitcoin_network;
+    }
+
+    pub fn get_bitcoin_network(&self) -> BitcoinNetwork {
+        self.bitcoin_network
+    }
+
     pub fn set_near_chain_id(&mut self, near_chain_id: U128) {
         assert!(self.is_owner(), "unexpected caller {}", env::predecessor_account_id());
         assert!(self.is_paused(PAUSE_TRANSFER_OUT_TOKEN)
@@ -1006,8 +2434,8 @@ impl MapCrossChainService {
 
     pub fn set_map_relay_address(&mut self, map_relay_address: String) {
         assert!(self.is_owner(), "unexpected caller {}", env::predecessor_account_id());
-        assert!(self.is_paused(PAUSE_TRANSFER_IN),
-                "transfer in should be paused when setting near chain id");
+        assert!(self.is_paused(PAUSE_TRANSFER_IN) && self.is_paused(PAUSE_MESSAGE_IN),
+                "transfer in and message in should be paused when setting near chain id");
 
         self.map_bridge_address = validate_eth_address(map_relay_address);
     }
@@ -1023,7 +2451,8 @@ impl MapCrossChainService {
                     && self.is_paused(PAUSE_TRANSFER_OUT_TOKEN)
                     && self.is_paused(PAUSE_TRANSFER_OUT_NATIVE)
                     && self.is_paused(PAUSE_DEPOSIT_OUT_TOKEN)
-                    && self.is_paused(PAUSE_DEPOSIT_OUT_NATIVE),
+                    && self.is_paused(PAUSE_DEPOSIT_OUT_NATIVE)
+                    && self.is_paused(PAUSE_MESSAGE_IN),
                 "everything should be paused when upgrading mcs contract");
 
         let current_id = env::current_account_id();
@@ -1051,7 +2480,11 @@ impl FungibleTokenReceiver for MapCrossChainService {
             assert!(self.valid_fungible_token_out(&token, transfer_msg.to_chain),
                     "transfer token {} to chain {} is not supported", token, transfer_msg.to_chain.0);
             self.check_to_account(transfer_msg.to.clone(), transfer_msg.to_chain.into());
-            self.check_amount(token.clone(), amount.0, false);
+            let (net_amount, fee) = self.charge_fee(&token, transfer_msg.to_chain.into(), amount.0);
+            self.check_amount(token.clone(), net_amount, false);
+            self.check_rate_limit(&token, Direction::Out, net_amount);
+            self.accrue_fee(&token, fee);
+            self.record_volume(&token, Direction::Out, net_amount);
 
             let order_id = self.get_order_id(&from, &transfer_msg.to, transfer_msg.to_chain.into());
             let event = TransferOutEvent {
@@ -1062,8 +2495,9 @@ impl FungibleTokenReceiver for MapCrossChainService {
                 order_id,
                 token,
                 to_chain_token: "".to_string(),
-                amount,
+                amount: net_amount.into(),
             };
+            self.record_out_order(&event.order_id, &OutOrder::new(&event.token, &event.from, &event.to, event.to_chain, event.amount, OrderStatus::Sent));
             log!("transfer out: {}", serde_json::to_string(&event).unwrap());
             log!("{}{}", TRANSFER_OUT_TYPE, event);
         } else if transfer_msg.msg_type == 1 {
@@ -1071,7 +2505,11 @@ impl FungibleTokenReceiver for MapCrossChainService {
             assert!(self.valid_fungible_token_out(&token, self.map_chain_id.into()),
                     "deposit token {} to chain {} is not supported", token, self.map_chain_id);
             self.check_to_account(transfer_msg.to.clone(), self.map_chain_id);
-            self.check_amount(token.clone(), amount.0, false);
+            let (net_amount, fee) = self.charge_fee(&token, self.map_chain_id, amount.0);
+            self.check_amount(token.clone(), net_amount, false);
+            self.check_rate_limit(&token, Direction::Out, net_amount);
+            self.accrue_fee(&token, fee);
+            self.record_volume(&token, Direction::Out, net_amount);
 
             let order_id = self.get_order_id(&from, &transfer_msg.to, self.map_chain_id);
             let event = DepositOutEvent {
@@ -1081,8 +2519,9 @@ impl FungibleTokenReceiver for MapCrossChainService {
                 from_chain: self.near_chain_id.into(),
                 to_chain: self.map_chain_id.into(),
                 token,
-                amount,
+                amount: net_amount.into(),
             };
+            self.record_out_order(&event.order_id, &OutOrder::new(&event.token, &event.from, &event.to, event.to_chain, event.amount, OrderStatus::Sent));
             log!("deposit out: {}", serde_json::to_string(&event).unwrap());
             log!("{}{}", DEPOSIT_OUT_TYPE, event);
         } else {
@@ -1099,7 +2538,9 @@ admin_controlled::impl_admin_controlled!(MapCrossChainService, paused);
 #[cfg(test)]
 mod tests {
     use super::*;
-    use near_sdk::{test_utils::VMContextBuilder, testing_env, env::sha256};
+    use near_sdk::{test_utils::VMContextBuilder, testing_env, env::sha256, RuntimeFeesConfig, VMConfig};
+    use near_sdk::test_utils::get_created_receipts;
+    use near_sdk::mock::VmAction;
     use std::convert::TryInto;
     use near_sdk::json_types::U64;
     use uint::rustc_hex::ToHex;
@@ -1211,6 +2652,21 @@ mod tests {
             map_chain_id: 0,
             nonce: 0,
             paused: Mask::default(),
+            failed_messages: UnorderedMap::new(b"m".to_vec()),
+            fees: UnorderedMap::new(b"e".to_vec()),
+            fee_receiver: env::signer_account_id(),
+            accrued_fees: UnorderedMap::new(b"a".to_vec()),
+            rate_limits: UnorderedMap::new(b"l".to_vec()),
+            volumes: UnorderedMap::new(b"v".to_vec()),
+            pending_transfers_in: UnorderedMap::new(b"p".to_vec()),
+            out_orders: UnorderedMap::new(b"o".to_vec()),
+            sender_out_orders: LookupMap::new(b"n".to_vec()),
+            order_storage_balance: 0,
+            claimables: LookupMap::new(b"k".to_vec()),
+            recipient_claimables: LookupMap::new(b"r".to_vec()),
+            recovery_account: None,
+            map_client_verification_fee: 0,
+            bitcoin_network: BitcoinNetwork::Mainnet,
         }
     }
 
@@ -1236,6 +2692,630 @@ mod tests {
         contract.transfer_in(sample_proof(), 0);
     }
 
+    #[test]
+    #[should_panic(expected = "attached deposit 100 is less than the verification fee 1000")]
+    fn test_fail_transfer_in_verification_fee() {
+        let mut contract = mcs_contract();
+        contract.set_map_client_verification_fee(U128(1000));
+        set_env!(
+            predecessor_account_id: alice().0,
+            attached_deposit: 100,
+            prepaid_gas: Gas(300_000_000_000_000),
+        );
+        contract.transfer_in(transfer_in_proof(vec![[1; 32]]), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "not map message out event")]
+    fn test_fail_message_in_no_event() {
+        let mut contract = mcs_contract();
+        set_env!(
+            predecessor_account_id: alice().0,
+            attached_deposit: env::storage_byte_cost() * 1000
+        );
+        let mut proof = sample_proof();
+        proof.receipt.logs.push(MapTransferOutEvent {
+            map_bridge_address: validate_eth_address(map_bridge_address()),
+            from_chain: U128(ETH_CHAIN_ID),
+            to_chain: U128(NEAR_CHAIN_ID),
+            order_id: [1; 32],
+            token: alice().1,
+            from: alice().1,
+            to: alice().0.as_bytes().to_vec(),
+            amount: U128(100),
+            to_chain_token: wrap_token().into_bytes(),
+            message: vec![],
+        }.to_log_entry_data());
+        contract.message_in(proof, 0);
+    }
+
+    fn message_event(order_id: CryptoHash) -> MapMessageOutEvent {
+        MapMessageOutEvent {
+            map_bridge_address: validate_eth_address(map_bridge_address()),
+            from_chain: U128(ETH_CHAIN_ID),
+            to_chain: U128(NEAR_CHAIN_ID),
+            order_id,
+            from: alice().1,
+            to: alice().0.as_bytes().to_vec(),
+            payload: vec![1, 2, 3],
+            gas_limit: U128(10_000_000_000_000),
+        }
+    }
+
+    #[test]
+    fn test_retry_message() {
+        let mut contract = mcs_contract();
+        for i in 0..3 {
+            contract.failed_messages.insert(&[i; 32], &message_event([i; 32]));
+        }
+        assert_eq!(3, contract.get_failed_messages(0, 10).len());
+        assert_eq!(vec![message_event([1; 32])], contract.get_failed_messages(1, 1));
+
+        contract.retry_message(hex::encode([1; 32]));
+        assert_eq!(vec![message_event([0; 32]), message_event([2; 32])], contract.get_failed_messages(0, 10));
+
+        testing_env!(
+            VMContextBuilder::new().predecessor_account_id(map_cross_chain_service()).current_account_id(map_cross_chain_service()).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        contract.finish_retry_message(&message_event([1; 32]), U128(0));
+        assert_eq!(3, contract.get_failed_messages(0, 10).len());
+
+        contract.owner = map_cross_chain_service();
+        contract.remove_failed_message(hex::encode([1; 32]));
+        assert_eq!(2, contract.get_failed_messages(0, 10).len());
+    }
+
+    #[test]
+    #[should_panic(expected = "current function is paused")]
+    fn test_fail_message_in_paused() {
+        let mut contract = mcs_contract();
+        contract.paused = PAUSE_MESSAGE_IN;
+        set_env!(
+            predecessor_account_id: alice().0,
+            attached_deposit: env::storage_byte_cost() * 1000
+        );
+        contract.message_in(sample_proof(), 0);
+    }
+
+    #[test]
+    fn test_get_fee() {
+        let mut contract = mcs_contract();
+        let token = wrap_token();
+        assert_eq!(0, contract.get_fee(token.clone(), ETH_CHAIN_ID.into(), U128(1_000_000)).0);
+
+        contract.set_fee(token.clone(), ETH_CHAIN_ID.into(), FeeConfig {
+            rate: 30,
+            min: U128(100),
+            max: U128(5_000),
+        });
+        assert_eq!(3_000, contract.get_fee(token.clone(), ETH_CHAIN_ID.into(), U128(1_000_000)).0);
+        assert_eq!(100, contract.get_fee(token.clone(), ETH_CHAIN_ID.into(), U128(1_000)).0);
+        assert_eq!(5_000, contract.get_fee(token.clone(), ETH_CHAIN_ID.into(), U128(10_000_000)).0);
+        assert_eq!(0, contract.get_fee(token.clone(), NEAR_CHAIN_ID.into(), U128(1_000_000)).0);
+
+        contract.set_fee(token.clone(), NEAR_CHAIN_ID.into(), FeeConfig {
+            rate: 10000,
+            min: U128(0),
+            max: U128(u128::MAX),
+        });
+        assert_eq!(u128::MAX, contract.get_fee(token.clone(), NEAR_CHAIN_ID.into(), U128(u128::MAX)).0);
+
+        let (net, fee) = contract.charge_fee(&token, ETH_CHAIN_ID, 1_000_000);
+        assert_eq!((997_000, 3_000), (net, fee));
+        contract.accrue_fee(&token, fee);
+        contract.accrue_fee(&token, fee);
+        assert_eq!(6_000, contract.get_accrued_fee(token).0);
+    }
+
+    #[test]
+    fn test_withdraw_fee() {
+        let mut contract = mcs_contract();
+        contract.accrue_fee(&wrap_token(), 100);
+
+        contract.withdraw_fee(wrap_token(), Some(U128(40)));
+        assert_eq!(U128(60), contract.get_accrued_fee(wrap_token()));
+
+        set_promise_result(PromiseResult::Failed);
+        contract.finish_withdraw_fee(wrap_token(), U128(40));
+        assert_eq!(U128(100), contract.get_accrued_fee(wrap_token()));
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected caller")]
+    fn test_set_fee_not_owner() {
+        let mut contract = mcs_contract();
+        set_env!(predecessor_account_id: alice().0);
+        contract.set_fee(wrap_token(), ETH_CHAIN_ID.into(), FeeConfig {
+            rate: 30,
+            min: U128(0),
+            max: U128(5_000),
+        });
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let mut contract = mcs_contract();
+        let token = wrap_token();
+        contract.record_volume(&token, Direction::In, 100);
+        assert_eq!(0, contract.get_volume(token.clone(), Direction::In).0);
+        assert!(contract.is_within_rate_limit(&token, Direction::In, u128::MAX));
+
+        contract.set_rate_limit(token.clone(), Direction::In, RateLimit {
+            limit: U128(1_000),
+            window: 3600,
+        });
+        contract.record_volume(&token, Direction::In, 600);
+        assert_eq!(600, contract.get_volume(token.clone(), Direction::In).0);
+        assert_eq!(0, contract.get_volume(token.clone(), Direction::Out).0);
+        assert!(contract.is_within_rate_limit(&token, Direction::In, 400));
+        assert!(!contract.is_within_rate_limit(&token, Direction::In, 401));
+
+        set_env!(block_timestamp: 1800 * 1_000_000_000u64);
+        contract.record_volume(&token, Direction::In, 300);
+        assert_eq!(900, contract.get_volume(token.clone(), Direction::In).0);
+
+        set_env!(block_timestamp: 3600 * 1_000_000_000u64);
+        assert_eq!(300, contract.get_volume(token.clone(), Direction::In).0);
+        assert!(contract.is_within_rate_limit(&token, Direction::In, 700));
+        contract.record_volume(&token, Direction::In, 100);
+        assert_eq!(400, contract.get_volume(token.clone(), Direction::In).0);
+        assert_eq!(RATE_LIMIT_BUCKETS as usize, contract.volumes.get(&(token.clone(), Direction::In)).unwrap().amounts.len());
+
+        set_env!(block_timestamp: 100_000 * 1_000_000_000u64);
+        assert_eq!(0, contract.get_volume(token.clone(), Direction::In).0);
+        contract.record_volume(&token, Direction::In, 200);
+        contract.release_volume(&token, Direction::In, 50);
+        assert_eq!(150, contract.get_volume(token.clone(), Direction::In).0);
+
+        contract.remove_rate_limit(token.clone(), Direction::In);
+        assert!(contract.volumes.get(&(token, Direction::In)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds the rate limit")]
+    fn test_transfer_out_native_exceeds_rate_limit() {
+        let mut contract = mcs_contract();
+        contract.add_native_to_chain(ETH_CHAIN_ID.into());
+        contract.set_chain_type(ETH_CHAIN_ID.into(), EvmChain);
+        contract.set_rate_limit(wrap_token(), Direction::Out, RateLimit {
+            limit: U128(10_u128.pow(24)),
+            window: 3600,
+        });
+        set_env!(attached_deposit: 2 * 10_u128.pow(24));
+        contract.transfer_out_native(alice().1, ETH_CHAIN_ID.into());
+    }
+
+    #[test]
+    fn test_transfer_out_native_reserves_rate_limit() {
+        let mut contract = mcs_contract();
+        contract.add_native_to_chain(ETH_CHAIN_ID.into());
+        contract.set_chain_type(ETH_CHAIN_ID.into(), EvmChain);
+        contract.set_rate_limit(wrap_token(), Direction::Out, RateLimit {
+            limit: U128(10_u128.pow(24)),
+            window: 3600,
+        });
+        set_env!(attached_deposit: env::storage_byte_cost() * 1000);
+        contract.fund_order_storage();
+        set_env!(attached_deposit: 10_u128.pow(24));
+        contract.transfer_out_native(alice().1, ETH_CHAIN_ID.into());
+        assert_eq!(10_u128.pow(24), contract.get_volume(wrap_token(), Direction::Out).0);
+        assert!(!contract.is_within_rate_limit(&wrap_token(), Direction::Out, 1));
+        let (order_id, order) = contract.get_out_orders(0, 1).pop().unwrap();
+        assert_eq!(OrderStatus::Pending, order.status);
+
+        set_promise_result(PromiseResult::Failed);
+        contract.finish_transfer_out(TransferOutEvent {
+            from_chain: NEAR_CHAIN_ID.into(),
+            to_chain: ETH_CHAIN_ID.into(),
+            from: order.from,
+            to: order.to,
+            order_id: parse_order_id(&order_id),
+            token: wrap_token(),
+            to_chain_token: "".to_string(),
+            amount: 10_u128.pow(24).into(),
+        }, U128(0));
+        assert_eq!(0, contract.get_volume(wrap_token(), Direction::Out).0);
+        assert_eq!(OrderStatus::Failed, contract.get_out_order(order_id).unwrap().status);
+    }
+
+    fn transfer_in_proof(order_ids: Vec<CryptoHash>) -> ReceiptProof {
+        let mut proof = sample_proof();
+        for order_id in order_ids {
+            proof.receipt.logs.push(MapTransferOutEvent {
+                map_bridge_address: validate_eth_address(map_bridge_address()),
+                from_chain: U128(ETH_CHAIN_ID),
+                to_chain: U128(NEAR_CHAIN_ID),
+                order_id,
+                token: alice().1,
+                from: alice().1,
+                to: alice().0.as_bytes().to_vec(),
+                amount: U128(100),
+                to_chain_token: wrap_token().into_bytes(),
+                message: vec![],
+            }.to_log_entry_data());
+        }
+        proof.header.bloom = map_light_client::bloom::logs_bloom(&proof.receipt.logs);
+        proof
+    }
+
+    #[test]
+    fn test_transfer_in_batch() {
+        let mut contract = mcs_contract();
+        set_env!(
+            predecessor_account_id: alice().0,
+            attached_deposit: env::storage_byte_cost() * 1000,
+            prepaid_gas: Gas(300_000_000_000_000),
+        );
+        contract.transfer_in_batch(transfer_in_proof(vec![[1; 32], [2; 32], [3; 32], [4; 32]]), vec![0, 1, 2, 3]);
+
+        // the proof is verified once and the whole deposit is attached to processing the events
+        let receipts = get_created_receipts();
+        assert_eq!(Some(&0), function_call_deposits(&receipts, "verify_proof_data").first());
+        assert_eq!(vec![env::storage_byte_cost() * 1000], function_call_deposits(&receipts, "finish_verify_proof_batch"));
+    }
+
+    #[test]
+    fn test_finish_verify_proof_batch() {
+        let mut contract = mcs_contract();
+        let token = format!("{}.{}", mcs_token().0, map_cross_chain_service());
+        contract.mcs_tokens.insert(&token, &Default::default());
+        contract.mcs_storage_transfer_in_required = env::storage_byte_cost() * 10000;
+        let proof = transfer_in_proof(vec![[1; 32], [2; 32]]);
+        let mut mcs_event = MapTransferOutEvent::from_log_entry_data(&proof.receipt.logs[0]).unwrap();
+        mcs_event.to_chain_token = token.into_bytes();
+        let native_event = MapTransferOutEvent::from_log_entry_data(&proof.receipt.logs[1]).unwrap();
+
+        let mcs_deposit = contract.transfer_in_required_deposit(&mcs_event);
+        let native_deposit = contract.transfer_in_required_deposit(&native_event);
+        assert!(mcs_deposit > native_deposit + contract.mcs_storage_transfer_in_required / 2);
+        let order_id_deposit = contract.record_order_id(&[3; 32]);
+        contract.remove_order_id(&[3; 32]);
+
+        let extra_deposit = env::storage_byte_cost() * 100;
+        testing_env!(
+            VMContextBuilder::new()
+                .predecessor_account_id(map_cross_chain_service())
+                .current_account_id(map_cross_chain_service())
+                .attached_deposit(mcs_deposit + native_deposit + extra_deposit)
+                .build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])],
+        );
+        contract.finish_verify_proof_batch(vec![mcs_event, native_event]);
+        assert!(contract.is_used_event(&[1; 32]));
+        assert!(contract.is_used_event(&[2; 32]));
+        // neither event is queued for lack of deposit
+        assert!(contract.get_pending_transfers_in(0, 10).is_empty());
+
+        // each event gets the deposit it requires, and the deposit left goes to the last event
+        let receipts = get_created_receipts();
+        let mint_deposit = function_call_deposits(&receipts, "mint");
+        assert_eq!(vec![contract.mcs_storage_transfer_in_required], mint_deposit);
+        assert_eq!(
+            vec![mcs_deposit - order_id_deposit - mint_deposit[0]],
+            function_call_deposits(&receipts, "finish_transfer_in")
+        );
+        assert_eq!(vec![1], function_call_deposits(&receipts, "near_withdraw"));
+        assert_eq!(
+            vec![native_deposit + extra_deposit - order_id_deposit - 1],
+            function_call_deposits(&receipts, "transfer_in_native_token")
+        );
+    }
+
+    fn function_call_deposits(receipts: &[near_sdk::mock::Receipt], method: &str) -> Vec<Balance> {
+        receipts.iter()
+            .flat_map(|receipt| receipt.actions.iter())
+            .filter_map(|action| match action {
+                VmAction::FunctionCall { function_name, deposit, .. } if function_name == method => Some(*deposit),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    #[should_panic(expected = "is duplicated")]
+    fn test_fail_transfer_in_batch_duplicated_event() {
+        let mut contract = mcs_contract();
+        set_env!(
+            predecessor_account_id: alice().0,
+            attached_deposit: env::storage_byte_cost() * 1000,
+            prepaid_gas: Gas(300_000_000_000_000),
+        );
+        contract.transfer_in_batch(transfer_in_proof(vec![[1; 32], [2; 32]]), vec![0, 1, 0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "not enough gas for 5 events")]
+    fn test_fail_transfer_in_batch_not_enough_gas() {
+        let mut contract = mcs_contract();
+        set_env!(
+            predecessor_account_id: alice().0,
+            attached_deposit: env::storage_byte_cost() * 1000,
+            prepaid_gas: Gas(300_000_000_000_000),
+        );
+        contract.transfer_in_batch(transfer_in_proof(vec![[1; 32], [2; 32], [3; 32], [4; 32], [5; 32]]), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_out_orders() {
+        let mut contract = mcs_contract();
+        let from = alice().0.to_string();
+        set_env!(attached_deposit: env::storage_byte_cost() * 2000);
+        contract.fund_order_storage();
+        for i in 0..3 {
+            contract.record_out_order(&[i; 32], &OutOrder::new(&wrap_token(), &from, &alice().1, ETH_CHAIN_ID.into(), U128(100), OrderStatus::Sent));
+        }
+        contract.record_out_order(&[3; 32], &OutOrder::new(&wrap_token(), "bob.near", &alice().1, ETH_CHAIN_ID.into(), U128(100), OrderStatus::Sent));
+        assert!(contract.get_order_storage_balance().0 < env::storage_byte_cost() * 2000);
+
+        let order = contract.get_out_order(hex::encode([1; 32])).unwrap();
+        assert_eq!(OrderStatus::Sent, order.status);
+        assert_eq!(from, order.from);
+        assert!(contract.get_out_order(hex::encode([4; 32])).is_none());
+
+        assert_eq!(4, contract.get_out_orders(0, 10).len());
+        assert_eq!(1, contract.get_out_orders(3, 10).len());
+
+        let orders = contract.get_out_orders_by_sender(from.clone(), 1, 5);
+        assert_eq!(vec![hex::encode([1; 32]), hex::encode([2; 32])], orders.into_iter().map(|(id, _)| id).collect::<Vec<_>>());
+        assert_eq!(0, contract.get_out_orders_by_sender(from, 3, 5).len());
+        assert_eq!(1, contract.get_out_orders_by_sender("bob.near".to_string(), 0, 5).len());
+    }
+
+    #[test]
+    fn test_record_out_order_no_storage_balance() {
+        let mut contract = mcs_contract();
+        contract.record_out_order(&[1; 32], &OutOrder::new(&wrap_token(), alice().0.as_str(), &alice().1, ETH_CHAIN_ID.into(), U128(100), OrderStatus::Pending));
+        assert!(contract.get_out_order(hex::encode([1; 32])).is_none());
+        assert!(contract.get_out_orders_by_sender(alice().0.to_string(), 0, 10).is_empty());
+        contract.update_out_order_status(&[1; 32], OrderStatus::Sent);
+
+        set_env!(attached_deposit: env::storage_byte_cost() * 1000);
+        contract.fund_order_storage();
+        contract.record_out_order(&[1; 32], &OutOrder::new(&wrap_token(), alice().0.as_str(), &alice().1, ETH_CHAIN_ID.into(), U128(100), OrderStatus::Pending));
+        assert!(contract.get_out_order(hex::encode([1; 32])).is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "does not exist")]
+    fn test_fail_mark_delivered_no_order() {
+        let mut contract = mcs_contract();
+        contract.mark_delivered(transfer_relay_proof([1; 32], ETH_CHAIN_ID), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected to chain")]
+    fn test_fail_mark_delivered_to_chain() {
+        let mut contract = mcs_contract();
+        set_env!(attached_deposit: env::storage_byte_cost() * 1000);
+        contract.fund_order_storage();
+        contract.record_out_order(&[1; 32], &OutOrder::new(&wrap_token(), alice().0.as_str(), &alice().1, ETH_CHAIN_ID.into(), U128(100), OrderStatus::Sent));
+        contract.mark_delivered(transfer_relay_proof([1; 32], 56), 0);
+    }
+
+    #[test]
+    fn test_mark_delivered() {
+        let mut contract = mcs_contract();
+        set_env!(attached_deposit: env::storage_byte_cost() * 1000);
+        contract.fund_order_storage();
+        contract.record_out_order(&[1; 32], &OutOrder::new(&wrap_token(), alice().0.as_str(), &alice().1, ETH_CHAIN_ID.into(), U128(100), OrderStatus::Sent));
+        contract.record_out_order(&[2; 32], &OutOrder::new(&wrap_token(), alice().0.as_str(), &alice().1, ETH_CHAIN_ID.into(), U128(100), OrderStatus::Sent));
+
+        contract.mark_delivered(transfer_relay_proof([1; 32], ETH_CHAIN_ID), 0);
+        let receipts = get_created_receipts();
+        assert_eq!(1, function_call_deposits(&receipts, "verify_proof_data").len());
+        assert_eq!(1, function_call_deposits(&receipts, "finish_mark_delivered").len());
+        set_promise_result(PromiseResult::Successful(vec![]));
+        contract.finish_mark_delivered([1; 32]);
+        assert_eq!(OrderStatus::Delivered, contract.get_out_order(hex::encode([1; 32])).unwrap().status);
+        assert_eq!(OrderStatus::Sent, contract.get_out_order(hex::encode([2; 32])).unwrap().status);
+    }
+
+    fn transfer_relay_proof(order_id: CryptoHash, to_chain: u128) -> ReceiptProof {
+        let mut proof = sample_proof();
+        proof.receipt.logs.push(MapTransferRelayEvent {
+            map_bridge_address: validate_eth_address(map_bridge_address()),
+            from_chain: U128(NEAR_CHAIN_ID),
+            to_chain: U128(to_chain),
+            order_id,
+            token: validate_eth_address(map_bridge_address()),
+            from: alice().0.as_bytes().to_vec(),
+            to: alice().1,
+            amount: U128(100),
+        }.to_log_entry_data());
+        proof.header.bloom = map_light_client::bloom::logs_bloom(&proof.receipt.logs);
+        proof
+    }
+
+    #[test]
+    fn test_retry_transfer_in() {
+        let mut contract = mcs_contract();
+        let token = format!("{}.{}", mcs_token().0, map_cross_chain_service());
+        contract.mcs_tokens.insert(&token, &Default::default());
+        contract.mcs_storage_transfer_in_required = env::storage_byte_cost() * 10000;
+        let proof = transfer_in_proof(vec![[1; 32]]);
+        let mut event = MapTransferOutEvent::from_log_entry_data(&proof.receipt.logs[0]).unwrap();
+        event.to_chain_token = token.into_bytes();
+
+        contract.process_transfer_in(&event, env::storage_byte_cost() * 1000);
+        assert!(contract.is_used_event(&[1; 32]));
+        assert_eq!(vec![event], contract.get_pending_transfers_in(0, 10));
+        assert!(contract.get_pending_transfers_in(1, 10).is_empty());
+
+        set_env!(predecessor_account_id: map_cross_chain_service(), attached_deposit: env::storage_byte_cost() * 11000);
+        contract.retry_transfer_in(hex::encode([1; 32]));
+        assert!(contract.get_pending_transfers_in(0, 10).is_empty());
+    }
+
+    fn set_promise_result(result: PromiseResult) {
+        testing_env!(
+            VMContextBuilder::new()
+                .predecessor_account_id(map_cross_chain_service())
+                .current_account_id(map_cross_chain_service())
+                .attached_deposit(env::storage_byte_cost() * 1000)
+                .build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result],
+        );
+    }
+
+    #[test]
+    fn test_transfer_in_call() {
+        let mut contract = mcs_contract();
+        let token = format!("{}.{}", mcs_token().0, map_cross_chain_service());
+        contract.mcs_tokens.insert(&token, &Default::default());
+        let proof = transfer_in_proof(vec![[1; 32]]);
+        let mut event = MapTransferOutEvent::from_log_entry_data(&proof.receipt.logs[0]).unwrap();
+        event.to_chain_token = token.into_bytes();
+        event.message = b"msg".to_vec();
+
+        contract.process_transfer_in(&event, env::storage_byte_cost() * 1000);
+        assert!(contract.is_used_event(&[1; 32]));
+        assert!(contract.get_pending_transfers_in(0, 10).is_empty());
+
+        // the event is queued for retry if minting failed
+        set_promise_result(PromiseResult::Failed);
+        contract.finish_mint_transfer_in_call(&event);
+        assert_eq!(vec![event], contract.get_pending_transfers_in(0, 10));
+        assert!(contract.is_used_event(&[1; 32]));
+    }
+
+    #[test]
+    fn test_transfer_in_call_failed() {
+        let mut contract = mcs_contract();
+        let token = format!("{}.{}", mcs_token().0, map_cross_chain_service());
+        contract.mcs_tokens.insert(&token, &Default::default());
+        let proof = transfer_in_proof(vec![[1; 32]]);
+        let mut event = MapTransferOutEvent::from_log_entry_data(&proof.receipt.logs[0]).unwrap();
+        event.to_chain_token = token.clone().into_bytes();
+        event.message = b"msg".to_vec();
+        contract.record_order_id(&[1; 32]);
+
+        // the minted token is claimable by the receiver if ft_transfer_call failed
+        set_promise_result(PromiseResult::Failed);
+        contract.finish_transfer_in_call(&event);
+        assert!(contract.is_used_event(&[1; 32]));
+        let claimables = contract.get_claimables(alice().0.to_string(), 0, 10);
+        assert_eq!(vec![(hex::encode([1; 32]), Claimable { token, amount: U128(100) })], claimables);
+    }
+
+    #[test]
+    fn test_claim() {
+        let mut contract = mcs_contract();
+        let proof = transfer_in_proof(vec![[1; 32]]);
+        let event = MapTransferOutEvent::from_log_entry_data(&proof.receipt.logs[0]).unwrap();
+        contract.record_claimable(&event);
+        let claimables = contract.get_claimables(alice().0.to_string(), 0, 10);
+        assert_eq!(vec![(hex::encode([1; 32]), Claimable { token: wrap_token(), amount: U128(100) })], claimables);
+
+        set_env!(predecessor_account_id: alice().0, attached_deposit: env::storage_byte_cost() * 1000);
+        contract.claim(alice().0.to_string(), hex::encode([1; 32]));
+        assert!(contract.get_claimables(alice().0.to_string(), 0, 10).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected caller")]
+    fn test_fail_claim_not_recipient() {
+        let mut contract = mcs_contract();
+        let proof = transfer_in_proof(vec![[1; 32]]);
+        let event = MapTransferOutEvent::from_log_entry_data(&proof.receipt.logs[0]).unwrap();
+        contract.record_claimable(&event);
+
+        set_env!(predecessor_account_id: map_cross_chain_service());
+        contract.claim(alice().0.to_string(), hex::encode([1; 32]));
+    }
+
+    #[test]
+    fn test_claim_by_recovery_account() {
+        let mut contract = mcs_contract();
+        let proof = transfer_in_proof(vec![[1; 32]]);
+        let event = MapTransferOutEvent::from_log_entry_data(&proof.receipt.logs[0]).unwrap();
+        contract.record_claimable(&event);
+
+        contract.set_recovery_account(Some(map_cross_chain_service()));
+        set_env!(predecessor_account_id: map_cross_chain_service());
+        contract.claim(alice().0.to_string(), hex::encode([1; 32]));
+        assert!(contract.get_claimables(alice().0.to_string(), 0, 10).is_empty());
+
+        // the token is transferred to the receiver, not to the recovery account
+        let ft_transfer_args = get_created_receipts().into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .find_map(|action| match action {
+                VmAction::FunctionCall { function_name, args, .. } if function_name == "ft_transfer" => Some(args),
+                _ => None,
+            })
+            .unwrap();
+        let args: serde_json::Value = serde_json::from_slice(&ft_transfer_args).unwrap();
+        assert_eq!(alice().0.as_str(), args["receiver_id"]);
+    }
+
+    #[test]
+    fn test_get_claimables() {
+        let mut contract = mcs_contract();
+        let proof = transfer_in_proof(vec![[1; 32], [2; 32], [3; 32]]);
+        for log in proof.receipt.logs.iter() {
+            contract.record_claimable(&MapTransferOutEvent::from_log_entry_data(log).unwrap());
+        }
+        assert_eq!(3, contract.get_claimables(alice().0.to_string(), 0, 10).len());
+        let claimables = contract.get_claimables(alice().0.to_string(), 1, 1);
+        assert_eq!(vec![hex::encode([2; 32])], claimables.into_iter().map(|(id, _)| id).collect::<Vec<_>>());
+        assert!(contract.get_claimables("bob.near".to_string(), 0, 10).is_empty());
+
+        set_env!(predecessor_account_id: alice().0, attached_deposit: env::storage_byte_cost() * 1000);
+        contract.claim(alice().0.to_string(), hex::encode([1; 32]));
+        assert_eq!(2, contract.get_claimables(alice().0.to_string(), 0, 10).len());
+    }
+
+    #[test]
+    fn test_transfer_in_call_mint_failed() {
+        let mut contract = mcs_contract();
+        let token = format!("{}.{}", mcs_token().0, map_cross_chain_service());
+        contract.mcs_tokens.insert(&token, &Default::default());
+        let proof = transfer_in_proof(vec![[1; 32]]);
+        let mut event = MapTransferOutEvent::from_log_entry_data(&proof.receipt.logs[0]).unwrap();
+        event.to_chain_token = token.into_bytes();
+        event.message = b"msg".to_vec();
+        contract.record_order_id(&[1; 32]);
+
+        // the event is queued for retry if minting the mcs token failed
+        set_promise_result(PromiseResult::Failed);
+        contract.finish_mint_transfer_in_call(&event);
+        assert!(contract.is_used_event(&[1; 32]));
+        assert_eq!(vec![event], contract.get_pending_transfers_in(0, 10));
+    }
+
+    #[test]
+    fn test_transfer_in_queued_if_deposit_short() {
+        let mut contract = mcs_contract();
+        let token = format!("{}.{}", mcs_token().0, map_cross_chain_service());
+        contract.mcs_tokens.insert(&token, &Default::default());
+        contract.mcs_storage_transfer_in_required = env::storage_byte_cost() * 10000;
+        let proof = transfer_in_proof(vec![[1; 32]]);
+        let mut event = MapTransferOutEvent::from_log_entry_data(&proof.receipt.logs[0]).unwrap();
+        event.to_chain_token = token.into_bytes();
+        let required_deposit = contract.record_order_id(&[1; 32]) + contract.pending_transfer_in_storage_cost(&event);
+        contract.remove_order_id(&[1; 32]);
+
+        // the deposit is not enough for recording the proof and queuing the event
+        contract.process_transfer_in(&event, required_deposit - 1);
+        assert!(!contract.is_used_event(&[1; 32]));
+        assert!(contract.get_pending_transfers_in(0, 10).is_empty());
+
+        // the deposit covers queuing the event, but not delivering the token and recording it as claimable
+        contract.process_transfer_in(&event, required_deposit);
+        assert!(contract.is_used_event(&[1; 32]));
+        assert_eq!(vec![event], contract.get_pending_transfers_in(0, 10));
+        assert!(contract.get_claimables(alice().0.to_string(), 0, 10).is_empty());
+    }
+
     #[test]
     fn test_deploy_mcs_token() {
         let mut contract = mcs_contract();
@@ -1255,6 +3335,65 @@ mod tests {
         assert!(contract.mcs_tokens.get(&account).is_some());
     }
 
+    #[test]
+    fn test_check_to_account() {
+        let mut contract = mcs_contract();
+        let chains = [(1, NearChain), (2, SolanaChain), (3, AptosChain), (4, BitcoinChain)];
+        for (chain_id, chain_type) in chains {
+            contract.set_chain_type(U128(chain_id), chain_type);
+        }
+
+        contract.check_to_account(alice().0.as_bytes().to_vec(), 1);
+        contract.check_to_account([1; 32].to_vec(), 2);
+        contract.check_to_account([1; 32].to_vec(), 3);
+        contract.check_to_account(b"bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".to_vec(), 4);
+        contract.check_to_account(b"bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0".to_vec(), 4);
+
+        contract.check_to_account(b"1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2".to_vec(), 4);
+        contract.check_to_account(b"3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy".to_vec(), 4);
+
+        let mainnet = BitcoinNetwork::Mainnet;
+        assert!(!is_valid_bitcoin_address(b"bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5", mainnet));
+        assert!(!is_valid_bitcoin_address(b"bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh", mainnet));
+        assert!(!is_valid_bitcoin_address(b"1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN3", mainnet));
+        assert!(!is_valid_bitcoin_address(alice().0.as_bytes(), mainnet));
+        // addresses of other networks
+        assert!(!is_valid_bitcoin_address(b"tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", mainnet));
+        assert!(!is_valid_bitcoin_address(b"ltc1qw508d6qejxtdg4y5r3zarvary0c5xw7kgmn4n9", mainnet));
+        assert!(!is_valid_bitcoin_address(b"mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn", mainnet));
+
+        contract.set_bitcoin_network(BitcoinNetwork::Testnet);
+        contract.check_to_account(b"tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".to_vec(), 4);
+        contract.check_to_account(b"mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn".to_vec(), 4);
+        contract.check_to_account(b"2MzQwSSnBHWHqSAqtTVQ6v47XtaisrJa1Vc".to_vec(), 4);
+        assert!(!is_valid_bitcoin_address(b"bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", BitcoinNetwork::Testnet));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid address for bitcoin chain type")]
+    fn test_fail_check_to_account_bitcoin_wrong_network() {
+        let mut contract = mcs_contract();
+        contract.set_chain_type(U128(4), BitcoinChain);
+        contract.check_to_account(b"tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".to_vec(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid account id for near chain type")]
+    fn test_fail_check_to_account_near_chain() {
+        let mut contract = mcs_contract();
+        contract.set_chain_type(U128(1), NearChain);
+        contract.check_to_account(b"Alice.near".to_vec(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "address length is incorrect for solana chain type")]
+    fn test_fail_check_to_account_solana_chain() {
+        let mut contract = mcs_contract();
+        contract.set_chain_type(U128(2), SolanaChain);
+        // the base58 text of the key is not accepted
+        contract.check_to_account(b"So11111111111111111111111111111111111111112".to_vec(), 2);
+    }
+
     #[test]
     #[should_panic]
     fn test_transfer_out_token_no_tochian() {
@@ -1,6 +1,7 @@
 use std::convert::From;
 use ethabi::{Event, EventParam, Hash, Log, ParamType, RawLog};
 use near_sdk::ext_contract;
+use near_sdk::json_types::U128;
 use map_light_client::proof::{ReceiptProof, LogEntry};
 
 pub type Address = [u8; 20];
@@ -15,7 +16,7 @@ pub fn validate_eth_address(address: String) -> Address {
 
 #[ext_contract(ext_map_light_client)]
 pub trait MapLightClient {
-    fn verify_proof_data(&self, receipt_proof: ReceiptProof);
+    fn verify_proof_data(&self, receipt_proof: ReceiptProof, chain_id: Option<U128>, strict: Option<bool>);
 }
 
 pub type EthEventParams = Vec<(String, ParamType, bool)>;
@@ -2,7 +2,7 @@ use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::{AccountId, env, Gas, near_bindgen, PanicOnDefault, Promise};
 // use num_bigint::BigInt as Integer;
 // use hex::FromHex;
-use near_sdk::json_types::Base64VecU8;
+use near_sdk::json_types::{Base64VecU8, U128};
 use near_sdk::serde::{Serialize,  Deserialize};
 // use near_sdk::serde::de::Error;
 
@@ -218,7 +218,7 @@ impl MapLightClient {
     //
     // }
 
-    pub fn verify_proof_data(&self, receipt_proof: ReceiptProof) {
+    pub fn verify_proof_data(&self, receipt_proof: ReceiptProof, _chain_id: Option<U128>, _strict: Option<bool>) {
         assert!(self.value);
     }
 
@@ -24,11 +24,26 @@ function printHelp() {
   echo "    remove_ft    <token> <chain id>          remove fungible token to_chain"
   echo "    upgrade_multisig  <wasm file>            upgrade multisig contract"
   echo "    upgrade_map_client  <wasm file>          upgrade map light client contract"
+  echo "    update_map_client_chain_id  <chain id> <new chain id>  update chain id of network tracked by map light client"
   echo "    upgrade_mcs  <wasm file>                 upgrade mcs contract"
   echo "    upgrade_mcs_token <token>  <wasm file>   upgrade mcs token contract"
   echo "    set_client  <map client account>         set new map light client account to mcs contract"
   echo "    set_owner  <multisig account>            set new multisig light client account to mcs contract"
   echo "    set_paused  <mask>                       set paused flag to mcs contract"
+  echo "    set_fee  <token> <chain id> <rate> <min> <max>  set fee rate in bps and min/max fee of token to_chain"
+  echo "    withdraw_fee  <token>                    withdraw accrued fee of token to fee receiver"
+  echo "    set_rate_limit  <token> <In|Out> <limit> <window>  set max amount of token transferred in/out in window seconds"
+  echo "    set_recovery_account  <account>       set account claiming failed transfer in on behalf of receiver"
+  echo "    remove_failed_message  <order id>     remove failed message which will not be retried"
+  echo "    set_map_client_verification_fee  <fee>  set verification fee of map light client in yoctoNEAR"
+  echo "    set_map_client_max_staleness  <nanoseconds>  set max time since last header before map light client refuses proofs"
+  echo "    add_map_client_fork  <height> <seal version> <signature scheme>  add consensus fork to map light client"
+  echo "    set_map_client_zk_verifying_key  <json file>  set groth16 verifying key of map light client"
+  echo "    set_map_client_restricted  <true|false>  set whether only consumers can verify proofs by map light client"
+  echo "    add_map_client_consumer  <account>     add consumer allowed to verify proofs by map light client"
+  echo "    remove_map_client_consumer  <account>  remove consumer of map light client"
+  echo "    set_mcs_verification_fee  <fee>       set verification fee mcs attaches to map light client in yoctoNEAR"
+  echo "    set_bitcoin_network  <Mainnet|Testnet>  set bitcoin network whose addresses are accepted by mcs contract"
   echo "  confirm <request id> <member>              confirm request"
   echo "  execute <request id> <account>             execute confirmed request"
   echo "  help                                       show help"
@@ -200,6 +215,18 @@ function prepare_request() {
         exit 1
       fi
       ;;
+    update_map_client_chain_id)
+      if [[ $# == 4 ]]; then
+        echo "update chain id $2 of map light client to $3"
+        RECEIVER=$CLIENT_ACCOUNT
+        METHOD="update_chain_id"
+        ARGS=`echo '{"chain_id": "'$2'", "new_chain_id": "'$3'"}'| base64`
+        MEMBER=$4
+      else
+        printHelp
+        exit 1
+      fi
+      ;;
     upgrade_mcs)
       if [[ $# == 3 ]]; then
         echo "upgrade mcs contract to $2"
@@ -262,6 +289,174 @@ function prepare_request() {
         exit 1
       fi
       ;;
+    set_fee)
+      if [[ $# == 7 ]]; then
+        echo "set fee of token $2 to_chain $3 to rate $4, min $5, max $6"
+        RECEIVER=$MCS_ACCOUNT
+        METHOD="set_fee"
+        ARGS=`echo '{"token": "'$2'", "to_chain": "'$3'", "fee": {"rate": '$4', "min": "'$5'", "max": "'$6'"}}'| base64`
+        MEMBER=$7
+      else
+        printHelp
+        exit 1
+      fi
+      ;;
+    withdraw_fee)
+      if [[ $# == 3 ]]; then
+        echo "withdraw accrued fee of token $2"
+        RECEIVER=$MCS_ACCOUNT
+        METHOD="withdraw_fee"
+        ARGS=`echo '{"token": "'$2'"}'| base64`
+        MEMBER=$3
+      else
+        printHelp
+        exit 1
+      fi
+      ;;
+    set_rate_limit)
+      if [[ $# == 6 ]]; then
+        echo "set rate limit of token $2 transferred $3 to $4 in $5 seconds"
+        RECEIVER=$MCS_ACCOUNT
+        METHOD="set_rate_limit"
+        ARGS=`echo '{"token": "'$2'", "direction": "'$3'", "rate_limit": {"limit": "'$4'", "window": '$5'}}'| base64`
+        MEMBER=$6
+      else
+        printHelp
+        exit 1
+      fi
+      ;;
+    set_recovery_account)
+      if [[ $# == 3 ]]; then
+        echo "set recovery account to $2"
+        RECEIVER=$MCS_ACCOUNT
+        METHOD="set_recovery_account"
+        ARGS=`echo '{"recovery_account": "'$2'"}'| base64`
+        MEMBER=$3
+      else
+        printHelp
+        exit 1
+      fi
+      ;;
+    remove_failed_message)
+      if [[ $# == 3 ]]; then
+        echo "remove failed message $2"
+        RECEIVER=$MCS_ACCOUNT
+        METHOD="remove_failed_message"
+        ARGS=`echo '{"order_id": "'$2'"}'| base64`
+        MEMBER=$3
+      else
+        printHelp
+        exit 1
+      fi
+      ;;
+    set_map_client_verification_fee)
+      if [[ $# == 3 ]]; then
+        echo "set verification fee of map light client to $2"
+        RECEIVER=$CLIENT_ACCOUNT
+        METHOD="set_verification_fee"
+        ARGS=`echo '{"fee": "'$2'"}'| base64`
+        MEMBER=$3
+      else
+        printHelp
+        exit 1
+      fi
+      ;;
+    set_map_client_max_staleness)
+      if [[ $# == 3 ]]; then
+        echo "set max staleness of map light client to $2"
+        RECEIVER=$CLIENT_ACCOUNT
+        METHOD="set_max_staleness"
+        ARGS=`echo '{"max_staleness": "'$2'"}'| base64`
+        MEMBER=$3
+      else
+        printHelp
+        exit 1
+      fi
+      ;;
+    add_map_client_fork)
+      if [[ $# == 5 ]]; then
+        echo "add fork of map light client at height $2"
+        RECEIVER=$CLIENT_ACCOUNT
+        METHOD="add_fork"
+        ARGS=`echo '{"fork": {"height": "'$2'", "seal_version": "'$3'", "signature_scheme": "'$4'"}}'| base64`
+        MEMBER=$5
+      else
+        printHelp
+        exit 1
+      fi
+      ;;
+    set_map_client_zk_verifying_key)
+      if [[ $# == 3 ]]; then
+        echo "set zk verifying key of map light client to $2"
+        RECEIVER=$CLIENT_ACCOUNT
+        METHOD="set_zk_verifying_key"
+        ARGS=`echo '{"verifying_key": '"$(cat $2)"'}'| base64`
+        MEMBER=$3
+      else
+        printHelp
+        exit 1
+      fi
+      ;;
+    set_map_client_restricted)
+      if [[ $# == 3 ]]; then
+        echo "set restricted of map light client to $2"
+        RECEIVER=$CLIENT_ACCOUNT
+        METHOD="set_restricted"
+        ARGS=`echo '{"restricted": '$2'}'| base64`
+        MEMBER=$3
+      else
+        printHelp
+        exit 1
+      fi
+      ;;
+    add_map_client_consumer)
+      if [[ $# == 3 ]]; then
+        echo "add consumer $2 of map light client"
+        RECEIVER=$CLIENT_ACCOUNT
+        METHOD="add_consumer"
+        ARGS=`echo '{"account_id": "'$2'"}'| base64`
+        MEMBER=$3
+      else
+        printHelp
+        exit 1
+      fi
+      ;;
+    remove_map_client_consumer)
+      if [[ $# == 3 ]]; then
+        echo "remove consumer $2 of map light client"
+        RECEIVER=$CLIENT_ACCOUNT
+        METHOD="remove_consumer"
+        ARGS=`echo '{"account_id": "'$2'"}'| base64`
+        MEMBER=$3
+      else
+        printHelp
+        exit 1
+      fi
+      ;;
+    set_mcs_verification_fee)
+      if [[ $# == 3 ]]; then
+        echo "set verification fee attached by mcs to $2"
+        RECEIVER=$MCS_ACCOUNT
+        METHOD="set_map_client_verification_fee"
+        ARGS=`echo '{"fee": "'$2'"}'| base64`
+        MEMBER=$3
+      else
+        printHelp
+        exit 1
+      fi
+      ;;
+    set_bitcoin_network)
+      if [[ $# == 3 ]]; then
+        echo "set bitcoin network to $2"
+        RECEIVER=$MCS_ACCOUNT
+        METHOD="set_bitcoin_network"
+        ARGS=`echo '{"bitcoin_network": "'$2'"}'| base64`
+        MEMBER=$3
+      else
+        printHelp
+        exit 1
+      fi
+      ;;
     help)
       printHelp
       ;;
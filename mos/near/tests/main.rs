@@ -373,7 +373,7 @@ async fn test_manage_map_relay_address() -> anyhow::Result<()> {
                                   wnear.id().to_string()).await?;
 
     let map_relay_address = "aaaa5a86411ab8627516cbb77d5db00b74fe610d";
-    let paused_mask = 1 << 1;
+    let paused_mask = 1 << 1 | 1 << 6;
 
     let ret = gen_call_transaction(&worker, &mcs, "get_map_relay_address", json!({}), false)
         .view()